@@ -0,0 +1,28 @@
+use anyhow::Result;
+use cdsapi::AsyncClient;
+use serde_json::json;
+use std::path::Path;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    // Same request as `era5_pressure_levels_geopotential`, driven by the async client.
+    // Run with: cargo run --example async_retrieve --features async
+    let client = AsyncClient::from_env()?;
+
+    let dataset = "reanalysis-era5-pressure-levels";
+    let request = json!({
+        "product_type": ["reanalysis"],
+        "variable": ["geopotential"],
+        "year": ["2024"],
+        "month": ["03"],
+        "day": ["01"],
+        "time": ["13:00"],
+        "pressure_level": ["1000"],
+        "data_format": "grib"
+    });
+
+    client
+        .retrieve(dataset, &request, Some(Path::new("download.grib")))
+        .await?;
+    Ok(())
+}
@@ -0,0 +1,251 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+use crate::error::{CdsError, Result};
+
+/// Geographic sub-area of a request, serialized as the `[north, west, south, east]` array
+/// the CDS expects.
+///
+/// Constructors validate the bounds, so an inverted box is rejected before submission
+/// instead of failing after hours in the queue. Longitudes may be given in `-180..=180` or
+/// `0..=360`; a box crossing the antimeridian is written with `east > 180`, e.g.
+/// `Area::new(60.0, 170.0, 50.0, 190.0)`.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(into = "[f64; 4]", try_from = "[f64; 4]")]
+pub struct Area {
+    north: f64,
+    west: f64,
+    south: f64,
+    east: f64,
+}
+
+impl Area {
+    /// The whole globe.
+    pub const GLOBAL: Area = Area::unchecked(90.0, -180.0, -90.0, 180.0);
+    pub const EUROPE: Area = Area::unchecked(72.0, -25.0, 34.0, 45.0);
+    pub const NORTH_AMERICA: Area = Area::unchecked(84.0, -170.0, 7.0, -50.0);
+    pub const SOUTH_AMERICA: Area = Area::unchecked(13.0, -82.0, -56.0, -34.0);
+    pub const AFRICA: Area = Area::unchecked(38.0, -18.0, -35.0, 52.0);
+    pub const ASIA: Area = Area::unchecked(82.0, 25.0, -11.0, 180.0);
+    pub const AUSTRALIA: Area = Area::unchecked(-10.0, 112.0, -44.0, 154.0);
+    /// North of the Arctic Circle.
+    pub const ARCTIC: Area = Area::unchecked(90.0, -180.0, 66.5, 180.0);
+    /// South of the Antarctic Circle.
+    pub const ANTARCTIC: Area = Area::unchecked(-66.5, -180.0, -90.0, 180.0);
+
+    const REGIONS: &'static [(&'static str, Area)] = &[
+        ("global", Area::GLOBAL),
+        ("europe", Area::EUROPE),
+        ("north_america", Area::NORTH_AMERICA),
+        ("south_america", Area::SOUTH_AMERICA),
+        ("africa", Area::AFRICA),
+        ("asia", Area::ASIA),
+        ("australia", Area::AUSTRALIA),
+        ("arctic", Area::ARCTIC),
+        ("antarctic", Area::ANTARCTIC),
+    ];
+
+    const fn unchecked(north: f64, west: f64, south: f64, east: f64) -> Self {
+        Self {
+            north,
+            west,
+            south,
+            east,
+        }
+    }
+
+    /// Creates an area from its bounds in degrees, in the CDS order.
+    pub fn new(north: f64, west: f64, south: f64, east: f64) -> Result<Self> {
+        let invalid = |msg: &str| {
+            Err(CdsError::Config(format!(
+                "invalid area [N={}, W={}, S={}, E={}]: {}",
+                north, west, south, east, msg
+            )))
+        };
+        if ![north, west, south, east].iter().all(|v| v.is_finite()) {
+            return invalid("bounds must be finite numbers");
+        }
+        if !(-90.0..=90.0).contains(&north) || !(-90.0..=90.0).contains(&south) {
+            return invalid("latitudes must be within -90..=90");
+        }
+        if !(-180.0..=360.0).contains(&west) || !(-180.0..=360.0).contains(&east) {
+            return invalid("longitudes must be within -180..=360");
+        }
+        if north < south {
+            return invalid("north is below south (bounds are [N, W, S, E])");
+        }
+        if west > east {
+            return invalid(
+                "west is greater than east (bounds are [N, W, S, E]; use east > 180 to cross the antimeridian)",
+            );
+        }
+        if east - west > 360.0 {
+            return invalid("the area spans more than 360 degrees of longitude");
+        }
+        Ok(Self::unchecked(north, west, south, east))
+    }
+
+    /// Smallest area containing two `(lat, lon)` corner points, given in any order.
+    pub fn from_corners(a: (f64, f64), b: (f64, f64)) -> Result<Self> {
+        Self::new(a.0.max(b.0), a.1.min(b.1), a.0.min(b.0), a.1.max(b.1))
+    }
+
+    /// Area extending `radius` degrees around the `(lat, lon)` point, clamped at the poles.
+    ///
+    /// Longitudes are wrapped so a box reaching past the antimeridian stays valid (written
+    /// with `east > 180`), and a radius of 180 degrees or more covers all longitudes.
+    pub fn around(lat: f64, lon: f64, radius: f64) -> Result<Self> {
+        let (mut west, mut east) = (lon - radius, lon + radius);
+        if east - west >= 360.0 {
+            (west, east) = (-180.0, 180.0);
+        } else if west < -180.0 {
+            (west, east) = (west + 360.0, east + 360.0);
+        } else if east > 360.0 {
+            (west, east) = (west - 360.0, east - 360.0);
+        }
+        Self::new(
+            (lat + radius).min(90.0),
+            west,
+            (lat - radius).max(-90.0),
+            east,
+        )
+    }
+
+    /// One of the named regions (`global`, `europe`, `north_america`, `south_america`,
+    /// `africa`, `asia`, `australia`, `arctic`, `antarctic`), case-insensitively.
+    pub fn region(name: &str) -> Option<Self> {
+        let name = name.trim().to_lowercase().replace(['-', ' '], "_");
+        Self::REGIONS
+            .iter()
+            .find(|(n, _)| *n == name)
+            .map(|(_, area)| *area)
+    }
+
+    pub fn north(&self) -> f64 {
+        self.north
+    }
+
+    pub fn west(&self) -> f64 {
+        self.west
+    }
+
+    pub fn south(&self) -> f64 {
+        self.south
+    }
+
+    pub fn east(&self) -> f64 {
+        self.east
+    }
+}
+
+impl From<Area> for [f64; 4] {
+    fn from(area: Area) -> Self {
+        [area.north, area.west, area.south, area.east]
+    }
+}
+
+impl TryFrom<[f64; 4]> for Area {
+    type Error = CdsError;
+
+    fn try_from([north, west, south, east]: [f64; 4]) -> Result<Self> {
+        Self::new(north, west, south, east)
+    }
+}
+
+/// Parses a region name or `N/W/S/E` bounds (also comma-separated), as used in MARS requests.
+impl FromStr for Area {
+    type Err = CdsError;
+
+    fn from_str(s: &str) -> Result<Self> {
+        if let Some(area) = Self::region(s) {
+            return Ok(area);
+        }
+        let bounds: Vec<f64> = s
+            .split(['/', ','])
+            .map(|v| v.trim().parse::<f64>())
+            .collect::<std::result::Result<_, _>>()
+            .map_err(|_| CdsError::Config(format!("invalid area {:?}", s)))?;
+        match bounds[..] {
+            [north, west, south, east] => Self::new(north, west, south, east),
+            _ => Err(CdsError::Config(format!(
+                "invalid area {:?}: expected a region name or N/W/S/E",
+                s
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for Area {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "{}/{}/{}/{}",
+            self.north, self.west, self.south, self.east
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bounds(area: Area) -> [f64; 4] {
+        area.into()
+    }
+
+    #[test]
+    fn new_validates_bounds() {
+        assert_eq!(
+            bounds(Area::new(60.0, -10.0, 50.0, 2.0).unwrap()),
+            [60.0, -10.0, 50.0, 2.0]
+        );
+        assert!(Area::new(60.0, 170.0, 50.0, 190.0).is_ok());
+        assert!(Area::new(50.0, -10.0, 60.0, 2.0).is_err());
+        assert!(Area::new(60.0, 2.0, 50.0, -10.0).is_err());
+        assert!(Area::new(91.0, 0.0, 50.0, 10.0).is_err());
+        assert!(Area::new(60.0, -181.0, 50.0, 10.0).is_err());
+        assert!(Area::new(60.0, -180.0, 50.0, 360.0).is_err());
+        assert!(Area::new(f64::NAN, 0.0, 50.0, 10.0).is_err());
+    }
+
+    #[test]
+    fn around_clamps_and_wraps() {
+        assert_eq!(
+            bounds(Area::around(50.0, 10.0, 5.0).unwrap()),
+            [55.0, 5.0, 45.0, 15.0]
+        );
+        assert_eq!(
+            bounds(Area::around(88.0, 0.0, 5.0).unwrap()),
+            [90.0, -5.0, 83.0, 5.0]
+        );
+        assert_eq!(
+            bounds(Area::around(0.0, -178.0, 5.0).unwrap()),
+            [5.0, 177.0, -5.0, 187.0]
+        );
+        assert_eq!(
+            bounds(Area::around(0.0, 0.0, 200.0).unwrap()),
+            [90.0, -180.0, -90.0, 180.0]
+        );
+    }
+
+    #[test]
+    fn parses_regions_and_bounds() {
+        assert_eq!("Europe".parse::<Area>().unwrap(), Area::EUROPE);
+        assert_eq!(
+            "north-america".parse::<Area>().unwrap(),
+            Area::NORTH_AMERICA
+        );
+        assert_eq!(
+            bounds("60/-10/50/2".parse().unwrap()),
+            [60.0, -10.0, 50.0, 2.0]
+        );
+        assert_eq!(
+            bounds("60, -10, 50, 2".parse().unwrap()),
+            [60.0, -10.0, 50.0, 2.0]
+        );
+        assert!("60/-10/50".parse::<Area>().is_err());
+        assert!("atlantis".parse::<Area>().is_err());
+        assert!("50/-10/60/2".parse::<Area>().is_err());
+    }
+}
@@ -0,0 +1,918 @@
+use reqwest::header::{ACCEPT_ENCODING, HeaderMap, HeaderValue, RANGE};
+use reqwest::{Client as HttpClient, Method, RequestBuilder, Response, StatusCode};
+use serde::Serialize;
+use serde::de::DeserializeOwned;
+use std::fmt;
+use std::future::Future;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::sync::mpsc::Sender;
+use std::time::{Duration, Instant};
+use tokio::io::AsyncWriteExt;
+
+use crate::async_job::AsyncJob;
+use crate::client::{HttpOptions, RemoteFile, parse_header};
+use crate::config::load_config;
+use crate::error::{CdsError, Result};
+use crate::error::{ErrorResponse, error_from_response};
+use crate::events::CdsEvent;
+use crate::job::{JobKind, Submission};
+use crate::legacy::{ApiReply, LegacyBase};
+use crate::normalize::normalize_request;
+use crate::processing::ProcessingJob;
+use crate::progress::{ProgressReporter, Reporter};
+use crate::protocol::{self, HttpRequest, HttpResponse, header_pairs};
+use crate::retry::{RetryPolicy, Stall};
+use crate::util::{
+    content_disposition_filename, default_headers, guess_filename_from_url, retry_after,
+    split_key_basic,
+};
+use crate::wire::WireLog;
+
+/// Asynchronous counterpart of [`Client`](crate::Client).
+///
+/// Available with the `async` cargo feature. Sleeping between polls and retries uses
+/// `tokio::time`, so calls must run inside a Tokio runtime.
+#[derive(Debug, Clone)]
+pub struct AsyncClient {
+    url: String,
+    pub(crate) key: String,
+
+    timeout: Duration,
+    http_options: HttpOptions,
+    pub(crate) retry: RetryPolicy,
+    pub(crate) download_retry: RetryPolicy,
+    pub(crate) poll_min: Duration,
+    pub(crate) poll_max: Duration,
+    wait_until_complete: bool,
+    pub(crate) progress: bool,
+    pub(crate) reporter: Reporter,
+    normalize: bool,
+    events: Option<Sender<CdsEvent>>,
+    headers: HeaderMap,
+    wire: Option<Arc<WireLog>>,
+    legacy_base: Arc<LegacyBase>,
+    quiet: bool,
+    debug: bool,
+
+    pub(crate) http: HttpClient,
+}
+
+impl AsyncClient {
+    /// Creates a client using environment variables and/or `.cdsapirc`.
+    ///
+    /// This is equivalent to `AsyncClient::new(None, None, None)`.
+    pub fn from_env() -> Result<Self> {
+        Self::new(None, None, None)
+    }
+
+    /// Creates a client using the same configuration precedence as [`Client::new`](crate::Client::new).
+    pub fn new(url: Option<String>, key: Option<String>, verify: Option<bool>) -> Result<Self> {
+        let (cfg, settings) = load_config(url, key, verify, None)?;
+
+        let mut http_options = HttpOptions::new(&cfg)?;
+        http_options.apply_settings(&settings);
+        let http = build_http(&http_options)?;
+
+        let mut client = Self {
+            url: cfg.url,
+            key: cfg.key,
+            timeout: Duration::from_secs(settings.timeout.unwrap_or(60)),
+            http_options,
+            retry: RetryPolicy::default(),
+            download_retry: RetryPolicy::for_downloads(),
+            poll_min: Duration::from_secs(1),
+            poll_max: Duration::from_secs(120),
+            wait_until_complete: settings.wait_until_complete.unwrap_or(true),
+            progress: settings.progress.unwrap_or(true),
+            reporter: Reporter::default(),
+            normalize: true,
+            events: None,
+            headers: HeaderMap::new(),
+            wire: None,
+            legacy_base: Arc::default(),
+            quiet: cfg.quiet,
+            debug: cfg.debug,
+            http,
+        };
+        if let Some(retry_max) = settings.retry_max {
+            client = client.with_retry_max(retry_max);
+        }
+        if let Some(secs) = settings.sleep_max {
+            client = client.with_sleep_max(Duration::from_secs(secs));
+        }
+        if let Some(secs) = settings.retry_max_elapsed {
+            client = client.with_retry_max_elapsed(Duration::from_secs(secs));
+        }
+        if settings.poll_min.is_some() || settings.poll_max.is_some() {
+            let min = settings
+                .poll_min
+                .map_or(client.poll_min, Duration::from_secs);
+            let max = settings
+                .poll_max
+                .map_or(client.poll_max, Duration::from_secs);
+            client = client.with_poll_interval(min, max);
+        }
+        if let Some(attempts) = settings.download_retry_max {
+            client.download_retry = client.download_retry.with_max_attempts(attempts);
+        }
+        if let Some(secs) = settings.download_retry_sleep_max {
+            client.download_retry = client
+                .download_retry
+                .with_max_delay(Duration::from_secs(secs));
+        }
+        if let Some(secs) = settings.download_retry_max_elapsed {
+            client.download_retry = client
+                .download_retry
+                .with_max_elapsed(Duration::from_secs(secs));
+        }
+        Ok(client)
+    }
+
+    /// Sets the total timeout of each API call; downloads are only bound by the connect
+    /// and read timeouts.
+    pub fn with_timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Sends API calls and downloads through `proxy` instead of the proxy from the environment.
+    pub fn with_proxy(mut self, proxy: &str) -> Result<Self> {
+        self.http_options.proxy = Some(proxy.to_string());
+        self.http = build_http(&self.http_options)?;
+        Ok(self)
+    }
+
+    /// Sends the header `name: value` with every API call and download.
+    pub fn with_default_header(mut self, name: &str, value: &str) -> Result<Self> {
+        let (name, value) = parse_header(name, value)?;
+        self.headers.append(name, value);
+        Ok(self)
+    }
+
+    /// Appends every HTTP request and response as JSON lines to the file at `path`; see
+    /// [`Client::with_wire_log`](crate::Client::with_wire_log).
+    pub fn with_wire_log(mut self, path: &Path) -> Result<Self> {
+        self.wire = Some(Arc::new(WireLog::open(path)?));
+        Ok(self)
+    }
+
+    /// Sends all traffic through `http` instead of a client built from the configuration.
+    ///
+    /// Builders changing HTTP settings (proxy, timeouts) replace it again, so call this last.
+    pub fn with_http_client(mut self, http: HttpClient) -> Self {
+        self.http = http;
+        self
+    }
+
+    /// Sets the timeout for establishing connections (default 30s).
+    pub fn with_connect_timeout(mut self, timeout: Duration) -> Result<Self> {
+        self.http_options.connect_timeout = timeout;
+        self.http = build_http(&self.http_options)?;
+        Ok(self)
+    }
+
+    /// Sets how long to wait for a response, or for the next chunk of a download, before
+    /// the attempt fails and is retried (default 60s).
+    pub fn with_read_timeout(mut self, timeout: Duration) -> Result<Self> {
+        self.http_options.read_timeout = timeout;
+        self.http = build_http(&self.http_options)?;
+        Ok(self)
+    }
+
+    /// Sets how long an unused connection is kept open for reuse (default 90s; `None`
+    /// keeps it until the server closes it).
+    pub fn with_pool_idle_timeout(mut self, timeout: Option<Duration>) -> Result<Self> {
+        self.http_options.pool_idle_timeout = timeout;
+        self.http = build_http(&self.http_options)?;
+        Ok(self)
+    }
+
+    /// Sets how many unused connections per host are kept open for reuse (default
+    /// unlimited).
+    pub fn with_pool_max_idle_per_host(mut self, max: usize) -> Result<Self> {
+        self.http_options.pool_max_idle_per_host = max;
+        self.http = build_http(&self.http_options)?;
+        Ok(self)
+    }
+
+    /// Sets the interval of TCP keepalive probes (default 15s; `None` disables them).
+    pub fn with_tcp_keepalive(mut self, interval: Option<Duration>) -> Result<Self> {
+        self.http_options.tcp_keepalive = interval;
+        self.http = build_http(&self.http_options)?;
+        Ok(self)
+    }
+
+    /// Shorthand for setting [`RetryPolicy::with_max_attempts`] on the API call policy.
+    pub fn with_retry_max(mut self, retry_max: usize) -> Self {
+        self.retry = self.retry.with_max_attempts(retry_max);
+        self
+    }
+
+    /// Shorthand for setting [`RetryPolicy::with_max_elapsed`] on the API call policy, so a
+    /// dead endpoint fails after `budget` rather than after all attempts.
+    pub fn with_retry_max_elapsed(mut self, budget: Duration) -> Self {
+        self.retry = self.retry.with_max_elapsed(budget);
+        self
+    }
+
+    /// Sets the longest interval between status polls, and a constant delay between API call
+    /// retries.
+    pub fn with_sleep_max(mut self, sleep_max: Duration) -> Self {
+        self.poll_max = sleep_max;
+        self.retry.set_sleep(sleep_max);
+        self
+    }
+
+    /// Polls job status every `min` at first, backing off by 1.5x up to `max`.
+    ///
+    /// Defaults to 1s..120s. Use a small range for fast feedback in CI, or a large one to
+    /// be gentle on shared accounts.
+    pub fn with_poll_interval(mut self, min: Duration, max: Duration) -> Self {
+        self.poll_min = min.max(Duration::from_millis(10));
+        self.poll_max = max.max(self.poll_min);
+        self
+    }
+
+    /// Polls job status at a fixed interval, without backoff.
+    pub fn with_fixed_poll_interval(self, interval: Duration) -> Self {
+        self.with_poll_interval(interval, interval)
+    }
+
+    /// Replaces the retry policy for API calls (submission, status polls, result lookups).
+    ///
+    /// Polling intervals are set with `with_poll_interval`, and download resumes follow
+    /// `with_download_retry_policy`.
+    pub fn with_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.retry = policy;
+        self
+    }
+
+    /// Replaces the retry policy for download requests and for resuming interrupted
+    /// downloads (default [`RetryPolicy::for_downloads`]).
+    pub fn with_download_retry_policy(mut self, policy: RetryPolicy) -> Self {
+        self.download_retry = policy;
+        self
+    }
+
+    pub fn with_wait_until_complete(mut self, wait: bool) -> Self {
+        self.wait_until_complete = wait;
+        self
+    }
+
+    pub fn with_progress(mut self, progress: bool) -> Self {
+        self.progress = progress;
+        self
+    }
+
+    /// Suppresses informational messages (request state changes) on stderr.
+    pub fn with_quiet(mut self, quiet: bool) -> Self {
+        self.quiet = quiet;
+        self
+    }
+
+    /// Prints HTTP calls, retries and download resumes to stderr.
+    pub fn with_debug(mut self, debug: bool) -> Self {
+        self.debug = debug;
+        self
+    }
+
+    /// Whether to [normalize](crate::normalize_request) requests before submission
+    /// (default `true`).
+    pub fn with_request_normalization(mut self, normalize: bool) -> Self {
+        self.normalize = normalize;
+        self
+    }
+
+    /// Routes download progress to `reporter` instead of the default progress bar.
+    pub fn with_progress_reporter(mut self, reporter: impl ProgressReporter + 'static) -> Self {
+        self.reporter = Reporter(Arc::new(reporter));
+        self.progress = true;
+        self
+    }
+
+    /// Sends [`CdsEvent`]s to `sink` as requests are submitted, polled and downloaded.
+    ///
+    /// See [`Client::with_event_sink`](crate::Client::with_event_sink).
+    pub fn with_event_sink(mut self, sink: Sender<CdsEvent>) -> Self {
+        self.events = Some(sink);
+        self
+    }
+
+    /// Submits a request and downloads the resulting file.
+    ///
+    /// See [`Client::retrieve`](crate::Client::retrieve).
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(dataset = %dataset)))]
+    pub async fn retrieve<T: Serialize>(
+        &self,
+        dataset: &str,
+        request: &T,
+        target: Option<&Path>,
+    ) -> Result<RemoteFile> {
+        self.retrieve_job(dataset, request, target, self.wait_until_complete)
+            .await?
+            .into_file()
+    }
+
+    /// Submits a request without waiting for it to complete.
+    ///
+    /// See [`Client::retrieve_nowait`](crate::Client::retrieve_nowait); a pending job comes
+    /// back as an [`AsyncJob`].
+    pub async fn retrieve_nowait<T: Serialize>(
+        &self,
+        dataset: &str,
+        request: &T,
+        target: Option<&Path>,
+    ) -> Result<Submission<AsyncJob>> {
+        self.retrieve_job(dataset, request, target, false).await
+    }
+
+    /// Submits one job, waits for it if `wait` is set, and downloads it once finished.
+    async fn retrieve_job<T: Serialize>(
+        &self,
+        dataset: &str,
+        request: &T,
+        target: Option<&Path>,
+        wait: bool,
+    ) -> Result<Submission<AsyncJob>> {
+        let job = match self.submit(dataset, request).await {
+            Ok(job) => job,
+            Err(e) => {
+                self.emit(|| CdsEvent::Failed {
+                    job_id: None,
+                    message: e.to_string(),
+                });
+                return Err(e);
+            }
+        };
+        let result = async {
+            let file = if wait {
+                job.wait().await?
+            } else {
+                match job.check().await? {
+                    Submission::Completed(file) => file,
+                    pending => return Ok(pending),
+                }
+            };
+            if let Some(target) = target {
+                self.download(&file, target).await?;
+            }
+            Ok::<_, CdsError>(Submission::Completed(file))
+        }
+        .await;
+        let result = match result {
+            Ok(pending @ Submission::Pending { .. }) => return Ok(pending),
+            Ok(Submission::Completed(file)) => Ok(file),
+            Err(e) => Err(e),
+        };
+        self.emit(|| match &result {
+            Ok(file) => CdsEvent::Completed {
+                job_id: job.id().to_string(),
+                file: file.clone(),
+                path: target.map(Path::to_path_buf),
+            },
+            Err(e) => CdsEvent::Failed {
+                job_id: Some(job.id().to_string()),
+                message: e.to_string(),
+            },
+        });
+        result.map(Submission::Completed)
+    }
+
+    /// Submits a request and returns a handle to the job without waiting.
+    ///
+    /// See [`Client::submit`](crate::Client::submit).
+    pub async fn submit<T: Serialize>(&self, dataset: &str, request: &T) -> Result<AsyncJob> {
+        let mut request = serde_json::to_value(request)
+            .map_err(|e| CdsError::json("failed to serialize request", e))?;
+        if self.normalize {
+            normalize_request(&mut request);
+        }
+        if split_key_basic(&self.key).is_some() {
+            let (base_url, reply) = self.post_with_base_fallback(dataset, &request).await?;
+            let id = reply.request_id.clone().unwrap_or_default();
+            self.emit(|| CdsEvent::Submitted {
+                job_id: id.clone(),
+                dataset: dataset.to_string(),
+            });
+            let kind = JobKind::Legacy {
+                base_url,
+                reply: Some(reply),
+            };
+            return Ok(AsyncJob::new(self.clone(), id, kind));
+        }
+
+        let retrieve_base = protocol::retrieve_base(&self.url);
+        let exec_url = protocol::execution_url(&retrieve_base, dataset);
+        let submit_body = protocol::execution_body(&request);
+        let job: ProcessingJob = self.api_json("POST", &exec_url, &submit_body).await?;
+
+        let monitor_url = protocol::monitor_url(&job, &retrieve_base)?;
+        let id = job.job_id.clone().unwrap_or_else(|| {
+            monitor_url
+                .rsplit('/')
+                .next()
+                .unwrap_or_default()
+                .to_string()
+        });
+        self.emit(|| CdsEvent::Submitted {
+            job_id: id.clone(),
+            dataset: dataset.to_string(),
+        });
+        Ok(AsyncJob::new(
+            self.clone(),
+            id,
+            JobKind::Processing { monitor_url },
+        ))
+    }
+
+    /// Recreates a handle to an existing job from its ID.
+    ///
+    /// See [`Client::job`](crate::Client::job).
+    pub fn job(&self, id: &str) -> AsyncJob {
+        let kind = if split_key_basic(&self.key).is_some() {
+            JobKind::Legacy {
+                base_url: self.legacy_base.get(self.url.trim_end_matches('/')),
+                reply: None,
+            }
+        } else {
+            JobKind::Processing {
+                monitor_url: protocol::job_url(&protocol::retrieve_base(&self.url), id),
+            }
+        };
+        AsyncJob::new(self.clone(), id.to_string(), kind)
+    }
+
+    async fn post_with_base_fallback<T: Serialize>(
+        &self,
+        dataset: &str,
+        request: &T,
+    ) -> Result<(String, ApiReply)> {
+        let configured = self.url.trim_end_matches('/');
+        let base = self.legacy_base.get(configured);
+        let e = match self.post_legacy(&base, dataset, request).await {
+            Ok(reply) => return Ok((base, reply)),
+            Err(e) => e,
+        };
+        let Some(alt) = self.legacy_base.fallback_for(configured, &e) else {
+            return Err(e);
+        };
+        self.log_debug(format_args!("{} answered 404, trying {}", base, alt));
+        let result = self.post_legacy(&alt, dataset, request).await;
+        self.legacy_base.resolve(configured, e, alt, result)
+    }
+
+    async fn post_legacy<T: Serialize>(
+        &self,
+        base: &str,
+        dataset: &str,
+        request: &T,
+    ) -> Result<ApiReply> {
+        let url = format!("{}/resources/{}", base, dataset);
+        self.api_json::<T, ApiReply>("POST", &url, request).await
+    }
+
+    /// Downloads `file` to `target`, resuming partial downloads with HTTP range requests.
+    ///
+    /// If `target` is a directory or empty, the file is named after the server's
+    /// `Content-Disposition` header or else the URL.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(location = %file.location, size = file.content_length)))]
+    pub async fn download(&self, file: &RemoteFile, target: &Path) -> Result<PathBuf> {
+        let target = if target.as_os_str().is_empty() || target.is_dir() {
+            let from_header = match self
+                .send(
+                    self.apply_auth(
+                        self.http
+                            .head(&file.location)
+                            .header(ACCEPT_ENCODING, "identity"),
+                    ),
+                )
+                .await
+            {
+                Ok(resp) if resp.status().is_success() => {
+                    content_disposition_filename(resp.headers())
+                }
+                _ => None,
+            };
+            let name = from_header
+                .or_else(|| guess_filename_from_url(&file.location))
+                .unwrap_or_else(|| "download".to_string());
+            target.join(name)
+        } else {
+            target.to_path_buf()
+        };
+
+        if let Some(parent) = target.parent() {
+            if !parent.as_os_str().is_empty() {
+                tokio::fs::create_dir_all(parent).await.map_err(|e| {
+                    CdsError::io(
+                        format!("failed to create directory {}", parent.display()),
+                        e,
+                    )
+                })?;
+            }
+        }
+
+        let mut downloaded: u64 = 0;
+        let mut mode_append = false;
+        let mut range_from: Option<u64> = None;
+
+        if let Ok(meta) = tokio::fs::metadata(&target).await {
+            downloaded = meta.len();
+            if downloaded < file.content_length {
+                mode_append = true;
+                range_from = Some(downloaded);
+            }
+        }
+
+        let pb = self.progress.then(|| self.reporter.0.clone());
+        if let Some(pb) = &pb {
+            pb.started(&file.location, file.content_length, downloaded);
+        }
+
+        let mut tries = 0usize;
+        let mut stall = Stall::new(downloaded);
+        'download_attempt: while tries < self.download_retry.max_attempts() {
+            let mut resp = self.open_download(file, range_from).await?;
+            if range_from.is_some() && resp.status() != StatusCode::PARTIAL_CONTENT {
+                // The server ignored the range and sends the whole file: start over.
+                self.log_debug(format_args!(
+                    "range request ignored, restarting {}",
+                    target.display()
+                ));
+                downloaded = 0;
+                mode_append = false;
+                if let Some(pb) = &pb {
+                    pb.resumed(&file.location, 0);
+                }
+            }
+            let mut out = tokio::fs::OpenOptions::new()
+                .create(true)
+                .write(true)
+                .append(mode_append)
+                .truncate(!mode_append)
+                .open(&target)
+                .await
+                .map_err(|e| CdsError::io(format!("failed to open {}", target.display()), e))?;
+
+            loop {
+                let chunk = match resp.chunk().await {
+                    Ok(Some(chunk)) => chunk,
+                    Ok(None) => break,
+                    Err(e) => {
+                        tries += 1;
+                        let delay = self.download_retry.jittered_delay(tries);
+                        if self
+                            .download_retry
+                            .exhausted(tries, stall.since(downloaded), delay)
+                        {
+                            return Err(CdsError::download(
+                                "download interrupted",
+                                Some(Box::new(e)),
+                            ));
+                        }
+
+                        self.log_debug(format_args!(
+                            "download interrupted at {} byte(s): {}, resuming",
+                            downloaded, e
+                        ));
+                        // resume
+                        out.flush().await.ok();
+                        downloaded = tokio::fs::metadata(&target).await?.len();
+                        range_from = Some(downloaded);
+                        mode_append = true;
+                        if let Some(pb) = &pb {
+                            pb.resumed(&file.location, downloaded);
+                        }
+                        tokio::time::sleep(delay).await;
+                        continue 'download_attempt;
+                    }
+                };
+
+                out.write_all(&chunk).await?;
+                #[cfg(feature = "tracing")]
+                tracing::trace!(bytes = chunk.len(), "download chunk");
+                downloaded += chunk.len() as u64;
+                if let Some(pb) = &pb {
+                    pb.advanced(&file.location, chunk.len() as u64);
+                }
+                self.emit(|| CdsEvent::DownloadProgress {
+                    location: file.location.clone(),
+                    bytes: chunk.len() as u64,
+                    total: file.content_length,
+                });
+            }
+
+            out.flush().await?;
+
+            if downloaded >= file.content_length {
+                if let Some(pb) = &pb {
+                    pb.finished(&file.location);
+                }
+                return Ok(target);
+            }
+
+            tries += 1;
+            let delay = self.download_retry.jittered_delay(tries);
+            if self
+                .download_retry
+                .exhausted(tries, stall.since(downloaded), delay)
+            {
+                break;
+            }
+            // resume and retry
+            downloaded = tokio::fs::metadata(&target).await?.len();
+            range_from = Some(downloaded);
+            mode_append = true;
+            if let Some(pb) = &pb {
+                pb.resumed(&file.location, downloaded);
+            }
+            tokio::time::sleep(delay).await;
+        }
+
+        Err(CdsError::download(
+            format!(
+                "download failed: downloaded {} byte(s) out of {}",
+                downloaded, file.content_length
+            ),
+            None,
+        ))
+    }
+
+    /// Starts a `GET` of `file`, from byte `from` onwards if given.
+    pub(crate) async fn open_download(
+        &self,
+        file: &RemoteFile,
+        from: Option<u64>,
+    ) -> Result<Response> {
+        let mut headers = HeaderMap::new();
+        headers.insert(ACCEPT_ENCODING, HeaderValue::from_static("identity"));
+        if let Some(from) = from {
+            let range = HeaderValue::from_str(&format!("bytes={}-", from))
+                .map_err(|e| CdsError::Protocol(e.to_string()))?;
+            headers.insert(RANGE, range);
+        }
+
+        let resp = self
+            .robust_request(&self.download_retry, || {
+                let req = self.http.get(&file.location).headers(headers.clone());
+                self.send(self.apply_auth(req))
+            })
+            .await?;
+
+        if !resp.status().is_success() {
+            let response = ErrorResponse {
+                status: resp.status(),
+                url: file.location.clone(),
+                headers: resp.headers().clone(),
+                body: resp.text().await.unwrap_or_default(),
+            };
+            return Err(CdsError::download(
+                "download request failed",
+                Some(Box::new(error_from_response(response))),
+            ));
+        }
+        Ok(resp)
+    }
+
+    pub(crate) fn emit(&self, event: impl FnOnce() -> CdsEvent) {
+        if let Some(sink) = &self.events {
+            let _ = sink.send(event());
+        }
+    }
+
+    /// Informational message; a `tracing` INFO event when the `tracing` feature is enabled.
+    pub(crate) fn log_info(&self, args: fmt::Arguments<'_>) {
+        #[cfg(feature = "tracing")]
+        tracing::info!("{}", args);
+        #[cfg(not(feature = "tracing"))]
+        if !self.quiet {
+            eprintln!("{}", args);
+        }
+    }
+
+    /// Debug message; a `tracing` DEBUG event when the `tracing` feature is enabled.
+    pub(crate) fn log_debug(&self, args: fmt::Arguments<'_>) {
+        #[cfg(feature = "tracing")]
+        tracing::debug!("{}", args);
+        #[cfg(not(feature = "tracing"))]
+        if self.debug {
+            eprintln!("DEBUG {}", args);
+        }
+    }
+
+    pub(crate) fn apply_auth(&self, req: RequestBuilder) -> RequestBuilder {
+        let req = req.headers(self.headers.clone());
+        if let Some((u, p)) = split_key_basic(&self.key) {
+            req.basic_auth(u, Some(p))
+        } else {
+            req.header("PRIVATE-TOKEN", self.key.trim())
+        }
+    }
+
+    #[cfg_attr(feature = "tracing", tracing::instrument(level = "debug", skip_all, fields(method = %method, url = %url)))]
+    pub(crate) async fn api_json<TReq: Serialize, TResp: DeserializeOwned>(
+        &self,
+        method: &str,
+        url: &str,
+        request: &TReq,
+    ) -> Result<TResp> {
+        let resp = self
+            .robust_request(&self.retry, || {
+                let req = match method {
+                    "GET" => self.http.get(url),
+                    "PUT" => self.http.put(url),
+                    _ => self.http.post(url),
+                };
+                let req = self.apply_auth(req).timeout(self.timeout);
+                if method == "GET" {
+                    self.send(req)
+                } else {
+                    self.send(req.json(request))
+                }
+            })
+            .await?;
+
+        let status = resp.status();
+        self.log_debug(format_args!("{} {} -> {}", method, url, status));
+        let headers = resp.headers().clone();
+        let text = resp.text().await.unwrap_or_default();
+        if WireLog::enabled(self.wire.as_deref()) {
+            WireLog::response_body(self.wire.as_deref(), url, &text);
+        }
+        if !status.is_success() {
+            return Err(error_from_response(ErrorResponse {
+                status,
+                url: url.to_string(),
+                headers,
+                body: text,
+            }));
+        }
+
+        serde_json::from_str::<TResp>(&text).map_err(|e| {
+            CdsError::json(
+                format!("failed to parse API JSON (url={}, status={})", url, status),
+                e,
+            )
+        })
+    }
+
+    /// Sends a request of a [`Retrieval`](protocol::Retrieval) with this client's headers,
+    /// authentication and retries, and returns the response whatever its status.
+    pub(crate) async fn send_protocol(&self, request: &HttpRequest) -> Result<HttpResponse> {
+        let method = Method::from_bytes(request.method.as_bytes())
+            .map_err(|_| CdsError::Protocol(format!("invalid method {}", request.method)))?;
+        let resp = self
+            .robust_request(&self.retry, || {
+                let mut req = self.http.request(method.clone(), &request.url);
+                for (name, value) in &request.headers {
+                    // The token is added by apply_auth.
+                    if !name.eq_ignore_ascii_case("PRIVATE-TOKEN") {
+                        req = req.header(name, value);
+                    }
+                }
+                if let Some(body) = &request.body {
+                    req = req.body(body.clone());
+                }
+                self.send(self.apply_auth(req).timeout(self.timeout))
+            })
+            .await?;
+
+        let status = resp.status();
+        self.log_debug(format_args!(
+            "{} {} -> {}",
+            request.method, request.url, status
+        ));
+        let headers = header_pairs(resp.headers());
+        let body = resp
+            .bytes()
+            .await
+            .map_err(|e| CdsError::transport(format!("failed to read {}", request.url), e))?;
+        if WireLog::enabled(self.wire.as_deref()) {
+            WireLog::response_body(
+                self.wire.as_deref(),
+                &request.url,
+                &String::from_utf8_lossy(&body),
+            );
+        }
+        Ok(HttpResponse {
+            status: status.as_u16(),
+            headers,
+            body: body.to_vec(),
+        })
+    }
+
+    /// Sends `req`, recording the exchange in the wire log if enabled.
+    pub(crate) async fn send(
+        &self,
+        req: RequestBuilder,
+    ) -> std::result::Result<Response, reqwest::Error> {
+        let wire = self.wire.as_deref();
+        if !WireLog::enabled(wire) {
+            return req.send().await;
+        }
+        let (http, request) = req.build_split();
+        let request = request?;
+        let url = request.url().to_string();
+        WireLog::request(
+            wire,
+            request.method(),
+            &url,
+            request.headers(),
+            request.body().and_then(|b| b.as_bytes()),
+        );
+        let result = http.execute(request).await;
+        match &result {
+            Ok(resp) => WireLog::response(wire, &url, resp.status(), resp.headers()),
+            Err(e) => WireLog::error(wire, &url, e),
+        }
+        result
+    }
+
+    /// Sends the request built by `f`, retrying connection errors and retriable statuses
+    /// as `policy` allows.
+    pub(crate) async fn robust_request<F, Fut>(
+        &self,
+        policy: &RetryPolicy,
+        mut f: F,
+    ) -> Result<Response>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = std::result::Result<Response, reqwest::Error>>,
+    {
+        let mut tries = 0usize;
+        let started = Instant::now();
+        loop {
+            match f().await {
+                Ok(resp) => {
+                    if policy.is_retriable(resp.status()) {
+                        tries += 1;
+                        // Honor the server's Retry-After (429/503) over the policy delay.
+                        let delay = retry_after(resp.headers())
+                            .unwrap_or_else(|| policy.jittered_delay(tries));
+                        if policy.exhausted(tries, started, delay) {
+                            return Ok(resp);
+                        }
+                        self.log_debug(format_args!(
+                            "HTTP {} (attempt {}/{}), retrying in {:?}",
+                            resp.status(),
+                            tries,
+                            policy.max_attempts(),
+                            delay
+                        ));
+                        tokio::time::sleep(delay).await;
+                        continue;
+                    }
+                    return Ok(resp);
+                }
+                Err(err) => {
+                    tries += 1;
+                    let delay = policy.jittered_delay(tries);
+                    if policy.exhausted(tries, started, delay) {
+                        return Err(CdsError::transport("could not connect", err));
+                    }
+                    self.log_debug(format_args!(
+                        "request failed (attempt {}/{}): {}, retrying in {:?}",
+                        tries,
+                        policy.max_attempts(),
+                        err,
+                        delay
+                    ));
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+}
+
+fn build_http(options: &HttpOptions) -> Result<HttpClient> {
+    let mut builder = HttpClient::builder()
+        .default_headers(default_headers())
+        .connect_timeout(options.connect_timeout)
+        .read_timeout(options.read_timeout)
+        .pool_idle_timeout(options.pool_idle_timeout)
+        .pool_max_idle_per_host(options.pool_max_idle_per_host)
+        .tcp_keepalive(options.tcp_keepalive);
+
+    if let Some(proxy) = options.proxy()? {
+        builder = builder.proxy(proxy);
+    }
+    #[cfg(any(feature = "rustls", feature = "native-tls"))]
+    {
+        if !options.verify {
+            builder = builder.danger_accept_invalid_certs(true);
+        }
+        for cert in options.root_certificates()? {
+            builder = builder.add_root_certificate(cert);
+        }
+        if let Some(identity) = options.identity()? {
+            builder = builder.identity(identity);
+        }
+    }
+    #[cfg(not(any(feature = "rustls", feature = "native-tls")))]
+    options.require_no_tls_settings()?;
+
+    builder
+        .build()
+        .map_err(|e| CdsError::transport("failed to build HTTP client", e))
+}
@@ -0,0 +1,341 @@
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use crate::async_client::AsyncClient;
+use crate::client::RemoteFile;
+use crate::error::{CdsError, ErrorResponse, Result, error_from_response};
+use crate::events::CdsEvent;
+use crate::job::{JobKind, JobProgress, JobStatus, Submission};
+use crate::legacy::{ApiReply, failure_from_reply, remote_file_from_reply};
+use crate::processing::{ProcessingJobStatus, ProcessingResults};
+use crate::protocol::{Action, Retrieval, results_url, status_url};
+use crate::util::backoff;
+
+impl Submission<AsyncJob> {
+    /// The result file, or [`CdsError::Pending`] if the job has not finished.
+    pub(crate) fn into_file(self) -> Result<RemoteFile> {
+        match self {
+            Self::Completed(file) => Ok(file),
+            Self::Pending { job, status } => Err(CdsError::Pending {
+                job_id: job.id,
+                status,
+            }),
+        }
+    }
+}
+
+/// Handle to a request submitted with [`AsyncClient::submit`]; the asynchronous
+/// counterpart of [`Job`](crate::Job).
+///
+/// Like `Job`, it can be recreated later from a persisted ID with [`AsyncClient::job`].
+#[derive(Debug, Clone)]
+pub struct AsyncJob {
+    client: AsyncClient,
+    id: String,
+    kind: JobKind,
+    /// Server log lines seen while polling, shared by clones of the handle.
+    logs: Arc<Mutex<Vec<String>>>,
+}
+
+impl AsyncJob {
+    pub(crate) fn new(client: AsyncClient, id: String, kind: JobKind) -> Self {
+        Self {
+            client,
+            id,
+            kind,
+            logs: Arc::default(),
+        }
+    }
+
+    /// Server-side job (request) ID.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Log lines reported by the server for this job so far; see [`Job::logs`](crate::Job::logs).
+    pub fn logs(&self) -> Vec<String> {
+        self.logs.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Fetches the current status once, without waiting.
+    pub async fn status(&self) -> Result<JobStatus> {
+        match &self.kind {
+            JobKind::Legacy { base_url, reply } => {
+                let reply = match reply {
+                    Some(r) if self.id.is_empty() => r.clone(),
+                    _ => self.fetch_legacy(base_url).await?,
+                };
+                Ok(JobStatus::from_api(&reply.state))
+            }
+            JobKind::Processing { monitor_url } => {
+                let status = self.fetch_processing(monitor_url).await?;
+                Ok(JobStatus::from_api(&status.status))
+            }
+        }
+    }
+
+    /// Fetches the result location of a finished job, without waiting.
+    pub async fn results(&self) -> Result<RemoteFile> {
+        match &self.kind {
+            JobKind::Legacy { base_url, reply } => {
+                let reply = match reply {
+                    Some(r) if self.id.is_empty() || r.state == "completed" => r.clone(),
+                    _ => self.fetch_legacy(base_url).await?,
+                };
+                remote_file_from_reply(&reply, base_url)
+            }
+            JobKind::Processing { monitor_url } => {
+                let status = self.fetch_processing(monitor_url).await?;
+                self.processing_results(monitor_url, &status).await
+            }
+        }
+    }
+
+    /// Polls until the job finishes and returns the result location.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(job_id = %self.id)))]
+    pub async fn wait(&self) -> Result<RemoteFile> {
+        match &self.kind {
+            JobKind::Legacy { base_url, reply } => self.wait_legacy(base_url, reply.clone()).await,
+            JobKind::Processing { monitor_url } => {
+                self.follow_processing(monitor_url, true).await?.into_file()
+            }
+        }
+    }
+
+    /// Waits for the job to finish, then downloads the result to `target`.
+    pub async fn download(&self, target: &Path) -> Result<PathBuf> {
+        let file = self.wait().await?;
+        self.client.download(&file, target).await
+    }
+
+    /// Dismisses the job on the server, cancelling it if it has not finished yet.
+    pub async fn dismiss(&self) -> Result<()> {
+        let url = match &self.kind {
+            JobKind::Legacy { base_url, .. } => {
+                if self.id.is_empty() {
+                    return Err(CdsError::Protocol(
+                        "missing request_id for legacy job".to_string(),
+                    ));
+                }
+                format!("{}/tasks/{}", base_url.trim_end_matches('/'), self.id)
+            }
+            JobKind::Processing { monitor_url } => monitor_url.clone(),
+        };
+        let client = &self.client;
+        let resp = client
+            .robust_request(&client.retry, || {
+                client.send(client.apply_auth(client.http.delete(&url)))
+            })
+            .await?;
+        client.log_debug(format_args!("DELETE {} -> {}", url, resp.status()));
+        if !resp.status().is_success() {
+            return Err(error_from_response(ErrorResponse {
+                status: resp.status(),
+                url,
+                headers: resp.headers().clone(),
+                body: resp.text().await.unwrap_or_default(),
+            }));
+        }
+        Ok(())
+    }
+
+    /// Notifies the event sink that a poll saw the job in the new state `status`.
+    fn state_changed(&self, status: &JobStatus) {
+        self.client.emit(|| CdsEvent::StateChanged {
+            job_id: self.id.clone(),
+            status: status.clone(),
+        });
+    }
+
+    /// Sends `progress` to the event sink and, if progress display is on, the reporter.
+    fn report_progress(&self, progress: JobProgress) {
+        if self.client.progress {
+            self.client.reporter.0.job_progress(&self.id, &progress);
+        }
+        self.client.emit(|| CdsEvent::Progress {
+            job_id: self.id.clone(),
+            progress,
+        });
+    }
+
+    /// The result of the job if it has finished, without waiting; otherwise the handle and
+    /// the status seen.
+    pub(crate) async fn check(&self) -> Result<Submission<AsyncJob>> {
+        match &self.kind {
+            JobKind::Legacy { base_url, reply } => {
+                let reply = match reply {
+                    Some(r) => r.clone(),
+                    None => self.fetch_legacy(base_url).await?,
+                };
+                match reply.state.as_str() {
+                    "queued" | "running" if !self.id.is_empty() => Ok(Submission::Pending {
+                        job: self.clone(),
+                        status: JobStatus::from_api(&reply.state),
+                    }),
+                    "failed" => Err(failure_from_reply(&reply)),
+                    _ => remote_file_from_reply(&reply, base_url).map(Submission::Completed),
+                }
+            }
+            JobKind::Processing { monitor_url } => self.follow_processing(monitor_url, false).await,
+        }
+    }
+
+    async fn wait_legacy(&self, base_url: &str, reply: Option<ApiReply>) -> Result<RemoteFile> {
+        let mut reply = match reply {
+            Some(r) => r,
+            None => self.fetch_legacy(base_url).await?,
+        };
+
+        let mut sleep = self.client.poll_min;
+        let mut last_state: Option<String> = None;
+        let mut since = Instant::now();
+
+        loop {
+            if last_state.as_deref() != Some(reply.state.as_str()) {
+                self.state_changed(&JobStatus::from_api(&reply.state));
+                last_state = Some(reply.state.clone());
+                since = Instant::now();
+                self.client
+                    .log_info(format_args!("Request state: {}", reply.state));
+            }
+            self.report_progress(JobProgress {
+                status: JobStatus::from_api(&reply.state),
+                percent: None,
+                queue_position: None,
+                elapsed: since.elapsed(),
+            });
+
+            match reply.state.as_str() {
+                "completed" => return remote_file_from_reply(&reply, base_url),
+                "queued" | "running" => {
+                    let rid = reply.request_id.clone().ok_or_else(|| {
+                        CdsError::Protocol(format!(
+                            "missing request_id while state={}",
+                            reply.state
+                        ))
+                    })?;
+                    tokio::time::sleep(sleep).await;
+                    sleep = backoff(sleep, self.client.poll_max);
+
+                    let task_url = format!("{}/tasks/{}", base_url.trim_end_matches('/'), rid);
+                    reply = self
+                        .client
+                        .api_json::<Value, ApiReply>("GET", &task_url, &Value::Null)
+                        .await?;
+                }
+                "failed" => return Err(failure_from_reply(&reply)),
+                other => {
+                    return Err(CdsError::Protocol(format!("unknown API state [{}]", other)));
+                }
+            }
+        }
+    }
+
+    /// Follows the job with a [`Retrieval`] until it finishes or, unless `wait`, until a
+    /// poll finds it still queued or running.
+    async fn follow_processing(
+        &self,
+        monitor_url: &str,
+        wait: bool,
+    ) -> Result<Submission<AsyncJob>> {
+        let mut retrieval = Retrieval::follow(monitor_url, &self.client.key)
+            .with_poll_interval(self.client.poll_min, self.client.poll_max);
+        let mut request = retrieval
+            .poll_request()
+            .expect("a followed retrieval starts polling");
+        let mut last_status: Option<JobStatus> = None;
+        let mut since = Instant::now();
+        loop {
+            let response = self.client.send_protocol(&request).await?;
+            let polls = retrieval.polls();
+            let action = retrieval.handle(response);
+            if retrieval.polls() > polls {
+                let status = retrieval.status().cloned().expect("set by the poll");
+                self.record_logs(retrieval.logs().to_vec());
+                if last_status.as_ref() != Some(&status) {
+                    self.state_changed(&status);
+                    self.client.log_info(format_args!("Job status: {}", status));
+                    last_status = Some(status.clone());
+                    since = Instant::now();
+                }
+                self.report_progress(JobProgress {
+                    status,
+                    percent: retrieval.percent(),
+                    queue_position: retrieval.queue_position(),
+                    elapsed: since.elapsed(),
+                });
+            }
+            request = match action? {
+                Action::Send(request) => request,
+                Action::Poll { after, request } => {
+                    if !wait {
+                        return Ok(Submission::Pending {
+                            job: self.clone(),
+                            status: retrieval.status().cloned().expect("set by the poll"),
+                        });
+                    }
+                    tokio::time::sleep(after).await;
+                    request
+                }
+                Action::Done(files) => {
+                    let file = files.into_iter().next();
+                    return Ok(Submission::Completed(
+                        file.expect("results hold at least one file"),
+                    ));
+                }
+            };
+        }
+    }
+
+    async fn fetch_legacy(&self, base_url: &str) -> Result<ApiReply> {
+        if self.id.is_empty() {
+            return Err(CdsError::Protocol(
+                "missing request_id for legacy job".to_string(),
+            ));
+        }
+        let task_url = format!("{}/tasks/{}", base_url.trim_end_matches('/'), self.id);
+        self.client
+            .api_json::<Value, ApiReply>("GET", &task_url, &Value::Null)
+            .await
+    }
+
+    async fn fetch_processing(&self, monitor_url: &str) -> Result<ProcessingJobStatus> {
+        let status = self
+            .client
+            .api_json::<Value, ProcessingJobStatus>("GET", &status_url(monitor_url), &Value::Null)
+            .await?;
+        self.record_logs(status.log_lines());
+        Ok(status)
+    }
+
+    /// Keeps the server log and reports the lines not seen before.
+    fn record_logs(&self, lines: Vec<String>) {
+        let mut logs = self.logs.lock().unwrap_or_else(|e| e.into_inner());
+        // The server sends the whole log each time; anything past what we have is new.
+        for line in lines.iter().skip(logs.len()) {
+            self.client.log_info(format_args!("{}", line));
+            self.client.emit(|| CdsEvent::LogLine {
+                job_id: self.id.clone(),
+                line: line.clone(),
+            });
+        }
+        if lines.len() > logs.len() {
+            *logs = lines;
+        }
+    }
+
+    async fn processing_results(
+        &self,
+        monitor_url: &str,
+        status: &ProcessingJobStatus,
+    ) -> Result<RemoteFile> {
+        let results_url = results_url(monitor_url, status);
+        let results: ProcessingResults = self
+            .client
+            .api_json::<Value, ProcessingResults>("GET", &results_url, &Value::Null)
+            .await?;
+        results.to_remote_file(&results_url)
+    }
+}
@@ -0,0 +1,113 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::VecDeque;
+use std::path::PathBuf;
+use std::sync::mpsc::{self, Receiver};
+use std::sync::{Arc, Mutex};
+use std::thread;
+
+use crate::client::{Client, RemoteFile};
+use crate::error::{CdsError, Result};
+use crate::filename::expand_target_template;
+
+/// One entry of a [`Client::retrieve_many`] batch.
+#[derive(Debug, Clone)]
+pub struct BatchItem {
+    pub dataset: String,
+    pub request: Value,
+    pub target: PathBuf,
+}
+
+impl BatchItem {
+    pub fn new<T: Serialize>(
+        dataset: impl Into<String>,
+        request: &T,
+        target: impl Into<PathBuf>,
+    ) -> Result<Self> {
+        Ok(Self {
+            dataset: dataset.into(),
+            request: serde_json::to_value(request)
+                .map_err(|e| CdsError::json("failed to serialize request", e))?,
+            target: target.into(),
+        })
+    }
+
+    /// Like [`BatchItem::new`], with the target [expanded](expand_target_template) from
+    /// fields of the request, e.g. `era5_{variable}_{year}{month}.grib`.
+    pub fn from_template<T: Serialize>(
+        dataset: impl Into<String>,
+        request: &T,
+        target_template: &str,
+    ) -> Result<Self> {
+        let target = expand_target_template(target_template, request)?;
+        Self::new(dataset, request, target)
+    }
+}
+
+/// Outcome of one batch entry.
+#[derive(Debug)]
+pub struct BatchResult {
+    /// Position of the entry in the submitted batch.
+    pub index: usize,
+    /// The entry itself.
+    pub item: BatchItem,
+    /// The downloaded result, or why it failed.
+    pub result: Result<RemoteFile>,
+}
+
+/// Iterator over batch results, yielded in completion order.
+///
+/// Dropping it does not stop the workers; entries already running still complete.
+#[derive(Debug)]
+pub struct BatchResults {
+    rx: Receiver<BatchResult>,
+}
+
+impl Iterator for BatchResults {
+    type Item = BatchResult;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rx.recv().ok()
+    }
+}
+
+impl Client {
+    /// Retrieves many requests with at most `concurrency` of them in flight at once.
+    ///
+    /// Each worker submits an entry, waits for it and downloads it to its target before
+    /// picking the next one. Results are yielded as they complete.
+    pub fn retrieve_many<I>(&self, items: I, concurrency: usize) -> BatchResults
+    where
+        I: IntoIterator<Item = BatchItem>,
+    {
+        let queue: VecDeque<(usize, BatchItem)> = items.into_iter().enumerate().collect();
+        let workers = concurrency.max(1).min(queue.len().max(1));
+        let queue = Arc::new(Mutex::new(queue));
+        let (tx, rx) = mpsc::channel();
+
+        for _ in 0..workers {
+            let client = self.clone();
+            let queue = Arc::clone(&queue);
+            let tx = tx.clone();
+            thread::spawn(move || {
+                loop {
+                    let next = queue.lock().unwrap().pop_front();
+                    let Some((index, item)) = next else { break };
+                    let result = client.retrieve(&item.dataset, &item.request, Some(&item.target));
+                    if tx
+                        .send(BatchResult {
+                            index,
+                            item,
+                            result,
+                        })
+                        .is_err()
+                    {
+                        break;
+                    }
+                }
+            });
+        }
+
+        BatchResults { rx }
+    }
+}
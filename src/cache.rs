@@ -0,0 +1,192 @@
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+
+use crate::client::{Client, RemoteFile};
+use crate::error::{CdsError, Result};
+use crate::normalize::normalize_request;
+use crate::state::request_hash;
+
+/// Directory of downloaded results keyed by a hash of the dataset and normalized request
+/// (see [`Client::with_cache_dir`]).
+///
+/// Each result is stored as `<hash>` next to a `<hash>.json` file describing it.
+#[derive(Debug)]
+pub(crate) struct Cache {
+    dir: PathBuf,
+}
+
+/// Sidecar describing a cached result.
+#[derive(Debug, Serialize, Deserialize)]
+struct CachedFile {
+    location: String,
+    content_length: u64,
+    #[serde(default)]
+    content_type: Option<String>,
+    #[serde(default)]
+    checksum: Option<String>,
+}
+
+impl Cache {
+    pub(crate) fn new(dir: PathBuf) -> Self {
+        Self { dir }
+    }
+
+    /// Cache key of a request. Normalized first, so equivalent spellings share an entry.
+    pub(crate) fn key<T: Serialize>(dataset: &str, request: &T) -> Result<String> {
+        let mut request = serde_json::to_value(request)
+            .map_err(|e| CdsError::json("failed to serialize request", e))?;
+        normalize_request(&mut request);
+        request_hash(&json!({ "dataset": dataset, "request": request }))
+    }
+
+    /// The cached result for `key`, if complete.
+    pub(crate) fn get(&self, key: &str) -> Option<(PathBuf, RemoteFile)> {
+        let data = self.dir.join(key);
+        let meta: CachedFile = serde_json::from_slice(&fs::read(self.meta_path(key)).ok()?).ok()?;
+        if fs::metadata(&data).ok()?.len() != meta.content_length {
+            return None;
+        }
+        let file = RemoteFile {
+            location: meta.location,
+            content_length: meta.content_length,
+            content_type: meta.content_type,
+            checksum: meta.checksum,
+        };
+        Some((data, file))
+    }
+
+    /// Stores a copy of the downloaded `path` as the result for `key`.
+    pub(crate) fn put(
+        &self,
+        key: &str,
+        file: &RemoteFile,
+        path: &Path,
+        hard_links: bool,
+    ) -> Result<()> {
+        fs::create_dir_all(&self.dir).map_err(|e| {
+            CdsError::io(
+                format!("failed to create cache directory {}", self.dir.display()),
+                e,
+            )
+        })?;
+        let data = self.dir.join(key);
+        // Copy under a temporary name first so readers never see a partial entry.
+        let tmp = self.dir.join(format!("{}.tmp", key));
+        link_or_copy(path, &tmp, hard_links)?;
+        fs::rename(&tmp, &data)
+            .map_err(|e| CdsError::io(format!("failed to write {}", data.display()), e))?;
+
+        let meta = CachedFile {
+            location: file.location.clone(),
+            content_length: file.content_length,
+            content_type: file.content_type.clone(),
+            checksum: file.checksum.clone(),
+        };
+        let text = serde_json::to_vec_pretty(&meta)
+            .map_err(|e| CdsError::json("failed to serialize cache entry", e))?;
+        let meta_path = self.meta_path(key);
+        fs::write(&meta_path, text)
+            .map_err(|e| CdsError::io(format!("failed to write {}", meta_path.display()), e))
+    }
+
+    /// Puts the cached `data` at `target`.
+    pub(crate) fn restore(&self, data: &Path, target: &Path, hard_links: bool) -> Result<()> {
+        if let Some(parent) = target.parent() {
+            if !parent.as_os_str().is_empty() {
+                fs::create_dir_all(parent).map_err(|e| {
+                    CdsError::io(
+                        format!("failed to create directory {}", parent.display()),
+                        e,
+                    )
+                })?;
+            }
+        }
+        if target.exists() {
+            fs::remove_file(target)
+                .map_err(|e| CdsError::io(format!("failed to replace {}", target.display()), e))?;
+        }
+        link_or_copy(data, target, hard_links)
+    }
+
+    fn meta_path(&self, key: &str) -> PathBuf {
+        self.dir.join(format!("{}.json", key))
+    }
+}
+
+/// Hard-links `from` to `to` if enabled and possible (same file system), else copies.
+fn link_or_copy(from: &Path, to: &Path, hard_link: bool) -> Result<()> {
+    if hard_link && fs::hard_link(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to).map(drop).map_err(|e| {
+        CdsError::io(
+            format!("failed to copy {} to {}", from.display(), to.display()),
+            e,
+        )
+    })
+}
+
+impl Client {
+    /// Keeps downloaded results in `dir`, keyed by a hash of the dataset and the
+    /// [normalized](crate::normalize_request) request.
+    ///
+    /// [`Client::retrieve`] first looks the request up there and, on a hit, copies the
+    /// cached file to the target instead of submitting the request again. Only retrievals
+    /// to a single target file are cached.
+    pub fn with_cache_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.cache = Some(Arc::new(Cache::new(dir.into())));
+        self
+    }
+
+    /// Hard-links results into the cache and cached results to their targets instead of
+    /// copying them, where the file system allows it (default `false`). A linked target
+    /// shares its data with the cache entry, so it must not be modified in place.
+    pub fn with_cache_hard_links(mut self, hard_links: bool) -> Self {
+        self.cache_hard_links = hard_links;
+        self
+    }
+
+    /// Puts the cached result of `request` at `target`, if there is one.
+    pub(crate) fn retrieve_cached<T: Serialize>(
+        &self,
+        dataset: &str,
+        request: &T,
+        target: &Path,
+    ) -> Result<Option<RemoteFile>> {
+        let Some(cache) = &self.cache else {
+            return Ok(None);
+        };
+        let Some((data, file)) = cache.get(&Cache::key(dataset, request)?) else {
+            return Ok(None);
+        };
+        cache.restore(&data, target, self.cache_hard_links)?;
+        self.log_info(format_args!(
+            "{} served from cache {}",
+            target.display(),
+            data.display()
+        ));
+        Ok(Some(file))
+    }
+
+    /// Adds the result of `request`, downloaded to `path`, to the cache.
+    pub(crate) fn store_cached<T: Serialize>(
+        &self,
+        dataset: &str,
+        request: &T,
+        file: &RemoteFile,
+        path: &Path,
+    ) -> Result<()> {
+        match &self.cache {
+            Some(cache) => cache.put(
+                &Cache::key(dataset, request)?,
+                file,
+                path,
+                self.cache_hard_links,
+            ),
+            None => Ok(()),
+        }
+    }
+}
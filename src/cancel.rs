@@ -0,0 +1,26 @@
+use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Shared flag used to cancel in-flight calls of a [`Client`](crate::Client).
+///
+/// Clones share the same flag. Cancellation is noticed between polls, retries and download
+/// chunks, after which the call fails with [`CdsError::Cancelled`](crate::CdsError::Cancelled).
+/// The crate does not install signal handlers; to react to Ctrl-C, call [`cancel`](Self::cancel)
+/// from your own handler (e.g. with the `ctrlc` crate).
+#[derive(Debug, Clone, Default)]
+pub struct CancellationToken(Arc<AtomicBool>);
+
+impl CancellationToken {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Requests cancellation of every call using this token.
+    pub fn cancel(&self) {
+        self.0.store(true, Ordering::SeqCst);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
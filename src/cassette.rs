@@ -0,0 +1,230 @@
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD as BASE64;
+use reqwest::StatusCode;
+use reqwest::blocking::{RequestBuilder, Response};
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue, SET_COOKIE};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::client::Client;
+use crate::error::{CdsError, Result};
+
+/// One recorded request and the response it got.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Interaction {
+    request: RecordedRequest,
+    response: RecordedResponse,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedRequest {
+    method: String,
+    url: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct RecordedResponse {
+    status: u16,
+    #[serde(default)]
+    headers: Vec<(String, String)>,
+    /// Body as text, or base64 (`body_base64`) when it is not valid UTF-8.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    body_base64: Option<String>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct CassetteFile {
+    #[serde(default)]
+    interactions: Vec<Interaction>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mode {
+    Record,
+    Replay,
+}
+
+/// Recorded HTTP interactions, written by [`Client::with_recording`] and served by
+/// [`Client::with_replay`].
+#[derive(Debug)]
+pub(crate) struct Cassette {
+    mode: Mode,
+    path: PathBuf,
+    interactions: Mutex<Vec<Interaction>>,
+    /// Replay position per `(method, url)`, so repeated polls of one URL see the recorded
+    /// sequence of states.
+    cursors: Mutex<HashMap<(String, String), usize>>,
+}
+
+impl Cassette {
+    fn record(path: &Path) -> Result<Self> {
+        let cassette = Self {
+            mode: Mode::Record,
+            path: path.to_path_buf(),
+            interactions: Mutex::new(Vec::new()),
+            cursors: Mutex::new(HashMap::new()),
+        };
+        cassette.save(&[])?;
+        Ok(cassette)
+    }
+
+    fn replay(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| CdsError::io(format!("failed to read {}", path.display()), e))?;
+        let file: CassetteFile = serde_json::from_str(&text)
+            .map_err(|e| CdsError::json(format!("failed to parse {}", path.display()), e))?;
+        Ok(Self {
+            mode: Mode::Replay,
+            path: path.to_path_buf(),
+            interactions: Mutex::new(file.interactions),
+            cursors: Mutex::new(HashMap::new()),
+        })
+    }
+
+    pub(crate) fn is_replay(&self) -> bool {
+        self.mode == Mode::Replay
+    }
+
+    /// Sends `req` and records the exchange, or answers it from the recording.
+    pub(crate) fn send(
+        &self,
+        req: RequestBuilder,
+    ) -> std::result::Result<Response, reqwest::Error> {
+        let (http, request) = req.build_split();
+        let request = request?;
+        let method = request.method().to_string();
+        let url = request.url().to_string();
+        if self.mode == Mode::Replay {
+            return Ok(self.replayed(method, url));
+        }
+
+        let body = request
+            .body()
+            .and_then(|b| b.as_bytes())
+            .map(|b| String::from_utf8_lossy(b).into_owned());
+        let resp = http.execute(request)?;
+        let status = resp.status();
+        let headers = resp.headers().clone();
+        let bytes = resp.bytes()?.to_vec();
+
+        let (text, base64) = match String::from_utf8(bytes.clone()) {
+            Ok(text) => (Some(text), None),
+            Err(_) => (None, Some(BASE64.encode(&bytes))),
+        };
+        let interaction = Interaction {
+            request: RecordedRequest { method, url, body },
+            response: RecordedResponse {
+                status: status.as_u16(),
+                headers: headers
+                    .iter()
+                    .filter(|(name, _)| **name != SET_COOKIE)
+                    .filter_map(|(name, value)| {
+                        Some((name.to_string(), value.to_str().ok()?.to_string()))
+                    })
+                    .collect(),
+                body: text,
+                body_base64: base64,
+            },
+        };
+        let mut interactions = self.interactions.lock().unwrap_or_else(|e| e.into_inner());
+        interactions.push(interaction);
+        // Recording is a test aid: a failed write is reported but must not fail the call.
+        if let Err(e) = self.save(&interactions) {
+            eprintln!("WARNING failed to save cassette: {}", e);
+        }
+        Ok(response(status, &headers, bytes))
+    }
+
+    /// The next recorded response for `method url`, or a 501 response naming the unmatched
+    /// request.
+    fn replayed(&self, method: String, url: String) -> Response {
+        let interactions = self.interactions.lock().unwrap_or_else(|e| e.into_inner());
+        let mut cursors = self.cursors.lock().unwrap_or_else(|e| e.into_inner());
+        let matching: Vec<&Interaction> = interactions
+            .iter()
+            .filter(|i| i.request.method == method && i.request.url == url)
+            .collect();
+        let cursor = cursors.entry((method.clone(), url.clone())).or_default();
+        // Past the end, the last response repeats (e.g. a job that stays completed).
+        let Some(interaction) = matching.get(*cursor).or(matching.last()) else {
+            let body = serde_json::json!({
+                "title": "no recorded interaction",
+                "detail": format!("{} {} is not in cassette {}", method, url, self.path.display()),
+            });
+            return response(
+                StatusCode::NOT_IMPLEMENTED,
+                &HeaderMap::new(),
+                body.to_string().into_bytes(),
+            );
+        };
+        *cursor += 1;
+
+        let recorded = &interaction.response;
+        let mut headers = HeaderMap::new();
+        for (name, value) in &recorded.headers {
+            if let (Ok(name), Ok(value)) = (
+                HeaderName::from_bytes(name.as_bytes()),
+                HeaderValue::from_str(value),
+            ) {
+                headers.append(name, value);
+            }
+        }
+        let body = match (&recorded.body, &recorded.body_base64) {
+            (_, Some(encoded)) => BASE64.decode(encoded).unwrap_or_default(),
+            (Some(text), None) => text.clone().into_bytes(),
+            (None, None) => Vec::new(),
+        };
+        let status = StatusCode::from_u16(recorded.status).unwrap_or(StatusCode::OK);
+        response(status, &headers, body)
+    }
+
+    fn save(&self, interactions: &[Interaction]) -> Result<()> {
+        let file = CassetteFile {
+            interactions: interactions.to_vec(),
+        };
+        let text = serde_json::to_string_pretty(&file)
+            .map_err(|e| CdsError::json("failed to serialize cassette", e))?;
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        fs::write(&tmp, text)
+            .map_err(|e| CdsError::io(format!("failed to write {}", self.path.display()), e))?;
+        fs::rename(&tmp, &self.path)
+            .map_err(|e| CdsError::io(format!("failed to write {}", self.path.display()), e))
+    }
+}
+
+fn response(status: StatusCode, headers: &HeaderMap, body: Vec<u8>) -> Response {
+    let mut builder = http::Response::builder().status(status);
+    if let Some(h) = builder.headers_mut() {
+        h.extend(headers.clone());
+    }
+    Response::from(builder.body(body).expect("status and headers are valid"))
+}
+
+impl Client {
+    /// Records every HTTP interaction of this client to the cassette file at `path`
+    /// (overwriting it), for later offline replay with [`Client::with_replay`].
+    ///
+    /// Response bodies, including downloaded files, are buffered in memory and stored in the
+    /// cassette, so record small requests. Credentials are not recorded.
+    pub fn with_recording(mut self, path: &Path) -> Result<Self> {
+        self.cassette = Some(Arc::new(Cassette::record(path)?));
+        Ok(self)
+    }
+
+    /// Answers all HTTP requests from the cassette at `path` instead of the network.
+    ///
+    /// Requests are matched by method and URL; repeated requests to the same URL (status
+    /// polls) get the recorded responses in order. Polling does not sleep during replay.
+    pub fn with_replay(mut self, path: &Path) -> Result<Self> {
+        self.cassette = Some(Arc::new(Cassette::replay(path)?));
+        Ok(self)
+    }
+}
@@ -0,0 +1,135 @@
+use serde_json::Value;
+
+use crate::client::Client;
+use crate::error::Result;
+
+/// Dataset metadata from the CDS catalogue.
+#[derive(Debug, Clone)]
+pub struct DatasetInfo {
+    /// Dataset ID, as passed to [`Client::retrieve`].
+    pub id: String,
+    /// Human-readable title.
+    pub title: Option<String>,
+    /// Dataset abstract (description).
+    pub abstract_text: Option<String>,
+    /// Licences attached to the dataset.
+    pub licences: Vec<DatasetLicenceLink>,
+    /// Update frequency as advertised by the catalogue (free text).
+    pub update_frequency: Option<String>,
+    /// Publication timestamp.
+    pub published: Option<String>,
+    /// Last update timestamp.
+    pub updated: Option<String>,
+    /// Keywords attached to the dataset.
+    pub keywords: Vec<String>,
+}
+
+/// Licence link attached to a catalogue entry.
+#[derive(Debug, Clone)]
+pub struct DatasetLicenceLink {
+    /// Licence title.
+    pub title: Option<String>,
+    /// Link to the licence text.
+    pub href: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct CatalogueCollection {
+    id: String,
+    #[serde(default)]
+    title: Option<String>,
+    #[serde(default)]
+    description: Option<String>,
+    #[serde(default)]
+    keywords: Vec<String>,
+    #[serde(default)]
+    published: Option<String>,
+    #[serde(default)]
+    updated: Option<String>,
+    #[serde(
+        default,
+        alias = "update_frequency",
+        alias = "updateFrequency",
+        alias = "frequency"
+    )]
+    update_frequency: Option<Value>,
+    #[serde(default)]
+    links: Vec<CatalogueLink>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct CatalogueLink {
+    #[serde(default)]
+    rel: Option<String>,
+    #[serde(default)]
+    title: Option<String>,
+    href: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct CatalogueCollections {
+    #[serde(default)]
+    collections: Vec<CatalogueCollection>,
+    #[serde(default)]
+    links: Vec<CatalogueLink>,
+}
+
+impl From<CatalogueCollection> for DatasetInfo {
+    fn from(c: CatalogueCollection) -> Self {
+        let licences = c
+            .links
+            .into_iter()
+            .filter(|l| l.rel.as_deref() == Some("license"))
+            .map(|l| DatasetLicenceLink {
+                title: l.title,
+                href: l.href,
+            })
+            .collect();
+        let update_frequency = c.update_frequency.and_then(|v| match v {
+            Value::String(s) => Some(s),
+            Value::Null => None,
+            other => Some(other.to_string()),
+        });
+        Self {
+            id: c.id,
+            title: c.title,
+            abstract_text: c.description,
+            licences,
+            update_frequency,
+            published: c.published,
+            updated: c.updated,
+            keywords: c.keywords,
+        }
+    }
+}
+
+impl Client {
+    /// Lists all datasets published in the catalogue, following pagination.
+    pub fn list_datasets(&self) -> Result<Vec<DatasetInfo>> {
+        let mut url = Some(format!("{}/collections", self.catalogue_base()));
+        let mut out = Vec::new();
+        while let Some(u) = url {
+            let page: CatalogueCollections =
+                self.api_json::<Value, CatalogueCollections>("GET", &u, &Value::Null)?;
+            out.extend(page.collections.into_iter().map(DatasetInfo::from));
+            url = page
+                .links
+                .into_iter()
+                .find(|l| l.rel.as_deref() == Some("next"))
+                .map(|l| l.href);
+        }
+        Ok(out)
+    }
+
+    /// Fetches catalogue metadata for a single dataset.
+    pub fn dataset(&self, id: &str) -> Result<DatasetInfo> {
+        let url = format!("{}/collections/{}", self.catalogue_base(), id);
+        let c: CatalogueCollection =
+            self.api_json::<Value, CatalogueCollection>("GET", &url, &Value::Null)?;
+        Ok(c.into())
+    }
+
+    pub(crate) fn catalogue_base(&self) -> String {
+        format!("{}/catalogue/v1", self.url.trim_end_matches('/'))
+    }
+}
@@ -0,0 +1,148 @@
+use md5::Md5;
+use sha2::{Digest, Sha256};
+use std::fs::File;
+use std::io::{self, Read, Write};
+use std::path::{Path, PathBuf};
+
+use crate::client::Client;
+use crate::error::{CdsError, Result};
+
+/// Hash algorithms found in the `file:checksum` asset field.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Algorithm {
+    Md5,
+    Sha256,
+}
+
+/// Splits a `file:checksum` value into algorithm and hex digest.
+///
+/// The STAC file extension specifies a hex-encoded multihash (`1220...` for SHA-256,
+/// `d50110...` for MD5), but bare MD5/SHA-256 hex digests are accepted as well.
+fn parse(checksum: &str) -> Option<(Algorithm, String)> {
+    let checksum = checksum.trim().to_ascii_lowercase();
+    if !checksum.bytes().all(|b| b.is_ascii_hexdigit()) {
+        return None;
+    }
+    match checksum.len() {
+        32 => Some((Algorithm::Md5, checksum)),
+        64 => Some((Algorithm::Sha256, checksum)),
+        68 if checksum.starts_with("1220") => Some((Algorithm::Sha256, checksum[4..].to_string())),
+        38 if checksum.starts_with("d50110") => Some((Algorithm::Md5, checksum[6..].to_string())),
+        _ => None,
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+fn digest_file<D: Digest>(path: &Path) -> Result<String> {
+    let mut file = File::open(path)
+        .map_err(|e| CdsError::io(format!("failed to open {}", path.display()), e))?;
+    let mut hasher = D::new();
+    let mut buf = [0u8; 64 * 1024];
+    loop {
+        let n = file
+            .read(&mut buf)
+            .map_err(|e| CdsError::io(format!("failed to read {}", path.display()), e))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buf[..n]);
+    }
+    Ok(hex(&hasher.finalize()))
+}
+
+/// Passes writes through to `inner` while computing their SHA-256.
+pub(crate) struct Sha256Writer<W> {
+    inner: W,
+    hasher: Sha256,
+}
+
+impl<W: Write> Sha256Writer<W> {
+    pub(crate) fn new(inner: W) -> Self {
+        Self {
+            inner,
+            hasher: Sha256::new(),
+        }
+    }
+
+    /// Hashes the first `len` bytes of `path`, already written by an earlier attempt.
+    pub(crate) fn update_from_file(&mut self, path: &Path, len: u64) -> Result<()> {
+        let file = File::open(path)
+            .map_err(|e| CdsError::io(format!("failed to open {}", path.display()), e))?;
+        io::copy(&mut file.take(len), &mut self.hasher)
+            .map_err(|e| CdsError::io(format!("failed to read {}", path.display()), e))?;
+        Ok(())
+    }
+
+    /// Flushes the writer and returns the hex digest.
+    pub(crate) fn finish(mut self) -> Result<String> {
+        self.inner.flush()?;
+        Ok(hex(&self.hasher.finalize()))
+    }
+}
+
+impl<W: Write> Write for Sha256Writer<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let n = self.inner.write(buf)?;
+        self.hasher.update(&buf[..n]);
+        Ok(n)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+pub(crate) fn sha256_file(path: &Path) -> Result<String> {
+    digest_file::<Sha256>(path)
+}
+
+/// Writes `<target>.sha256` in the format of `sha256sum`, so `sha256sum -c` can check it.
+pub(crate) fn write_sidecar(target: &Path, digest: &str) -> Result<PathBuf> {
+    let mut name = target.as_os_str().to_owned();
+    name.push(".sha256");
+    let sidecar = PathBuf::from(name);
+    let file_name = target
+        .file_name()
+        .map(|n| n.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    std::fs::write(&sidecar, format!("{}  {}\n", digest, file_name))
+        .map_err(|e| CdsError::io(format!("failed to write {}", sidecar.display()), e))?;
+    Ok(sidecar)
+}
+
+impl Client {
+    /// Writes the SHA-256 of each downloaded file to `<target>.sha256` (in `sha256sum`
+    /// format), so archives can be verified later without hashing them again (default
+    /// `false`).
+    ///
+    /// The hash is computed while the file is streamed; downloads split into parallel
+    /// segments are hashed once complete.
+    pub fn with_checksum_sidecar(mut self, enabled: bool) -> Self {
+        self.checksum_sidecar = enabled;
+        self
+    }
+}
+
+/// Verifies `path` against a `file:checksum` value.
+///
+/// Returns `Ok(false)` when the checksum format is not recognized and nothing was checked.
+pub(crate) fn verify_file(path: &Path, checksum: &str) -> Result<bool> {
+    let Some((algorithm, expected)) = parse(checksum) else {
+        return Ok(false);
+    };
+    let actual = match algorithm {
+        Algorithm::Md5 => digest_file::<Md5>(path)?,
+        Algorithm::Sha256 => digest_file::<Sha256>(path)?,
+    };
+    if actual != expected {
+        return Err(CdsError::Integrity {
+            path: path.to_path_buf(),
+            expected,
+            actual,
+        });
+    }
+    Ok(true)
+}
@@ -0,0 +1,231 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::fs::File;
+use std::io;
+use std::path::{Path, PathBuf};
+
+use crate::batch::BatchItem;
+use crate::client::Client;
+use crate::error::{CdsError, Result};
+use crate::util::value_strings;
+
+/// How to split a request into smaller requests.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ChunkBy {
+    /// One request per value of `year`.
+    Year,
+    /// One request per (`year`, `month`) pair.
+    Month,
+    /// One request per value of an arbitrary list-valued field (e.g. `variable`).
+    Field(String),
+}
+
+/// What [`Client::retrieve_chunked`] does with the downloaded chunks.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkOutput {
+    /// Keep one file per chunk, named `<stem>_<chunk>.<ext>` next to the target.
+    Separate,
+    /// Concatenate chunks into the target in request order, then remove them.
+    ///
+    /// Only meaningful for formats whose files can be concatenated, such as GRIB.
+    Concatenate,
+}
+
+/// A request produced by [`split_request`], with a label describing the chunk.
+#[derive(Debug, Clone)]
+pub struct RequestChunk {
+    /// Label such as `2024` or `2024-03`, used in chunk file names.
+    pub label: String,
+    pub request: Value,
+}
+
+/// Splits `request` along `by`. Returns the request unchanged (one chunk labelled `all`)
+/// when the axis field is missing.
+pub fn split_request<T: Serialize>(request: &T, by: &ChunkBy) -> Result<Vec<RequestChunk>> {
+    let request = serde_json::to_value(request)
+        .map_err(|e| CdsError::json("failed to serialize request", e))?;
+    let field_values = |field: &str| request.get(field).map(value_strings).unwrap_or_default();
+
+    let with = |pairs: &[(&str, &str)]| {
+        let mut r = request.clone();
+        if let Value::Object(map) = &mut r {
+            for (k, v) in pairs {
+                map.insert(
+                    k.to_string(),
+                    Value::Array(vec![Value::String(v.to_string())]),
+                );
+            }
+        }
+        r
+    };
+
+    let chunks: Vec<RequestChunk> = match by {
+        ChunkBy::Year => field_values("year")
+            .iter()
+            .map(|y| RequestChunk {
+                label: y.clone(),
+                request: with(&[("year", y)]),
+            })
+            .collect(),
+        ChunkBy::Month => {
+            let months = field_values("month");
+            field_values("year")
+                .iter()
+                .flat_map(|y| months.iter().map(move |m| (y.clone(), m.clone())))
+                .map(|(y, m)| RequestChunk {
+                    label: format!("{}-{}", y, m),
+                    request: with(&[("year", &y), ("month", &m)]),
+                })
+                .collect()
+        }
+        ChunkBy::Field(field) => field_values(field)
+            .iter()
+            .map(|v| RequestChunk {
+                label: v.clone(),
+                request: with(&[(field.as_str(), v)]),
+            })
+            .collect(),
+    };
+
+    if chunks.is_empty() {
+        return Ok(vec![RequestChunk {
+            label: "all".to_string(),
+            request,
+        }]);
+    }
+    Ok(chunks)
+}
+
+fn chunk_path(target: &Path, label: &str) -> PathBuf {
+    let stem = target
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_else(|| "download".to_string());
+    let name = match target.extension() {
+        Some(ext) => format!("{}_{}.{}", stem, label, ext.to_string_lossy()),
+        None => format!("{}_{}", stem, label),
+    };
+    target.with_file_name(name)
+}
+
+/// Writes the files at `paths`, in order, to a new file at `out`.
+fn concatenate(paths: &[PathBuf], out: &Path) -> Result<()> {
+    let mut out_file = File::create(out)
+        .map_err(|e| CdsError::io(format!("failed to create {}", out.display()), e))?;
+    for path in paths {
+        let mut chunk = File::open(path)
+            .map_err(|e| CdsError::io(format!("failed to open {}", path.display()), e))?;
+        io::copy(&mut chunk, &mut out_file)?;
+    }
+    out_file.sync_all()?;
+    Ok(())
+}
+
+impl Client {
+    /// Splits `request` along `by`, retrieves the chunks with up to `concurrency` in flight,
+    /// and returns the produced files in request order.
+    ///
+    /// With [`ChunkOutput::Concatenate`] the only returned path is `target`.
+    pub fn retrieve_chunked<T: Serialize>(
+        &self,
+        dataset: &str,
+        request: &T,
+        by: &ChunkBy,
+        target: &Path,
+        concurrency: usize,
+        output: ChunkOutput,
+    ) -> Result<Vec<PathBuf>> {
+        let chunks = split_request(request, by)?;
+        let items: Vec<BatchItem> = chunks
+            .into_iter()
+            .map(|c| BatchItem {
+                dataset: dataset.to_string(),
+                request: c.request,
+                target: chunk_path(target, &c.label),
+            })
+            .collect();
+        let paths: Vec<PathBuf> = items.iter().map(|i| i.target.clone()).collect();
+
+        let mut first_error = None;
+        for done in self.retrieve_many(items, concurrency) {
+            if let Err(e) = done.result {
+                first_error.get_or_insert(e);
+            }
+        }
+        if let Some(e) = first_error {
+            return Err(e);
+        }
+
+        match output {
+            ChunkOutput::Separate => Ok(paths),
+            ChunkOutput::Concatenate => {
+                // Concatenate next to the target and rename, so a failure never leaves a
+                // truncated target that looks complete.
+                let mut tmp = target.to_path_buf().into_os_string();
+                tmp.push(".tmp");
+                let tmp = PathBuf::from(tmp);
+                if let Err(e) = concatenate(&paths, &tmp) {
+                    let _ = std::fs::remove_file(&tmp);
+                    return Err(e);
+                }
+                std::fs::rename(&tmp, target).map_err(|e| {
+                    CdsError::io(format!("failed to write {}", target.display()), e)
+                })?;
+                for path in &paths {
+                    std::fs::remove_file(path)?;
+                }
+                Ok(vec![target.to_path_buf()])
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn labels(chunks: &[RequestChunk]) -> Vec<&str> {
+        chunks.iter().map(|c| c.label.as_str()).collect()
+    }
+
+    #[test]
+    fn splits_by_year_and_month() {
+        let request = json!({"variable": ["2m_temperature"], "year": ["2023", "2024"], "month": ["01", "02"]});
+
+        let years = split_request(&request, &ChunkBy::Year).unwrap();
+        assert_eq!(labels(&years), ["2023", "2024"]);
+        assert_eq!(years[1].request["year"], json!(["2024"]));
+        assert_eq!(years[1].request["month"], json!(["01", "02"]));
+
+        let months = split_request(&request, &ChunkBy::Month).unwrap();
+        assert_eq!(
+            labels(&months),
+            ["2023-01", "2023-02", "2024-01", "2024-02"]
+        );
+        assert_eq!(months[2].request["year"], json!(["2024"]));
+        assert_eq!(months[2].request["month"], json!(["01"]));
+        assert_eq!(months[2].request["variable"], json!(["2m_temperature"]));
+    }
+
+    #[test]
+    fn splits_by_field_or_keeps_the_request() {
+        let request = json!({"variable": ["u", "v"], "year": "2024"});
+        let chunks = split_request(&request, &ChunkBy::Field("variable".to_string())).unwrap();
+        assert_eq!(labels(&chunks), ["u", "v"]);
+        assert_eq!(chunks[0].request["variable"], json!(["u"]));
+
+        let chunks = split_request(&request, &ChunkBy::Field("level".to_string())).unwrap();
+        assert_eq!(labels(&chunks), ["all"]);
+        assert_eq!(chunks[0].request, request);
+    }
+
+    #[test]
+    fn chunk_paths_keep_the_extension() {
+        assert_eq!(
+            chunk_path(Path::new("out/t2m.grib"), "2024-01"),
+            Path::new("out/t2m_2024-01.grib")
+        );
+        assert_eq!(chunk_path(Path::new("t2m"), "2024"), Path::new("t2m_2024"));
+    }
+}
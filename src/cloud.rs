@@ -0,0 +1,154 @@
+use object_store::path::Path as ObjectPath;
+use object_store::{ObjectStore, ObjectStoreExt, WriteMultipart};
+use reqwest::StatusCode;
+
+use crate::async_client::AsyncClient;
+use crate::error::{CdsError, Result};
+use crate::protocol::RemoteFile;
+use crate::retry::Stall;
+
+/// Parts uploaded concurrently by [`AsyncClient::download_to_store`].
+const MAX_CONCURRENT_PARTS: usize = 8;
+
+impl AsyncClient {
+    /// Streams `file` into `path` of `store` with a multipart upload, without staging it on
+    /// local disk, and returns the number of bytes written.
+    ///
+    /// Interrupted transfers are resumed with HTTP range requests, so the upload only ever
+    /// sees each byte once. If the download fails, the upload is aborted and nothing is
+    /// written to `path`.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(location = %file.location, size = file.content_length, path = %path)))]
+    pub async fn download_to_store(
+        &self,
+        file: &RemoteFile,
+        store: &dyn ObjectStore,
+        path: &ObjectPath,
+    ) -> Result<u64> {
+        let upload = store
+            .put_multipart(path)
+            .await
+            .map_err(|e| upload_error(path, e))?;
+        let mut upload = WriteMultipart::new(upload);
+        match self.stream_into(file, path, &mut upload).await {
+            Ok(written) => {
+                upload.finish().await.map_err(|e| upload_error(path, e))?;
+                Ok(written)
+            }
+            Err(e) => {
+                upload.abort().await.ok();
+                Err(e)
+            }
+        }
+    }
+
+    /// Like [`AsyncClient::download_to_store`], with the store and path given as a URL
+    /// such as `s3://bucket/era5/2024/03.grib`, `gs://...`, `az://...` or `file:///...`.
+    ///
+    /// Credentials and options are read from the environment (e.g. `AWS_ACCESS_KEY_ID`,
+    /// `AWS_REGION`). Cloud schemes need the matching `object_store` feature (`aws`,
+    /// `gcp`, `azure`) enabled in your own `Cargo.toml`.
+    pub async fn download_to_url(&self, file: &RemoteFile, url: &str) -> Result<u64> {
+        let parsed = url::Url::parse(url)
+            .map_err(|e| CdsError::Config(format!("invalid object store URL {}: {}", url, e)))?;
+        let (store, path) =
+            object_store::parse_url_opts(&parsed, std::env::vars()).map_err(|e| {
+                CdsError::Config(format!("unsupported object store URL {}: {}", url, e))
+            })?;
+        self.download_to_store(file, store.as_ref(), &path).await
+    }
+
+    async fn stream_into(
+        &self,
+        file: &RemoteFile,
+        path: &ObjectPath,
+        upload: &mut WriteMultipart,
+    ) -> Result<u64> {
+        let mut downloaded: u64 = 0;
+        let pb = self.progress.then(|| self.reporter.0.clone());
+        if let Some(pb) = &pb {
+            pb.started(&file.location, file.content_length, 0);
+        }
+
+        let mut tries = 0usize;
+        let mut stall = Stall::new(0);
+        while tries < self.download_retry.max_attempts() {
+            let mut resp = self
+                .open_download(file, (downloaded > 0).then_some(downloaded))
+                .await?;
+            // The server may ignore the range and send the whole file: skip what we have.
+            let mut skip = if downloaded > 0 && resp.status() != StatusCode::PARTIAL_CONTENT {
+                downloaded
+            } else {
+                0
+            };
+
+            let interrupted = loop {
+                let mut chunk = match resp.chunk().await {
+                    Ok(Some(chunk)) => chunk,
+                    Ok(None) => break None,
+                    Err(e) => break Some(e),
+                };
+                if skip > 0 {
+                    let n = skip.min(chunk.len() as u64);
+                    skip -= n;
+                    let _ = chunk.split_to(n as usize);
+                }
+                if chunk.is_empty() {
+                    continue;
+                }
+                upload
+                    .wait_for_capacity(MAX_CONCURRENT_PARTS)
+                    .await
+                    .map_err(|e| upload_error(path, e))?;
+                upload.write(&chunk);
+                downloaded += chunk.len() as u64;
+                if let Some(pb) = &pb {
+                    pb.advanced(&file.location, chunk.len() as u64);
+                }
+            };
+
+            if downloaded >= file.content_length {
+                if let Some(pb) = &pb {
+                    pb.finished(&file.location);
+                }
+                return Ok(downloaded);
+            }
+
+            tries += 1;
+            let delay = self.download_retry.jittered_delay(tries);
+            let exhausted = self
+                .download_retry
+                .exhausted(tries, stall.since(downloaded), delay);
+            match interrupted {
+                Some(e) if exhausted => {
+                    return Err(CdsError::download(
+                        "download interrupted",
+                        Some(Box::new(e)),
+                    ));
+                }
+                Some(e) => self.log_debug(format_args!(
+                    "download interrupted at {} byte(s): {}, resuming",
+                    downloaded, e
+                )),
+                None if exhausted => break,
+                None => {}
+            }
+            if let Some(pb) = &pb {
+                pb.resumed(&file.location, downloaded);
+            }
+            tokio::time::sleep(delay).await;
+        }
+
+        Err(CdsError::download(
+            format!(
+                "download failed: downloaded {} byte(s) out of {}",
+                downloaded, file.content_length
+            ),
+            None,
+        ))
+    }
+}
+
+fn upload_error(path: &ObjectPath, e: object_store::Error) -> CdsError {
+    CdsError::download(format!("failed to upload to {}", path), Some(Box::new(e)))
+}
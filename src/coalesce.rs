@@ -0,0 +1,151 @@
+use serde::Serialize;
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Condvar, Mutex};
+
+use crate::cache::Cache;
+use crate::client::{Client, RemoteFile};
+use crate::error::{CdsError, Result};
+
+/// `retrieve` calls in progress, by request key, shared by all clones of a client.
+#[derive(Debug, Default)]
+pub(crate) struct InFlight {
+    calls: Mutex<HashMap<String, Arc<Call>>>,
+}
+
+/// One call other threads can wait for.
+#[derive(Debug, Default)]
+struct Call {
+    /// `None` while running; `Some(None)` if it failed.
+    outcome: Mutex<Option<Option<Shared>>>,
+    done: Condvar,
+}
+
+/// What a finished call hands to the callers that waited for it.
+#[derive(Debug, Clone)]
+struct Shared {
+    file: RemoteFile,
+    /// The downloaded file, if the call downloaded to a single file.
+    path: Option<PathBuf>,
+}
+
+impl Call {
+    fn wait(&self) -> Option<Shared> {
+        let mut outcome = self.outcome.lock().unwrap_or_else(|e| e.into_inner());
+        loop {
+            if let Some(outcome) = &*outcome {
+                return outcome.clone();
+            }
+            outcome = self.done.wait(outcome).unwrap_or_else(|e| e.into_inner());
+        }
+    }
+}
+
+/// Held by the thread running a call; publishes the outcome and unregisters the call when
+/// dropped, so waiting threads are released even if the call unwinds.
+struct Leader<'a> {
+    in_flight: &'a InFlight,
+    key: String,
+    call: Arc<Call>,
+    outcome: Option<Shared>,
+}
+
+impl Drop for Leader<'_> {
+    fn drop(&mut self) {
+        self.in_flight
+            .calls
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.key);
+        *self.call.outcome.lock().unwrap_or_else(|e| e.into_inner()) = Some(self.outcome.take());
+        self.call.done.notify_all();
+    }
+}
+
+impl Client {
+    /// Coalesces concurrent [`Client::retrieve`] calls for the same dataset and
+    /// [normalized](crate::normalize_request) request (default `true`).
+    ///
+    /// While one call is in progress, identical calls from other threads (on this client or
+    /// its clones) wait for it instead of submitting their own job, then receive a copy of
+    /// its download. If the first call fails, each waiting call is run on its own.
+    pub fn with_request_coalescing(mut self, enabled: bool) -> Self {
+        self.in_flight = enabled.then(Default::default);
+        self
+    }
+
+    /// Runs `retrieve`, or waits for an identical call already running and reuses its
+    /// result. Calls downloading into a directory are not coalesced.
+    pub(crate) fn retrieve_coalesced<T: Serialize>(
+        &self,
+        dataset: &str,
+        request: &T,
+        target: Option<&Path>,
+        retrieve: impl FnOnce() -> Result<RemoteFile>,
+    ) -> Result<RemoteFile> {
+        let Some(in_flight) = &self.in_flight else {
+            return retrieve();
+        };
+        if target.is_some_and(Path::is_dir) {
+            return retrieve();
+        }
+        let key = Cache::key(dataset, request)?;
+
+        let (call, leading) = {
+            let mut calls = in_flight.calls.lock().unwrap_or_else(|e| e.into_inner());
+            match calls.get(&key) {
+                Some(call) => (Arc::clone(call), false),
+                None => {
+                    let call = Arc::new(Call::default());
+                    calls.insert(key.clone(), Arc::clone(&call));
+                    (call, true)
+                }
+            }
+        };
+
+        if leading {
+            let mut leader = Leader {
+                in_flight,
+                key,
+                call,
+                outcome: None,
+            };
+            let file = retrieve()?;
+            leader.outcome = Some(Shared {
+                file: file.clone(),
+                path: target.filter(|t| t.is_file()).map(Path::to_path_buf),
+            });
+            return Ok(file);
+        }
+
+        self.log_info(format_args!("Waiting for an identical request in progress"));
+        let Some(shared) = call.wait() else {
+            return retrieve();
+        };
+        match (target, &shared.path) {
+            (Some(target), Some(path)) if target != path.as_path() => {
+                if let Some(parent) = target.parent() {
+                    if !parent.as_os_str().is_empty() {
+                        std::fs::create_dir_all(parent).map_err(|e| {
+                            CdsError::io(
+                                format!("failed to create directory {}", parent.display()),
+                                e,
+                            )
+                        })?;
+                    }
+                }
+                std::fs::copy(path, target).map_err(|e| {
+                    CdsError::io(
+                        format!("failed to copy {} to {}", path.display(), target.display()),
+                        e,
+                    )
+                })?;
+            }
+            (Some(target), None) => {
+                self.download(&shared.file, target)?;
+            }
+            _ => {}
+        }
+        Ok(shared.file)
+    }
+}
@@ -1,42 +1,255 @@
-use anyhow::{Context, Result, bail};
+use std::collections::BTreeMap;
 use std::path::{Path, PathBuf};
 
 use crate::client::ClientConfig;
+use crate::error::{CdsError, Result};
+use crate::util::check_base_url;
 
 #[derive(Debug, Default)]
 struct RcConfig {
+    /// Whether the requested profile section exists in the file.
+    found: bool,
     url: Option<String>,
     key: Option<String>,
     verify: Option<bool>,
+    quiet: Option<bool>,
+    debug: Option<bool>,
+    proxy: Option<String>,
+    ca_bundle: Option<PathBuf>,
+    client_cert: Option<PathBuf>,
+    client_key: Option<PathBuf>,
 }
 
+/// Contents of a TOML configuration file (`~/.config/cdsapi/config.toml`, `./cdsapi.toml`).
+///
+/// Durations are in seconds.
+#[derive(Debug, Default, Clone, serde::Deserialize)]
+pub(crate) struct Settings {
+    pub(crate) url: Option<String>,
+    pub(crate) key: Option<String>,
+    pub(crate) verify: Option<bool>,
+    pub(crate) quiet: Option<bool>,
+    pub(crate) debug: Option<bool>,
+    pub(crate) proxy: Option<String>,
+    pub(crate) ca_bundle: Option<PathBuf>,
+    pub(crate) client_cert: Option<PathBuf>,
+    pub(crate) client_key: Option<PathBuf>,
+    pub(crate) timeout: Option<u64>,
+    pub(crate) connect_timeout: Option<u64>,
+    pub(crate) read_timeout: Option<u64>,
+    /// `0` keeps no idle connections open.
+    pub(crate) pool_idle_timeout: Option<u64>,
+    pub(crate) pool_max_idle_per_host: Option<usize>,
+    /// `0` disables TCP keepalive.
+    pub(crate) tcp_keepalive: Option<u64>,
+    #[serde(alias = "api_retry_max")]
+    pub(crate) retry_max: Option<usize>,
+    pub(crate) sleep_max: Option<u64>,
+    pub(crate) retry_max_elapsed: Option<u64>,
+    pub(crate) poll_min: Option<u64>,
+    pub(crate) poll_max: Option<u64>,
+    pub(crate) download_retry_max: Option<usize>,
+    pub(crate) download_retry_sleep_max: Option<u64>,
+    pub(crate) download_retry_max_elapsed: Option<u64>,
+    pub(crate) progress: Option<bool>,
+    pub(crate) wait_until_complete: Option<bool>,
+    pub(crate) download_connections: Option<usize>,
+    pub(crate) webhook: Option<String>,
+    pub(crate) notify_command: Option<String>,
+    /// `[profiles.<name>]` tables.
+    #[serde(default)]
+    profiles: BTreeMap<String, Settings>,
+}
+
+impl Settings {
+    /// Fills the fields unset in `self` from `lower`.
+    fn or(self, lower: Settings) -> Settings {
+        let mut profiles = lower.profiles;
+        for (name, p) in self.profiles {
+            let merged = match profiles.remove(&name) {
+                Some(lower) => p.or(lower),
+                None => p,
+            };
+            profiles.insert(name, merged);
+        }
+        Settings {
+            url: self.url.or(lower.url),
+            key: self.key.or(lower.key),
+            verify: self.verify.or(lower.verify),
+            quiet: self.quiet.or(lower.quiet),
+            debug: self.debug.or(lower.debug),
+            proxy: self.proxy.or(lower.proxy),
+            ca_bundle: self.ca_bundle.or(lower.ca_bundle),
+            client_cert: self.client_cert.or(lower.client_cert),
+            client_key: self.client_key.or(lower.client_key),
+            timeout: self.timeout.or(lower.timeout),
+            connect_timeout: self.connect_timeout.or(lower.connect_timeout),
+            read_timeout: self.read_timeout.or(lower.read_timeout),
+            pool_idle_timeout: self.pool_idle_timeout.or(lower.pool_idle_timeout),
+            pool_max_idle_per_host: self.pool_max_idle_per_host.or(lower.pool_max_idle_per_host),
+            tcp_keepalive: self.tcp_keepalive.or(lower.tcp_keepalive),
+            retry_max: self.retry_max.or(lower.retry_max),
+            sleep_max: self.sleep_max.or(lower.sleep_max),
+            retry_max_elapsed: self.retry_max_elapsed.or(lower.retry_max_elapsed),
+            poll_min: self.poll_min.or(lower.poll_min),
+            poll_max: self.poll_max.or(lower.poll_max),
+            download_retry_max: self.download_retry_max.or(lower.download_retry_max),
+            download_retry_sleep_max: self
+                .download_retry_sleep_max
+                .or(lower.download_retry_sleep_max),
+            download_retry_max_elapsed: self
+                .download_retry_max_elapsed
+                .or(lower.download_retry_max_elapsed),
+            progress: self.progress.or(lower.progress),
+            wait_until_complete: self.wait_until_complete.or(lower.wait_until_complete),
+            download_connections: self.download_connections.or(lower.download_connections),
+            webhook: self.webhook.or(lower.webhook),
+            notify_command: self.notify_command.or(lower.notify_command),
+            profiles,
+        }
+    }
+
+    /// Settings for `profile`: its table over the top-level tunables (but never the
+    /// top-level credentials). `None` if the profile is not defined.
+    fn for_profile(mut self, profile: Option<&str>) -> Option<Settings> {
+        let Some(profile) = profile else {
+            return Some(self);
+        };
+        let section = self.profiles.remove(profile)?;
+        self.url = None;
+        self.key = None;
+        Some(section.or(self))
+    }
+}
+
+/// TOML config files, lowest precedence first.
+fn toml_candidates() -> Vec<PathBuf> {
+    let mut v = Vec::new();
+    if let Some(dir) = dirs::config_dir() {
+        v.push(dir.join("cdsapi").join("config.toml"));
+    }
+    if let Ok(cwd) = std::env::current_dir() {
+        v.push(cwd.join("cdsapi.toml"));
+    }
+    v
+}
+
+fn read_settings() -> Result<Settings> {
+    let mut settings = Settings::default();
+    for path in toml_candidates() {
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => continue,
+            Err(e) => {
+                return Err(CdsError::io(
+                    format!("failed to read configuration file {}", path.display()),
+                    e,
+                ));
+            }
+        };
+        let file: Settings = toml::from_str(&text).map_err(|e| {
+            CdsError::Config(format!(
+                "invalid configuration file {}: {}",
+                path.display(),
+                e
+            ))
+        })?;
+        settings = file.or(settings);
+    }
+    Ok(settings)
+}
+
+/// Loads the configuration, using the section named `profile` (or `CDSAPI_PROFILE`)
+/// when one is selected.
+///
+/// Precedence, highest first: explicit arguments, `CDSAPI_URL` / `CDSAPI_KEY`, `./cdsapi.toml`,
+/// `~/.config/cdsapi/config.toml`, then the rc file. A selected profile takes precedence over
+/// `CDSAPI_URL` / `CDSAPI_KEY`, which only apply to the default (unnamed) section.
+///
+/// Also returns the tunables from the TOML files, for the caller to apply.
 pub(crate) fn load_config(
     url: Option<String>,
     key: Option<String>,
     verify: Option<bool>,
-) -> Result<ClientConfig> {
-    let mut url = url.or_else(|| std::env::var("CDSAPI_URL").ok());
-    let mut key = key.or_else(|| std::env::var("CDSAPI_KEY").ok());
+    profile: Option<&str>,
+) -> Result<(ClientConfig, Settings)> {
+    let env_profile = std::env::var("CDSAPI_PROFILE").ok();
+    let profile = profile
+        .or(env_profile.as_deref())
+        .filter(|p| !p.is_empty() && *p != "default");
+
+    let (mut url, mut key) = match profile {
+        Some(_) => (url, key),
+        None => (
+            url.or_else(|| std::env::var("CDSAPI_URL").ok()),
+            key.or_else(|| std::env::var("CDSAPI_KEY").ok()),
+        ),
+    };
+
+    let (settings, mut profile_found) = match read_settings()?.for_profile(profile) {
+        Some(settings) => (settings, profile.is_some()),
+        None => (Settings::default(), false),
+    };
+    url = url.or_else(|| settings.url.clone());
+    key = key.or_else(|| settings.key.clone());
+    let verify = verify.or(settings.verify);
 
     let rc_candidates = rc_candidates();
     let mut file_verify: Option<bool> = None;
+    let mut file_quiet: Option<bool> = None;
+    let mut file_debug: Option<bool> = None;
+    let mut file_proxy: Option<String> = None;
+    let mut file_tls: (Option<PathBuf>, Option<PathBuf>, Option<PathBuf>) = (None, None, None);
 
-    if url.is_none() || key.is_none() || verify.is_none() {
-        for rc_path in &rc_candidates {
-            if rc_path.exists() {
-                let cfg = read_rc(rc_path).with_context(|| {
-                    format!("failed to read configuration file {}", rc_path.display())
-                })?;
+    // Read even when url and key are known, for its other keys.
+    for rc_path in &rc_candidates {
+        if rc_path.exists() {
+            let cfg = read_rc(rc_path, profile).map_err(|e| {
+                CdsError::io(
+                    format!("failed to read configuration file {}", rc_path.display()),
+                    e,
+                )
+            })?;
+            if !cfg.found {
+                continue;
+            }
+            profile_found = true;
 
-                if url.is_none() {
-                    url = cfg.url;
-                }
-                if key.is_none() {
-                    key = cfg.key;
-                }
-                file_verify = cfg.verify;
-                break;
+            if url.is_none() {
+                url = cfg.url;
+            }
+            if key.is_none() {
+                key = cfg.key;
             }
+            file_verify = cfg.verify;
+            file_quiet = cfg.quiet;
+            file_debug = cfg.debug;
+            file_proxy = cfg.proxy;
+            file_tls = (cfg.ca_bundle, cfg.client_cert, cfg.client_key);
+            break;
+        }
+    }
+
+    #[cfg(feature = "keyring")]
+    if key.is_none() {
+        if let Some(url) = &url {
+            key = crate::credentials::keyring_key(url)?;
+        }
+    }
+
+    if let Some(profile) = profile {
+        if !profile_found {
+            return Err(CdsError::Config(format!(
+                "profile `{}` not found (add a `[profiles.{}]` table to cdsapi.toml or a `[{}]` section to one of: {})",
+                profile,
+                profile,
+                profile,
+                rc_candidates
+                    .iter()
+                    .map(|p| p.display().to_string())
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            )));
         }
     }
 
@@ -44,16 +257,18 @@ pub(crate) fn load_config(
         Some(v) => v,
         None => {
             if !rc_candidates.is_empty() {
-                bail!(
+                return Err(CdsError::Config(format!(
                     "Missing configuration: url (set CDSAPI_URL or put `url:` in one of: {})",
                     rc_candidates
                         .iter()
                         .map(|p| p.display().to_string())
                         .collect::<Vec<_>>()
                         .join(", ")
-                );
+                )));
             }
-            bail!("Missing configuration: url (set CDSAPI_URL or create .cdsapirc)");
+            return Err(CdsError::Config(
+                "Missing configuration: url (set CDSAPI_URL or create .cdsapirc)".to_string(),
+            ));
         }
     };
 
@@ -61,30 +276,81 @@ pub(crate) fn load_config(
         Some(v) => v,
         None => {
             if !rc_candidates.is_empty() {
-                bail!(
+                return Err(CdsError::Config(format!(
                     "Missing configuration: key (set CDSAPI_KEY or put `key:` in one of: {})",
                     rc_candidates
                         .iter()
                         .map(|p| p.display().to_string())
                         .collect::<Vec<_>>()
                         .join(", ")
-                );
+                )));
             }
-            bail!("Missing configuration: key (set CDSAPI_KEY or create .cdsapirc)");
+            return Err(CdsError::Config(
+                "Missing configuration: key (set CDSAPI_KEY or create .cdsapirc)".to_string(),
+            ));
         }
     };
 
+    check_base_url(&url)?;
+    let key = sanitize_key(&key);
+    check_key(&key)?;
     let verify = verify.or(file_verify).unwrap_or(true);
 
-    Ok(ClientConfig { url, key, verify })
+    let config = ClientConfig {
+        url,
+        key,
+        verify,
+        quiet: settings.quiet.or(file_quiet).unwrap_or(false),
+        debug: settings.debug.or(file_debug).unwrap_or(false),
+        proxy: settings.proxy.clone().or(file_proxy),
+        ca_bundle: settings.ca_bundle.clone().or(file_tls.0),
+        client_cert: settings.client_cert.clone().or(file_tls.1),
+        client_key: settings.client_key.clone().or(file_tls.2),
+    };
+    Ok((config, settings))
 }
 
-fn read_rc(path: &Path) -> Result<RcConfig> {
+/// Removes whitespace and invisible characters picked up when copy-pasting a key, e.g. a
+/// trailing newline or a token wrapped over two lines.
+fn sanitize_key(key: &str) -> String {
+    key.chars()
+        .filter(|c| !c.is_whitespace() && !matches!(c, '\u{feff}' | '\u{200b}'))
+        .collect()
+}
+
+/// Rejects keys that cannot be valid, before they are sent to the server.
+pub(crate) fn check_key(key: &str) -> Result<()> {
+    let problem = if key.is_empty() {
+        "is empty"
+    } else if key.chars().any(char::is_whitespace) {
+        "contains whitespace or line breaks"
+    } else if key.chars().any(|c| c.is_control() || !c.is_ascii()) {
+        "contains control or non-ASCII characters"
+    } else {
+        return Ok(());
+    };
+    Err(CdsError::InvalidKey {
+        message: format!(
+            "The API key {}.\nHow to fix:\n1) Copy the Personal Access Token again from your profile page (https://cds.climate.copernicus.eu/profile)\n2) Put it on one line as `key: <token>` in .cdsapirc, or set CDSAPI_KEY",
+            problem
+        ),
+        expired: false,
+        response: None,
+    })
+}
+
+/// Reads the keys of section `profile` (`[name]`), or of the lines before any section
+/// header (or `[default]`) when `profile` is `None`.
+fn read_rc(path: &Path, profile: Option<&str>) -> std::io::Result<RcConfig> {
     let text = std::fs::read_to_string(path)?;
-    let mut cfg = RcConfig::default();
+    let mut cfg = RcConfig {
+        found: profile.is_none(),
+        ..RcConfig::default()
+    };
 
     // Support formatting where `key:` is on one line and the token is on the next line.
     let mut pending_key: Option<&str> = None;
+    let mut in_section = profile.is_none();
 
     for raw in text.lines() {
         let line = raw.trim();
@@ -92,6 +358,20 @@ fn read_rc(path: &Path) -> Result<RcConfig> {
             continue;
         }
 
+        if line.starts_with('[') && line.ends_with(']') {
+            let name = line[1..line.len() - 1].trim();
+            in_section = match profile {
+                Some(p) => name == p,
+                None => name == "default",
+            };
+            cfg.found |= in_section;
+            pending_key = None;
+            continue;
+        }
+        if !in_section {
+            continue;
+        }
+
         if let Some(pk) = pending_key {
             // Continuation value line (no colon)
             if !line.contains(':') {
@@ -125,10 +405,26 @@ fn read_rc(path: &Path) -> Result<RcConfig> {
                         pending_key = Some("key");
                     }
                 }
-                "verify" => {
-                    if !v.is_empty() {
-                        cfg.verify = Some(v != "0");
-                    }
+                "verify" if !v.is_empty() => {
+                    cfg.verify = Some(parse_bool(v));
+                }
+                "quiet" if !v.is_empty() => {
+                    cfg.quiet = Some(parse_bool(v));
+                }
+                "debug" if !v.is_empty() => {
+                    cfg.debug = Some(parse_bool(v));
+                }
+                "proxy" if !v.is_empty() => {
+                    cfg.proxy = Some(v.to_string());
+                }
+                "ca_bundle" if !v.is_empty() => {
+                    cfg.ca_bundle = Some(PathBuf::from(v));
+                }
+                "client_cert" if !v.is_empty() => {
+                    cfg.client_cert = Some(PathBuf::from(v));
+                }
+                "client_key" if !v.is_empty() => {
+                    cfg.client_key = Some(PathBuf::from(v));
                 }
                 _ => {}
             }
@@ -138,6 +434,53 @@ fn read_rc(path: &Path) -> Result<RcConfig> {
     Ok(cfg)
 }
 
+/// `url` and `key` of the default section of an rc file.
+#[cfg(feature = "keyring")]
+pub(crate) fn read_rc_file(path: &Path) -> Result<(Option<String>, Option<String>)> {
+    let cfg = read_rc(path, None).map_err(|e| {
+        CdsError::io(
+            format!("failed to read configuration file {}", path.display()),
+            e,
+        )
+    })?;
+    Ok((cfg.url, cfg.key))
+}
+
+/// Removes the `key:` entry (and a value on the following line) from the default section.
+#[cfg(feature = "keyring")]
+pub(crate) fn strip_rc_key(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut in_default = true;
+    let mut skip_value = false;
+    for raw in text.lines() {
+        let line = raw.trim();
+        if line.starts_with('[') && line.ends_with(']') {
+            in_default = line[1..line.len() - 1].trim() == "default";
+        } else if in_default {
+            if skip_value {
+                skip_value = false;
+                if !line.is_empty() && !line.contains(':') {
+                    continue;
+                }
+            }
+            if let Some(("key", v)) = line.split_once(':').map(|(k, v)| (k.trim(), v.trim())) {
+                skip_value = v.is_empty();
+                continue;
+            }
+        }
+        out.push_str(raw);
+        out.push('\n');
+    }
+    out
+}
+
+fn parse_bool(v: &str) -> bool {
+    !matches!(
+        v.to_ascii_lowercase().as_str(),
+        "0" | "false" | "no" | "off"
+    )
+}
+
 fn strip_quotes(s: &str) -> &str {
     let s = s.trim();
     if (s.starts_with('"') && s.ends_with('"') && s.len() >= 2)
@@ -0,0 +1,171 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+use std::fmt;
+
+use crate::client::Client;
+use crate::error::{CdsError, Result};
+use crate::util::value_strings;
+
+/// Valid value combinations published for a dataset.
+///
+/// Each entry maps request fields to the values that may be combined with each other.
+/// A request is valid if, for every selected value, some entry allows it together with
+/// at least one of the selected values of every other constrained field.
+#[derive(Debug, Clone, Default)]
+pub struct Constraints {
+    entries: Vec<BTreeMap<String, BTreeSet<String>>>,
+}
+
+/// Why a request value was rejected by [`Constraints::validate`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ViolationKind {
+    /// The value does not appear in any constraint for this field.
+    UnknownValue,
+    /// The value exists, but not in combination with the other selected values.
+    InvalidCombination,
+}
+
+/// A single request value rejected by the dataset constraints.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConstraintViolation {
+    /// Request field, e.g. `pressure_level`.
+    pub field: String,
+    /// Offending value.
+    pub value: String,
+    pub kind: ViolationKind,
+    /// Values accepted for this field by any constraint.
+    pub allowed: Vec<String>,
+}
+
+impl fmt::Display for ConstraintViolation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.kind {
+            ViolationKind::UnknownValue => write!(
+                f,
+                "{}: value {:?} is not available for this dataset",
+                self.field, self.value
+            ),
+            ViolationKind::InvalidCombination => write!(
+                f,
+                "{}: value {:?} is not available in combination with the other selected values",
+                self.field, self.value
+            ),
+        }
+    }
+}
+
+impl Constraints {
+    /// Builds constraints from the JSON published by CDS (an array of field -> values maps).
+    pub fn from_json(value: &Value) -> Result<Self> {
+        let raw: Vec<BTreeMap<String, Value>> = serde_json::from_value(value.clone())
+            .map_err(|e| CdsError::json("invalid constraints document", e))?;
+        let entries = raw
+            .into_iter()
+            .map(|entry| {
+                entry
+                    .into_iter()
+                    .map(|(k, v)| (k, value_strings(&v).into_iter().collect()))
+                    .collect()
+            })
+            .collect();
+        Ok(Self { entries })
+    }
+
+    /// Fields covered by at least one constraint.
+    pub fn fields(&self) -> BTreeSet<&str> {
+        self.entries
+            .iter()
+            .flat_map(|e| e.keys().map(String::as_str))
+            .collect()
+    }
+
+    /// Values accepted for `field` by any constraint.
+    pub fn allowed_values(&self, field: &str) -> BTreeSet<&str> {
+        self.entries
+            .iter()
+            .filter_map(|e| e.get(field))
+            .flat_map(|vals| vals.iter().map(String::as_str))
+            .collect()
+    }
+
+    /// Checks `request` and returns every rejected value (empty if the request is valid).
+    ///
+    /// Fields that are not covered by any constraint (e.g. `data_format`, `area`) are ignored.
+    pub fn validate<T: Serialize>(&self, request: &T) -> Result<Vec<ConstraintViolation>> {
+        let request = serde_json::to_value(request)
+            .map_err(|e| CdsError::json("failed to serialize request", e))?;
+        let constrained = self.fields();
+
+        let selected: BTreeMap<&str, Vec<String>> = match &request {
+            Value::Object(map) => map
+                .iter()
+                .filter(|(k, _)| constrained.contains(k.as_str()))
+                .map(|(k, v)| (k.as_str(), value_strings(v)))
+                .collect(),
+            _ => BTreeMap::new(),
+        };
+
+        let mut out = Vec::new();
+        for (field, values) in &selected {
+            let allowed = self.allowed_values(field);
+            for value in values {
+                let kind = if !allowed.contains(value.as_str()) {
+                    ViolationKind::UnknownValue
+                } else if !self.combination_allowed(field, value, &selected) {
+                    ViolationKind::InvalidCombination
+                } else {
+                    continue;
+                };
+                out.push(ConstraintViolation {
+                    field: field.to_string(),
+                    value: value.clone(),
+                    kind,
+                    allowed: allowed.iter().map(|s| s.to_string()).collect(),
+                });
+            }
+        }
+        Ok(out)
+    }
+
+    fn combination_allowed(
+        &self,
+        field: &str,
+        value: &str,
+        selected: &BTreeMap<&str, Vec<String>>,
+    ) -> bool {
+        self.entries.iter().any(|entry| {
+            entry.get(field).is_some_and(|vals| vals.contains(value))
+                && selected.iter().all(|(other, other_values)| {
+                    *other == field
+                        || entry
+                            .get(*other)
+                            .is_none_or(|vals| other_values.iter().any(|v| vals.contains(v)))
+                })
+        })
+    }
+}
+
+impl Client {
+    /// Fetches the published constraints for `dataset`.
+    pub fn constraints(&self, dataset: &str) -> Result<Constraints> {
+        let url = format!(
+            "{}/collections/{}/constraints.json",
+            self.catalogue_base(),
+            dataset
+        );
+        let doc: Value = self.api_json::<Value, Value>("GET", &url, &Value::Null)?;
+        Constraints::from_json(&doc)
+    }
+
+    /// Validates `request` against the constraints of `dataset` before submitting it.
+    ///
+    /// Returns the list of rejected values; an empty list means the request is valid.
+    pub fn validate_request<T: Serialize>(
+        &self,
+        dataset: &str,
+        request: &T,
+    ) -> Result<Vec<ConstraintViolation>> {
+        self.constraints(dataset)?.validate(request)
+    }
+}
@@ -0,0 +1,54 @@
+use std::path::Path;
+
+use crate::config::{read_rc_file, strip_rc_key};
+use crate::error::{CdsError, Result};
+
+/// Keyring entry for `url`: service `cdsapi://<url>`, user `key`.
+fn entry(url: &str) -> Result<keyring::Entry> {
+    let service = format!("cdsapi://{}", url.trim_end_matches('/'));
+    keyring::Entry::new(&service, "key").map_err(keyring_error)
+}
+
+fn keyring_error(e: keyring::Error) -> CdsError {
+    CdsError::Config(format!("OS keyring error: {}", e))
+}
+
+/// API key stored in the OS keyring for `url`, if any.
+pub fn keyring_key(url: &str) -> Result<Option<String>> {
+    match entry(url)?.get_password() {
+        Ok(key) => Ok(Some(key)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(keyring_error(e)),
+    }
+}
+
+/// Stores `key` in the OS keyring as the API key for `url`.
+pub fn set_keyring_key(url: &str, key: &str) -> Result<()> {
+    entry(url)?.set_password(key).map_err(keyring_error)
+}
+
+/// Removes the API key for `url` from the OS keyring.
+pub fn delete_keyring_key(url: &str) -> Result<()> {
+    match entry(url)?.delete_credential() {
+        Ok(()) | Err(keyring::Error::NoEntry) => Ok(()),
+        Err(e) => Err(keyring_error(e)),
+    }
+}
+
+/// Moves the key of an rc file (default section) into the OS keyring and removes it from the
+/// file, leaving `url:` and the other settings in place.
+///
+/// Returns `false` if the file has no key to migrate.
+pub fn migrate_rc_to_keyring(path: &Path) -> Result<bool> {
+    let (url, key) = read_rc_file(path)?;
+    let (Some(url), Some(key)) = (url, key) else {
+        return Ok(false);
+    };
+    set_keyring_key(&url, &key)?;
+
+    let text = std::fs::read_to_string(path)
+        .map_err(|e| CdsError::io(format!("failed to read {}", path.display()), e))?;
+    std::fs::write(path, strip_rc_key(&text))
+        .map_err(|e| CdsError::io(format!("failed to write {}", path.display()), e))?;
+    Ok(true)
+}
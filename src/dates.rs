@@ -0,0 +1,200 @@
+#[cfg(feature = "chrono")]
+use chrono::{Datelike, NaiveDate};
+use serde::Serialize;
+#[cfg(feature = "chrono")]
+use serde_json::Value;
+#[cfg(feature = "chrono")]
+use std::collections::BTreeSet;
+#[cfg(feature = "chrono")]
+use std::ops::RangeInclusive;
+
+/// Values of a request's `time` field, e.g. `["00:00", "03:00", ...]`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(transparent)]
+pub struct TimeSteps(Vec<String>);
+
+impl TimeSteps {
+    /// Every `step` hours from 00:00, e.g. `hourly(3)` gives 00:00, 03:00, ..., 21:00.
+    pub fn hourly(step: u8) -> Self {
+        Self::at((0..24).step_by(step.max(1) as usize))
+    }
+
+    /// All 24 hours.
+    pub fn all() -> Self {
+        Self::hourly(1)
+    }
+
+    /// The given hours (0 to 23).
+    pub fn at(hours: impl IntoIterator<Item = u8>) -> Self {
+        Self(hours.into_iter().map(|h| format!("{:02}:00", h)).collect())
+    }
+
+    pub fn as_slice(&self) -> &[String] {
+        &self.0
+    }
+}
+
+impl From<TimeSteps> for Vec<String> {
+    fn from(steps: TimeSteps) -> Self {
+        steps.0
+    }
+}
+
+/// A set of days, turned into a request's `year`/`month`/`day` fields.
+///
+/// The CDS combines these fields as a cartesian product, so a range spanning several months
+/// selects some extra days (e.g. 30 January to 2 February also selects 1-2 January). Check
+/// [`DateRange::is_exact`] and use [`DateRange::by_month`] to split such ranges.
+#[cfg(feature = "chrono")]
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DateRange {
+    dates: Vec<NaiveDate>,
+}
+
+#[cfg(feature = "chrono")]
+impl DateRange {
+    /// Every day from the start to the end of `range`, leap days included.
+    pub fn daily(range: RangeInclusive<NaiveDate>) -> Self {
+        let (start, end) = range.into_inner();
+        Self::from_dates(start.iter_days().take_while(|d| *d <= end))
+    }
+
+    /// The given days, deduplicated and sorted.
+    pub fn from_dates(dates: impl IntoIterator<Item = NaiveDate>) -> Self {
+        let dates: BTreeSet<NaiveDate> = dates.into_iter().collect();
+        Self {
+            dates: dates.into_iter().collect(),
+        }
+    }
+
+    pub fn dates(&self) -> &[NaiveDate] {
+        &self.dates
+    }
+
+    /// Distinct years, e.g. `["2023", "2024"]`.
+    pub fn years(&self) -> Vec<String> {
+        self.distinct(|d| d.year().to_string())
+    }
+
+    /// Distinct months, zero-padded (`"01"` to `"12"`).
+    pub fn months(&self) -> Vec<String> {
+        self.distinct(|d| format!("{:02}", d.month()))
+    }
+
+    /// Distinct days of the month, zero-padded (`"01"` to `"31"`).
+    pub fn days(&self) -> Vec<String> {
+        self.distinct(|d| format!("{:02}", d.day()))
+    }
+
+    /// Whether `year` x `month` x `day` selects exactly these days and no others.
+    pub fn is_exact(&self) -> bool {
+        let (years, months, days) = (self.years(), self.months(), self.days());
+        let mut selected = 0;
+        for y in &years {
+            for m in &months {
+                for d in &days {
+                    // Combinations that are not valid dates (30 February) select nothing.
+                    let (y, m, d) = (y.parse(), m.parse(), d.parse());
+                    if let (Ok(y), Ok(m), Ok(d)) = (y, m, d) {
+                        selected += usize::from(NaiveDate::from_ymd_opt(y, m, d).is_some());
+                    }
+                }
+            }
+        }
+        selected == self.dates.len()
+    }
+
+    /// Splits into one range per day.
+    pub fn by_day(&self) -> Vec<DateRange> {
+        self.group_by(|d| (d.year(), d.month(), d.day()))
+    }
+
+    /// Splits into one range per calendar month, each of which [is exact](Self::is_exact).
+    pub fn by_month(&self) -> Vec<DateRange> {
+        self.group_by(|d| (d.year(), d.month(), 0))
+    }
+
+    /// Splits into one range per calendar year.
+    pub fn by_year(&self) -> Vec<DateRange> {
+        self.group_by(|d| (d.year(), 0, 0))
+    }
+
+    /// `start/end` for datasets with a single `date` field, e.g. `2024-01-01/2024-01-31`.
+    pub fn to_date_string(&self) -> Option<String> {
+        Some(format!(
+            "{}/{}",
+            self.dates.first()?.format("%Y-%m-%d"),
+            self.dates.last()?.format("%Y-%m-%d")
+        ))
+    }
+
+    /// Sets the `year`, `month` and `day` fields of a JSON request object.
+    pub fn apply_to(&self, request: &mut Value) {
+        if let Value::Object(map) = request {
+            map.insert("year".to_string(), self.years().into());
+            map.insert("month".to_string(), self.months().into());
+            map.insert("day".to_string(), self.days().into());
+        }
+    }
+
+    fn group_by(&self, key: impl Fn(&NaiveDate) -> (i32, u32, u32)) -> Vec<DateRange> {
+        let mut ranges: Vec<DateRange> = Vec::new();
+        for date in &self.dates {
+            match ranges.last_mut() {
+                Some(range) if key(&range.dates[0]) == key(date) => range.dates.push(*date),
+                _ => ranges.push(DateRange { dates: vec![*date] }),
+            }
+        }
+        ranges
+    }
+
+    fn distinct(&self, f: impl Fn(&NaiveDate) -> String) -> Vec<String> {
+        let values: BTreeSet<String> = self.dates.iter().map(f).collect();
+        values.into_iter().collect()
+    }
+}
+
+#[cfg(all(test, feature = "chrono"))]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn is_exact_detects_extra_days() {
+        assert!(DateRange::daily(date(2024, 1, 1)..=date(2024, 1, 31)).is_exact());
+        // 1-31 of February selects only 1-29 in a leap year.
+        assert!(DateRange::daily(date(2024, 1, 1)..=date(2024, 2, 29)).is_exact());
+        assert!(!DateRange::daily(date(2024, 1, 30)..=date(2024, 2, 2)).is_exact());
+        assert!(!DateRange::from_dates([date(2023, 5, 1), date(2024, 6, 1)]).is_exact());
+    }
+
+    #[test]
+    fn by_month_splits_into_exact_ranges() {
+        let range = DateRange::daily(date(2023, 12, 30)..=date(2024, 2, 2));
+        let months = range.by_month();
+        assert_eq!(months.len(), 3);
+        assert!(months.iter().all(DateRange::is_exact));
+        assert_eq!(months[0].dates(), [date(2023, 12, 30), date(2023, 12, 31)]);
+        assert_eq!(months[1].dates().len(), 31);
+        assert_eq!(months[2].days(), ["01", "02"]);
+    }
+
+    #[test]
+    fn apply_to_sets_date_fields() {
+        let mut request = json!({"variable": ["2m_temperature"], "year": ["1999"]});
+        DateRange::daily(date(2024, 1, 30)..=date(2024, 2, 2)).apply_to(&mut request);
+        assert_eq!(
+            request,
+            json!({
+                "variable": ["2m_temperature"],
+                "year": ["2024"],
+                "month": ["01", "02"],
+                "day": ["01", "02", "30", "31"],
+            })
+        );
+    }
+}
@@ -1,2 +1,692 @@
-// Download logic currently lives on `Client::download` in `client.rs`.
-// This module exists to keep the crate structure stable during refactors.
+use reqwest::StatusCode;
+use reqwest::blocking::RequestBuilder;
+use reqwest::blocking::Response;
+use reqwest::header::{
+    ACCEPT_ENCODING, ACCEPT_RANGES, CONTENT_LENGTH, CONTENT_RANGE, CONTENT_TYPE, ETAG,
+    LAST_MODIFIED,
+};
+use std::fs::OpenOptions;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::{Path, PathBuf};
+use std::thread;
+use std::time::SystemTime;
+
+use crate::client::{Client, RemoteFile};
+use crate::error::{CdsError, ErrorResponse, Result, error_from_response};
+use crate::events::CdsEvent;
+use crate::retry::Stall;
+use crate::throttle::RateLimit;
+use crate::util::{content_disposition_filename, guess_filename_from_url};
+
+/// Files smaller than this are always downloaded over a single connection.
+const MIN_SEGMENT_SIZE: u64 = 8 * 1024 * 1024;
+
+/// What [`Client::download`] does when the target file already exists.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum OverwritePolicy {
+    /// Keep the existing file and skip the download.
+    Skip,
+    /// Always download again from scratch.
+    Overwrite,
+    /// Continue a partial file shorter than the remote size; otherwise download again.
+    #[default]
+    Resume,
+    /// Fail with an I/O error of kind [`std::io::ErrorKind::AlreadyExists`].
+    Error,
+}
+
+/// Result of [`Client::download_if_changed`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum DownloadOutcome {
+    /// The file was (re-)downloaded to this path.
+    Downloaded(PathBuf),
+    /// The existing file at this path already matched the remote file.
+    Skipped(PathBuf),
+}
+
+impl DownloadOutcome {
+    /// Path of the local file.
+    pub fn path(&self) -> &Path {
+        match self {
+            Self::Downloaded(p) | Self::Skipped(p) => p,
+        }
+    }
+
+    /// Consumes the outcome and returns the path of the local file.
+    pub fn into_path(self) -> PathBuf {
+        match self {
+            Self::Downloaded(p) | Self::Skipped(p) => p,
+        }
+    }
+}
+
+/// Validators from a `HEAD` request on the remote file.
+#[derive(Debug, Default)]
+struct RemoteMetadata {
+    content_length: Option<u64>,
+    etag: Option<String>,
+    last_modified: Option<SystemTime>,
+}
+
+/// File extension for the result content types the CDS serves.
+pub(crate) fn extension_for(content_type: &str) -> Option<&'static str> {
+    let mime = content_type.split(';').next()?.trim().to_ascii_lowercase();
+    Some(match mime.as_str() {
+        "application/x-grib" | "application/grib" | "application/x-grib2" => "grib",
+        "application/netcdf" | "application/x-netcdf" | "application/x-netcdf4" => "nc",
+        "application/zip" | "application/x-zip-compressed" => "zip",
+        "application/x-tar" => "tar",
+        "application/gzip" | "application/x-gzip" => "gz",
+        "application/json" => "json",
+        "text/csv" => "csv",
+        _ => return None,
+    })
+}
+
+/// `path`, or the first of `stem-1.ext`, `stem-2.ext`, ... that does not exist yet.
+fn unused_path(path: &Path) -> PathBuf {
+    if !path.exists() {
+        return path.to_path_buf();
+    }
+    let stem = path
+        .file_stem()
+        .map(|s| s.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    let ext = path
+        .extension()
+        .map(|e| format!(".{}", e.to_string_lossy()))
+        .unwrap_or_default();
+    (1..)
+        .map(|n| path.with_file_name(format!("{}-{}{}", stem, n, ext)))
+        .find(|candidate| !candidate.exists())
+        .expect("unbounded range")
+}
+
+/// Sidecar file remembering the ETag of the last download of `target`.
+fn etag_path(target: &Path) -> PathBuf {
+    let mut name = target.as_os_str().to_owned();
+    name.push(".etag");
+    PathBuf::from(name)
+}
+
+/// Streaming reader over a remote result, returned by [`RemoteFile::open`].
+///
+/// The HTTP request is sent on the first read. If the connection drops, the reader
+/// reconnects with a range request from the current position, up to the client's retry limit.
+#[derive(Debug)]
+pub struct RemoteReader {
+    client: Client,
+    file: RemoteFile,
+    position: u64,
+    tries: usize,
+    stall: Stall,
+    resp: Option<Response>,
+    limit: Option<RateLimit>,
+}
+
+impl RemoteFile {
+    /// Opens the result for streaming reads through `client` (authentication, retries).
+    pub fn open(&self, client: &Client) -> RemoteReader {
+        RemoteReader {
+            client: client.clone(),
+            file: self.clone(),
+            position: 0,
+            tries: 0,
+            stall: Stall::new(0),
+            resp: None,
+            limit: client.download_rate_limit(),
+        }
+    }
+}
+
+impl RemoteReader {
+    /// Number of bytes returned so far.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+
+    fn retry(&mut self, err: io::Error) -> io::Result<()> {
+        self.resp = None;
+        self.tries += 1;
+        let retry = &self.client.download_retry;
+        let delay = retry.jittered_delay(self.tries);
+        if retry.exhausted(self.tries, self.stall.since(self.position), delay) {
+            return Err(err);
+        }
+        self.client.log_debug(format_args!(
+            "stream interrupted at {} byte(s): {}, resuming",
+            self.position, err
+        ));
+        self.client.sleep(delay).map_err(io::Error::other)
+    }
+
+    /// Fails unless a partial response starts at the current position.
+    fn check_content_range(&self, resp: &Response) -> io::Result<()> {
+        let range = resp
+            .headers()
+            .get(CONTENT_RANGE)
+            .and_then(|v| v.to_str().ok());
+        let expected = format!("bytes {}-", self.position);
+        if range.is_some_and(|r| r.starts_with(&expected)) {
+            return Ok(());
+        }
+        Err(io::Error::other(CdsError::download(
+            format!(
+                "cannot resume at byte {}: server sent Content-Range {}",
+                self.position,
+                range.unwrap_or("(none)")
+            ),
+            None,
+        )))
+    }
+}
+
+impl Read for RemoteReader {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        if buf.is_empty() || self.position >= self.file.content_length {
+            return Ok(0);
+        }
+        loop {
+            if self.resp.is_none() {
+                let mut resp = self
+                    .client
+                    .open_range(&self.file, self.position, None)
+                    .map_err(io::Error::other)?;
+                if self.position > 0 && resp.status() == StatusCode::PARTIAL_CONTENT {
+                    self.check_content_range(&resp)?;
+                } else if self.position > 0 {
+                    // The server ignored the range and sends the whole file: skip what we
+                    // returned already.
+                    self.client.log_debug(format_args!(
+                        "range request ignored, skipping the first {} byte(s)",
+                        self.position
+                    ));
+                    match io::copy(&mut resp.by_ref().take(self.position), &mut io::sink()) {
+                        Ok(n) if n == self.position => {}
+                        Ok(n) => {
+                            let err = io::Error::new(
+                                io::ErrorKind::UnexpectedEof,
+                                format!(
+                                    "stream ended at {} of {} skipped byte(s)",
+                                    n, self.position
+                                ),
+                            );
+                            self.retry(err)?;
+                            continue;
+                        }
+                        Err(e) => {
+                            self.retry(e)?;
+                            continue;
+                        }
+                    }
+                }
+                self.resp = Some(resp);
+            }
+            let resp = self.resp.as_mut().expect("response opened above");
+
+            match resp.read(buf) {
+                Ok(0) => {
+                    // Connection closed before the advertised length: reconnect.
+                    let err = io::Error::new(
+                        io::ErrorKind::UnexpectedEof,
+                        format!(
+                            "stream ended at {} of {} byte(s)",
+                            self.position, self.file.content_length
+                        ),
+                    );
+                    self.retry(err)?;
+                }
+                Ok(n) => {
+                    self.position += n as u64;
+                    self.client
+                        .throttle(self.limit.as_ref(), n as u64)
+                        .map_err(io::Error::other)?;
+                    return Ok(n);
+                }
+                Err(e) if e.kind() == io::ErrorKind::Interrupted => {}
+                Err(e) => self.retry(e)?,
+            }
+        }
+    }
+}
+
+/// Whether `target` is `-`, which stands for standard output.
+pub(crate) fn is_stdout(target: &Path) -> bool {
+    target.as_os_str() == "-"
+}
+
+impl Client {
+    /// Streams `file` to standard output and returns the number of bytes written, so results
+    /// can be piped into `cdo`, `wgrib2` or `gzip` without an intermediate file.
+    ///
+    /// Progress bars and log messages go to stderr and never mix with the data.
+    pub fn download_to_stdout(&self, file: &RemoteFile) -> Result<u64> {
+        let stdout = io::stdout();
+        let mut out = io::BufWriter::new(stdout.lock());
+        let written = self.download_to(file, &mut out)?;
+        out.flush()
+            .map_err(|e| CdsError::io("failed to write to stdout", e))?;
+        Ok(written)
+    }
+
+    /// A `HEAD` request for the file at `url`, asking for its size uncompressed.
+    pub(crate) fn head_file(&self, url: &str) -> RequestBuilder {
+        self.prepare_request(self.http.head(url).header(ACCEPT_ENCODING, "identity"))
+    }
+
+    /// Describes a known result URL so it can be downloaded with the client's authentication,
+    /// retries and resume support (Python's `Client.remote(url)`).
+    ///
+    /// Size and content type come from a `HEAD` request.
+    pub fn remote(&self, url: &str) -> Result<RemoteFile> {
+        let resp = self.robust_request(&self.download_retry, || self.send(self.head_file(url)))?;
+        self.log_debug(format_args!("HEAD {} -> {}", url, resp.status()));
+        if !resp.status().is_success() {
+            return Err(error_from_response(ErrorResponse {
+                status: resp.status(),
+                url: url.to_string(),
+                headers: resp.headers().clone(),
+                body: String::new(),
+            }));
+        }
+
+        let header = |name| resp.headers().get(name).and_then(|v| v.to_str().ok());
+        let content_length = header(CONTENT_LENGTH)
+            .and_then(|v| v.parse().ok())
+            .ok_or_else(|| CdsError::Protocol(format!("missing Content-Length for {}", url)))?;
+        Ok(RemoteFile {
+            location: url.to_string(),
+            content_length,
+            content_type: header(CONTENT_TYPE).map(str::to_string),
+            checksum: None,
+        })
+    }
+
+    /// Where to download `file` when the caller passed `target`: a file named as the server
+    /// suggests inside `target` if it is a directory, or in the working directory if it is
+    /// empty.
+    pub(crate) fn resolve_target(&self, file: &RemoteFile, target: &Path) -> PathBuf {
+        if target.as_os_str().is_empty() || target.is_dir() {
+            let name = self
+                .target_filename(file)
+                .unwrap_or_else(|| "download".to_string());
+            target.join(name)
+        } else {
+            target.to_path_buf()
+        }
+    }
+
+    /// File name for `file`: the `Content-Disposition` filename from a `HEAD` request, since
+    /// signed result URLs often end in opaque hashes, else the last segment of the URL.
+    pub(crate) fn target_filename(&self, file: &RemoteFile) -> Option<String> {
+        let from_header = self
+            .send(self.head_file(&file.location))
+            .ok()
+            .filter(|resp| resp.status().is_success())
+            .and_then(|resp| content_disposition_filename(resp.headers()));
+        from_header.or_else(|| guess_filename_from_url(&file.location))
+    }
+
+    /// Downloads `file` into `dir` (created if needed) under a new name, and returns the
+    /// path written.
+    ///
+    /// The name comes from the server's `Content-Disposition` header or the URL, with an
+    /// extension derived from the content type when it has none. If a file of that name
+    /// already exists, a numeric suffix is added (`data-1.grib`, `data-2.grib`, ...).
+    pub fn download_to_dir(&self, file: &RemoteFile, dir: &Path) -> Result<PathBuf> {
+        std::fs::create_dir_all(dir).map_err(|e| {
+            CdsError::io(format!("failed to create directory {}", dir.display()), e)
+        })?;
+        let mut name = self
+            .target_filename(file)
+            .unwrap_or_else(|| "download".to_string());
+        if Path::new(&name).extension().is_none() {
+            if let Some(ext) = file.content_type.as_deref().and_then(extension_for) {
+                name = format!("{}.{}", name, ext);
+            }
+        }
+        let target = unused_path(&dir.join(name));
+        self.download_with(file, &target, OverwritePolicy::Overwrite)
+    }
+
+    /// Downloads `file` to `target` unless the existing target already matches it.
+    ///
+    /// A `HEAD` request is sent first. The download is skipped when the local size equals the
+    /// remote size, the local file is not older than the remote `Last-Modified`, and the ETag
+    /// recorded by the previous download (in `<target>.etag`) still matches, when the server
+    /// sends those headers.
+    pub fn download_if_changed(&self, file: &RemoteFile, target: &Path) -> Result<DownloadOutcome> {
+        let target = self.resolve_target(file, target);
+        let remote = self.remote_metadata(file);
+
+        if self.is_unchanged(file, &target, &remote) {
+            self.log_info(format_args!(
+                "{} is up to date, skipping download",
+                target.display()
+            ));
+            return Ok(DownloadOutcome::Skipped(target));
+        }
+
+        let target = self.download_with(file, &target, OverwritePolicy::Resume)?;
+        let sidecar = etag_path(&target);
+        match &remote.etag {
+            Some(etag) => std::fs::write(&sidecar, etag)
+                .map_err(|e| CdsError::io(format!("failed to write {}", sidecar.display()), e))?,
+            None => {
+                let _ = std::fs::remove_file(&sidecar);
+            }
+        }
+        Ok(DownloadOutcome::Downloaded(target))
+    }
+
+    fn remote_metadata(&self, file: &RemoteFile) -> RemoteMetadata {
+        let resp = match self.robust_request(&self.download_retry, || {
+            self.send(self.head_file(&file.location))
+        }) {
+            Ok(resp) if resp.status().is_success() => resp,
+            _ => return RemoteMetadata::default(),
+        };
+        let header = |name| {
+            resp.headers()
+                .get(name)
+                .and_then(|v| v.to_str().ok())
+                .map(str::to_string)
+        };
+        RemoteMetadata {
+            content_length: header(CONTENT_LENGTH).and_then(|v| v.parse().ok()),
+            etag: header(ETAG),
+            last_modified: header(LAST_MODIFIED).and_then(|v| httpdate::parse_http_date(&v).ok()),
+        }
+    }
+
+    fn is_unchanged(&self, file: &RemoteFile, target: &Path, remote: &RemoteMetadata) -> bool {
+        let Ok(meta) = std::fs::metadata(target) else {
+            return false;
+        };
+        let size = remote.content_length.unwrap_or(file.content_length);
+        if meta.len() != size {
+            return false;
+        }
+        if let (Some(remote_time), Ok(local_time)) = (remote.last_modified, meta.modified()) {
+            if local_time < remote_time {
+                return false;
+            }
+        }
+        if let Some(etag) = &remote.etag {
+            if let Ok(previous) = std::fs::read_to_string(etag_path(target)) {
+                if previous.trim() != etag {
+                    return false;
+                }
+            }
+        }
+        true
+    }
+
+    /// Checks `file` with a `HEAD` request before downloading it (see
+    /// [`Client::with_download_preflight`]) and returns whether the server accepts range
+    /// requests, or `None` if it does not answer `HEAD`.
+    pub(crate) fn preflight(&self, file: &RemoteFile) -> Result<Option<bool>> {
+        let resp = self.robust_request(&self.download_retry, || {
+            self.send(self.head_file(&file.location))
+        })?;
+        self.log_debug(format_args!("HEAD {} -> {}", file.location, resp.status()));
+        let status = resp.status();
+        if status == StatusCode::METHOD_NOT_ALLOWED || status == StatusCode::NOT_IMPLEMENTED {
+            return Ok(None);
+        }
+        if !status.is_success() {
+            let response = ErrorResponse {
+                status,
+                url: file.location.clone(),
+                headers: resp.headers().clone(),
+                body: String::new(),
+            };
+            return Err(CdsError::download(
+                format!(
+                    "download URL rejected before downloading (HTTP {}); signed result URLs expire, so fetch the job results again",
+                    status
+                ),
+                Some(Box::new(error_from_response(response))),
+            ));
+        }
+        let header = |name| resp.headers().get(name).and_then(|v| v.to_str().ok());
+        if let Some(length) = header(CONTENT_LENGTH).and_then(|v| v.parse::<u64>().ok()) {
+            if length != file.content_length {
+                return Err(CdsError::download(
+                    format!(
+                        "server reports {} bytes for {}, expected {}",
+                        length, file.location, file.content_length
+                    ),
+                    None,
+                ));
+            }
+        }
+        Ok(Some(
+            header(ACCEPT_RANGES).is_some_and(|v| v.eq_ignore_ascii_case("bytes")),
+        ))
+    }
+
+    /// Whether `file` should be fetched as parallel segments. `accepts_ranges` is the
+    /// server's range support when already known from a preflight.
+    pub(crate) fn use_segments(&self, file: &RemoteFile, accepts_ranges: Option<bool>) -> bool {
+        if self.download_connections <= 1 || file.content_length < 2 * MIN_SEGMENT_SIZE {
+            return false;
+        }
+        if let Some(accepts_ranges) = accepts_ranges {
+            return accepts_ranges;
+        }
+        let head = self.robust_request(&self.download_retry, || {
+            self.send(self.head_file(&file.location))
+        });
+        match head {
+            Ok(resp) => resp
+                .headers()
+                .get(ACCEPT_RANGES)
+                .and_then(|v| v.to_str().ok())
+                .is_some_and(|v| v.eq_ignore_ascii_case("bytes")),
+            Err(_) => false,
+        }
+    }
+
+    /// Fails if the file system holding `target` has no room for a file of `size` bytes,
+    /// counting the space the existing target already takes, when the disk space check is
+    /// on. File systems that cannot report free space are not checked.
+    pub(crate) fn check_disk_space(&self, target: &Path, size: u64) -> Result<()> {
+        let existing = std::fs::metadata(target).map(|m| m.len()).unwrap_or(0);
+        let needed = size.saturating_sub(existing);
+        if !self.disk_space_check || needed == 0 {
+            return Ok(());
+        }
+        let dir = match target.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir,
+            _ => Path::new("."),
+        };
+        let available = match fs4::available_space(dir) {
+            Ok(available) => available,
+            Err(e) => {
+                self.log_debug(format_args!(
+                    "cannot tell free space of {}: {}",
+                    dir.display(),
+                    e
+                ));
+                return Ok(());
+            }
+        };
+        if available >= needed {
+            return Ok(());
+        }
+        Err(CdsError::io(
+            format!(
+                "not enough space to download {}: {} byte(s) needed, {} available",
+                target.display(),
+                needed,
+                available
+            ),
+            io::ErrorKind::StorageFull.into(),
+        ))
+    }
+
+    /// Downloads `file` into `target` as concurrent byte ranges.
+    pub(crate) fn download_segmented(&self, file: &RemoteFile, target: &Path) -> Result<()> {
+        let total = file.content_length;
+        let max_segments = (total / MIN_SEGMENT_SIZE).max(1) as usize;
+        let segments = self.download_connections.min(max_segments) as u64;
+        let segment_len = total.div_ceil(segments);
+
+        let out = OpenOptions::new()
+            .create(true)
+            .write(true)
+            .truncate(true)
+            .open(target)
+            .map_err(|e| CdsError::io(format!("failed to open {}", target.display()), e))?;
+        out.set_len(total)?;
+        drop(out);
+
+        let pb = self.progress.then(|| self.reporter.0.clone());
+        if let Some(pb) = &pb {
+            pb.started(&file.location, total, 0);
+        }
+
+        let limit = self.download_rate_limit();
+        let limit = limit.as_ref();
+        thread::scope(|scope| {
+            let handles: Vec<_> = (0..segments)
+                .map(|i| {
+                    let start = i * segment_len;
+                    let end = ((i + 1) * segment_len).min(total) - 1;
+                    scope.spawn(move || self.download_segment(file, target, start, end, limit))
+                })
+                .collect();
+            handles
+                .into_iter()
+                .map(|h| {
+                    h.join().unwrap_or_else(|_| {
+                        Err(CdsError::download("download thread panicked", None))
+                    })
+                })
+                .collect::<Result<Vec<()>>>()
+        })?;
+
+        if let Some(pb) = &pb {
+            pb.finished(&file.location);
+        }
+        Ok(())
+    }
+
+    /// Fetches bytes `start..=end` into `target`, resuming the segment on interruptions.
+    fn download_segment(
+        &self,
+        file: &RemoteFile,
+        target: &Path,
+        start: u64,
+        end: u64,
+        limit: Option<&RateLimit>,
+    ) -> Result<()> {
+        let mut out = OpenOptions::new()
+            .write(true)
+            .open(target)
+            .map_err(|e| CdsError::io(format!("failed to open {}", target.display()), e))?;
+        let mut position = start;
+        let mut tries = 0usize;
+        let mut stall = Stall::new(position);
+        let mut buf = [0u8; 64 * 1024];
+
+        while position <= end {
+            let mut resp = self.open_range(file, position, Some(end))?;
+            if resp.status() != reqwest::StatusCode::PARTIAL_CONTENT {
+                return Err(CdsError::download(
+                    format!("server ignored range request (HTTP {})", resp.status()),
+                    None,
+                ));
+            }
+            out.seek(SeekFrom::Start(position))?;
+
+            let interrupted = loop {
+                let want = buf.len().min((end + 1 - position) as usize);
+                if want == 0 {
+                    break None;
+                }
+                match resp.read(&mut buf[..want]) {
+                    Ok(0) => {
+                        break Some(io::Error::new(
+                            io::ErrorKind::UnexpectedEof,
+                            "segment ended early",
+                        ));
+                    }
+                    Ok(n) => {
+                        out.write_all(&buf[..n])?;
+                        self.check_interrupted()?;
+                        position += n as u64;
+                        if self.progress {
+                            self.reporter.0.advanced(&file.location, n as u64);
+                        }
+                        self.record_bytes(n as u64);
+                        self.emit(|| CdsEvent::DownloadProgress {
+                            location: file.location.clone(),
+                            bytes: n as u64,
+                            total: file.content_length,
+                        });
+                        self.throttle(limit, n as u64)?;
+                    }
+                    Err(e) => break Some(e),
+                }
+            };
+
+            if let Some(e) = interrupted {
+                tries += 1;
+                self.record_resume();
+                let delay = self.download_retry.jittered_delay(tries);
+                if self
+                    .download_retry
+                    .exhausted(tries, stall.since(position), delay)
+                {
+                    return Err(CdsError::download(
+                        "download interrupted",
+                        Some(Box::new(e)),
+                    ));
+                }
+                self.log_debug(format_args!(
+                    "segment {}-{} interrupted at {}: {}, resuming",
+                    start, end, position, e
+                ));
+                self.sleep(delay)?;
+            }
+        }
+
+        out.flush()?;
+        Ok(())
+    }
+
+    /// Flushes a finished download (and its checksum sidecar) and the directory holding it
+    /// to disk, when [`Client::with_fsync`] is on.
+    pub(crate) fn sync_download(&self, target: &Path, sidecar: Option<&Path>) -> Result<()> {
+        if !self.fsync {
+            return Ok(());
+        }
+        for path in std::iter::once(target).chain(sidecar) {
+            OpenOptions::new()
+                .write(true)
+                .open(path)
+                .and_then(|f| f.sync_all())
+                .map_err(|e| CdsError::io(format!("failed to sync {}", path.display()), e))?;
+        }
+        match target.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => sync_dir(dir),
+            _ => sync_dir(Path::new(".")),
+        }
+    }
+}
+
+/// Makes new entries in `dir` durable. Directories cannot be synced on Windows, where
+/// this does nothing.
+#[cfg(unix)]
+fn sync_dir(dir: &Path) -> Result<()> {
+    std::fs::File::open(dir)
+        .and_then(|d| d.sync_all())
+        .map_err(|e| CdsError::io(format!("failed to sync {}", dir.display()), e))
+}
+
+#[cfg(not(unix))]
+fn sync_dir(_dir: &Path) -> Result<()> {
+    Ok(())
+}
@@ -1,30 +1,316 @@
-use anyhow::anyhow;
 use reqwest::StatusCode;
+use reqwest::header::HeaderMap;
 
-#[derive(Debug, serde::Deserialize)]
-pub(crate) struct CdsErrorResponse {
+/// Result type used by all public APIs of this crate.
+pub type Result<T, E = CdsError> = std::result::Result<T, E>;
+
+/// Errors returned by this crate.
+///
+/// `CdsError` implements [`std::error::Error`] + `Send` + `Sync`, so `?` converts it into
+/// `anyhow::Error` (or any other boxed error) without extra glue.
+#[derive(Debug, thiserror::Error)]
+#[non_exhaustive]
+pub enum CdsError {
+    /// Authentication or authorization failed (HTTP 401/403).
+    #[error("{message}")]
+    Auth {
+        message: String,
+        response: Box<ErrorResponse>,
+    },
+    /// The API key is malformed, or the server reports that it has expired. `message`
+    /// explains how to fix it.
+    #[error("{message}")]
+    InvalidKey {
+        message: String,
+        /// Whether the server reported the key as expired.
+        expired: bool,
+        /// The server's reply, if the key was rejected by the server.
+        response: Option<Box<ErrorResponse>>,
+    },
+    /// The dataset licence(s) have not been accepted for this account.
+    #[error("{message}")]
+    LicenceNotAccepted {
+        /// Page where the licence can be accepted.
+        link: String,
+        message: String,
+        response: Box<ErrorResponse>,
+    },
+    /// The endpoint or resource does not exist (HTTP 404).
+    #[error("{message}")]
+    NotFound {
+        message: String,
+        response: Box<ErrorResponse>,
+    },
+    /// The server rejected the call because of rate or queue limits.
+    #[error("{message}")]
+    QuotaExceeded {
+        message: String,
+        response: Box<ErrorResponse>,
+    },
+    /// The job was processed but ended in a failed state.
+    #[error("{message}")]
+    JobFailed {
+        message: String,
+        /// Server-side log lines, when available.
+        logs: Vec<String>,
+    },
+    /// Downloading the result file failed.
+    #[error("{message}")]
+    Download {
+        message: String,
+        #[source]
+        source: Option<Box<dyn std::error::Error + Send + Sync>>,
+    },
+    /// The downloaded file does not match the checksum published by the server.
+    #[error("checksum mismatch for {}: expected {expected}, got {actual}", path.display())]
+    Integrity {
+        path: std::path::PathBuf,
+        expected: String,
+        actual: String,
+    },
+    /// The deadline set with [`Client::with_deadline`](crate::Client::with_deadline) passed.
+    #[error("deadline of {deadline:?} exceeded")]
+    Timeout {
+        deadline: std::time::Duration,
+        /// ID of the submitted job, if the deadline passed after submission.
+        job_id: Option<String>,
+    },
+    /// The call was cancelled through a [`CancellationToken`](crate::CancellationToken).
+    #[error("request cancelled")]
+    Cancelled {
+        /// ID of the submitted job, if cancellation happened after submission.
+        job_id: Option<String>,
+    },
+    /// The job was submitted but has not finished, and
+    /// [`wait_until_complete`](crate::Client::with_wait_until_complete) is off. Follow it
+    /// later with [`Client::job`](crate::Client::job), or use
+    /// [`Client::retrieve_nowait`](crate::Client::retrieve_nowait) to get the handle instead.
+    #[error("job {job_id} is {status}; not waiting for it to complete")]
+    Pending {
+        job_id: String,
+        status: crate::protocol::JobStatus,
+    },
+    /// The legacy API answered 404 at the configured URL, and its `/api/v2` variant failed
+    /// too. [`CdsError::response`] and [`CdsError::status`] refer to the configured URL.
+    #[error("{primary}; the /api/v2 variant {fallback_url} failed too: {fallback}")]
+    BaseFallback {
+        primary: Box<CdsError>,
+        fallback_url: String,
+        #[source]
+        fallback: Box<CdsError>,
+    },
+    /// Missing or invalid configuration.
+    #[error("{0}")]
+    Config(String),
+    /// Any other unsuccessful HTTP response.
+    #[error("{message}")]
+    Http {
+        message: String,
+        response: Box<ErrorResponse>,
+    },
+    /// The HTTP request could not be sent or the connection failed.
+    #[error("{context}")]
+    Transport {
+        context: String,
+        #[source]
+        source: reqwest::Error,
+    },
+    /// A local I/O operation failed.
+    #[error("{context}")]
+    Io {
+        context: String,
+        #[source]
+        source: std::io::Error,
+    },
+    /// A JSON payload could not be (de)serialized.
+    #[error("{context}")]
+    Json {
+        context: String,
+        #[source]
+        source: serde_json::Error,
+    },
+    /// The server replied with something this client does not understand.
+    #[error("{0}")]
+    Protocol(String),
+}
+
+impl CdsError {
+    pub(crate) fn io(context: impl Into<String>, source: std::io::Error) -> Self {
+        Self::Io {
+            context: context.into(),
+            source,
+        }
+    }
+
+    pub(crate) fn transport(context: impl Into<String>, source: reqwest::Error) -> Self {
+        Self::Transport {
+            context: context.into(),
+            source,
+        }
+    }
+
+    pub(crate) fn json(context: impl Into<String>, source: serde_json::Error) -> Self {
+        Self::Json {
+            context: context.into(),
+            source,
+        }
+    }
+
+    pub(crate) fn download(
+        message: impl Into<String>,
+        source: Option<Box<dyn std::error::Error + Send + Sync>>,
+    ) -> Self {
+        Self::Download {
+            message: message.into(),
+            source,
+        }
+    }
+
+    /// The unsuccessful HTTP response behind this error, if any.
+    pub fn response(&self) -> Option<&ErrorResponse> {
+        match self {
+            Self::Auth { response, .. }
+            | Self::LicenceNotAccepted { response, .. }
+            | Self::NotFound { response, .. }
+            | Self::QuotaExceeded { response, .. }
+            | Self::Http { response, .. } => Some(response),
+            Self::InvalidKey {
+                response: Some(response),
+                ..
+            } => Some(response),
+            Self::Download {
+                source: Some(source),
+                ..
+            } => source
+                .downcast_ref::<CdsError>()
+                .and_then(CdsError::response),
+            Self::BaseFallback { primary, .. } => primary.response(),
+            _ => None,
+        }
+    }
+
+    /// Problem details of the unsuccessful response behind this error, if the server sent
+    /// them.
+    pub fn problem_details(&self) -> Option<ProblemDetails> {
+        self.response()?.problem_details()
+    }
+
+    /// Trace ID of the unsuccessful response behind this error, for CDS support requests.
+    pub fn trace_id(&self) -> Option<String> {
+        self.problem_details()?.trace_id
+    }
+
+    /// HTTP status code associated with this error, if any.
+    pub fn status(&self) -> Option<StatusCode> {
+        match self {
+            Self::Transport { source, .. } => source.status(),
+            Self::BaseFallback { primary, .. } => primary.status(),
+            _ => self.response().map(|r| r.status),
+        }
+    }
+}
+
+impl From<std::io::Error> for CdsError {
+    fn from(source: std::io::Error) -> Self {
+        Self::io("I/O error", source)
+    }
+}
+
+/// An unsuccessful HTTP response, as received from the server.
+#[derive(Debug, Clone)]
+pub struct ErrorResponse {
+    /// HTTP status code.
+    pub status: StatusCode,
+    /// Requested URL.
+    pub url: String,
+    /// Response headers.
+    pub headers: HeaderMap,
+    /// Response body (may be empty).
+    pub body: String,
+}
+
+impl ErrorResponse {
+    /// The body parsed as an RFC 7807 problem details object, if it is one.
+    pub fn problem_details(&self) -> Option<ProblemDetails> {
+        serde_json::from_str(&self.body)
+            .ok()
+            .filter(|p| *p != ProblemDetails::default())
+    }
+}
+
+/// Problem details (RFC 7807) sent by the CDS with unsuccessful responses, available
+/// through [`CdsError::problem_details`].
+///
+/// Quote `trace_id` when contacting CDS support.
+#[derive(Debug, Clone, Default, PartialEq, Eq, serde::Deserialize)]
+#[non_exhaustive]
+pub struct ProblemDetails {
+    /// Problem type (`type`).
     #[serde(default, rename = "type")]
-    pub(crate) kind: Option<String>,
+    pub kind: Option<String>,
     #[serde(default)]
-    pub(crate) title: Option<String>,
+    pub title: Option<String>,
+    /// HTTP status reported in the body.
     #[serde(default)]
-    pub(crate) status: Option<u16>,
+    pub status: Option<u16>,
     #[serde(default)]
-    pub(crate) detail: Option<String>,
+    pub detail: Option<String>,
     #[serde(default)]
-    pub(crate) instance: Option<String>,
+    pub instance: Option<String>,
+    /// ID correlating the request with the server logs.
     #[serde(default)]
-    pub(crate) trace_id: Option<String>,
-    // Some endpoints respond with {"message":...,"detail":...}
+    pub trace_id: Option<String>,
+    /// Message of endpoints that reply with `{"message": ..., "detail": ...}` instead.
     #[serde(default)]
-    pub(crate) message: Option<String>,
+    pub message: Option<String>,
+}
+
+/// Classifies an unsuccessful response into the matching [`CdsError`] variant.
+pub(crate) fn error_from_response(response: ErrorResponse) -> CdsError {
+    // Try to parse CDS error payloads for actionable messages.
+    if let Ok(err_json) = serde_json::from_str::<ProblemDetails>(&response.body) {
+        return format_cds_error(response, &err_json);
+    }
+
+    let message = format!(
+        "API request failed: HTTP {} for url ({})\n{}",
+        response.status, response.url, response.body
+    );
+    if is_expired_key(&response, &response.body) {
+        return expired_key(response);
+    }
+    let response = Box::new(response);
+    match response.status {
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN => CdsError::Auth { message, response },
+        StatusCode::NOT_FOUND => CdsError::NotFound { message, response },
+        StatusCode::TOO_MANY_REQUESTS => CdsError::QuotaExceeded { message, response },
+        _ => CdsError::Http { message, response },
+    }
+}
+
+/// Whether `response` rejects the key as expired, judging by its `text`.
+fn is_expired_key(response: &ErrorResponse, text: &str) -> bool {
+    matches!(
+        response.status,
+        StatusCode::UNAUTHORIZED | StatusCode::FORBIDDEN
+    ) && text.to_lowercase().contains("expired")
 }
 
-pub(crate) fn format_cds_error(
-    status: StatusCode,
-    url: &str,
-    e: &CdsErrorResponse,
-) -> anyhow::Error {
+fn expired_key(response: ErrorResponse) -> CdsError {
+    let message = format!(
+        "The API key has expired (HTTP {}).\nHow to fix:\n1) Sign in and copy your current Personal Access Token from https://cds.climate.copernicus.eu/profile\n2) Replace the `key:` in .cdsapirc (or CDSAPI_KEY) with it\n\nrequest: {}",
+        response.status, response.url
+    );
+    CdsError::InvalidKey {
+        message,
+        expired: true,
+        response: Some(Box::new(response)),
+    }
+}
+
+fn format_cds_error(response: ErrorResponse, e: &ProblemDetails) -> CdsError {
+    let status = response.status;
+    let url = response.url.as_str();
     let title = e.title.as_deref().or(e.message.as_deref()).unwrap_or("");
     let detail = e.detail.as_deref().unwrap_or("");
     let trace = e.trace_id.as_deref().unwrap_or("");
@@ -48,16 +334,25 @@ pub(crate) fn format_cds_error(
                 .to_string();
         }
 
-        return anyhow!(
+        let message = format!(
             "CDS returned 403: required dataset licence(s) have not been accepted.\n\nHow to fix:\n1) Open and sign in: {}\n2) Scroll to the bottom and accept the required licence(s) (Manage licences)\n3) Re-run this program\n\nServer message: {}\ntrace_id: {}",
             link,
             title,
             if trace.is_empty() { "(none)" } else { trace }
         );
+        return CdsError::LicenceNotAccepted {
+            link,
+            message,
+            response: Box::new(response),
+        };
+    }
+
+    if is_expired_key(&response, &format!("{} {}", title, detail)) {
+        return expired_key(response);
     }
 
     if status == StatusCode::UNAUTHORIZED || status == StatusCode::FORBIDDEN {
-        return anyhow!(
+        let message = format!(
             "CDS authentication/authorization failed (HTTP {}).\n- Check that the key in .cdsapirc is a valid Personal Access Token (often WITHOUT the deprecated '<UID>:' prefix)\n- Ensure the token is not expired\n- If dataset licences are not accepted, CDS returns: 403 required licences not accepted\n\nServer message: {}\n{}\nkind: {}\ninstance: {}\ntrace_id: {}\nrequest: {}",
             status_in_body,
             title,
@@ -67,22 +362,40 @@ pub(crate) fn format_cds_error(
             if trace.is_empty() { "(none)" } else { trace },
             url
         );
+        return CdsError::Auth {
+            message,
+            response: Box::new(response),
+        };
     }
 
     if status == StatusCode::NOT_FOUND {
-        return anyhow!(
+        let message = format!(
             "CDS API endpoint not found (HTTP 404).\n- The API path may have changed, or your configured base URL is incorrect\n- Recommended .cdsapirc url: https://cds.climate.copernicus.eu/api\n\nServer message: {}\n{}\nrequest: {}",
-            title,
-            detail,
-            url
+            title, detail, url
+        );
+        return CdsError::NotFound {
+            message,
+            response: Box::new(response),
+        };
+    }
+
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        let message = format!(
+            "CDS rejected the request because of rate or queue limits (HTTP {}).\n{}\n{}\nrequest: {}",
+            status_in_body, title, detail, url
         );
+        return CdsError::QuotaExceeded {
+            message,
+            response: Box::new(response),
+        };
     }
 
-    anyhow!(
+    let message = format!(
         "API request failed: HTTP {} for url ({})\n{}\n{}",
-        status_in_body,
-        url,
-        title,
-        detail
-    )
+        status_in_body, url, title, detail
+    );
+    CdsError::Http {
+        message,
+        response: Box::new(response),
+    }
 }
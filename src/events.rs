@@ -0,0 +1,88 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::mpsc::Sender;
+
+use crate::client::{Client, RemoteFile};
+use crate::job::{Job, JobProgress, JobStatus};
+
+type StateCallback = dyn Fn(Option<&JobStatus>, &JobStatus, &Job) + Send + Sync;
+
+/// Callback registered with [`Client::with_on_state_change`].
+#[derive(Clone, Default)]
+pub(crate) struct OnStateChange(pub(crate) Option<Arc<StateCallback>>);
+
+impl fmt::Debug for OnStateChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(if self.0.is_some() {
+            "state change callback"
+        } else {
+            "no state change callback"
+        })
+    }
+}
+
+/// Lifecycle notifications sent to the channel given to [`Client::with_event_sink`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum CdsEvent {
+    /// A request was accepted by the server.
+    Submitted { job_id: String, dataset: String },
+    /// A poll saw the job in a new state.
+    StateChanged { job_id: String, status: JobStatus },
+    /// A poll of a job, with the progress the server reported.
+    Progress {
+        job_id: String,
+        progress: JobProgress,
+    },
+    /// A log line reported by the server for the job.
+    LogLine { job_id: String, line: String },
+    /// `bytes` more of the file at `location` were received (of `total`).
+    DownloadProgress {
+        location: String,
+        bytes: u64,
+        total: u64,
+    },
+    /// A [`Client::retrieve`] call finished; `path` is where the result was written, if
+    /// anywhere.
+    Completed {
+        job_id: String,
+        file: RemoteFile,
+        path: Option<PathBuf>,
+    },
+    /// A [`Client::retrieve`] call failed.
+    Failed {
+        job_id: Option<String>,
+        message: String,
+    },
+}
+
+impl Client {
+    /// Sends [`CdsEvent`]s to `sink` as requests are submitted, polled and downloaded, so
+    /// TUIs and orchestrators can follow progress without parsing stderr.
+    ///
+    /// Events are dropped silently once the receiver is gone.
+    pub fn with_event_sink(mut self, sink: Sender<CdsEvent>) -> Self {
+        self.events = Some(sink);
+        self
+    }
+
+    /// Calls `callback` with the previous state (`None` on the first poll), the new state and
+    /// the job whenever a poll sees a job change state, e.g. from queued to running to
+    /// successful.
+    ///
+    /// It runs on the polling thread, so it should return quickly.
+    pub fn with_on_state_change(
+        mut self,
+        callback: impl Fn(Option<&JobStatus>, &JobStatus, &Job) + Send + Sync + 'static,
+    ) -> Self {
+        self.on_state_change = OnStateChange(Some(Arc::new(callback)));
+        self
+    }
+
+    pub(crate) fn emit(&self, event: impl FnOnce() -> CdsEvent) {
+        if let Some(sink) = &self.events {
+            let _ = sink.send(event());
+        }
+    }
+}
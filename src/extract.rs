@@ -0,0 +1,142 @@
+use flate2::read::GzDecoder;
+use std::fs::File;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+
+use crate::client::{Client, RemoteFile};
+use crate::error::{CdsError, Result};
+use crate::util::guess_filename_from_url;
+
+/// Archive formats unpacked by [`Client::with_extract`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum ArchiveKind {
+    Zip,
+    Tar,
+    TarGz,
+}
+
+impl ArchiveKind {
+    /// Recognizes an archive from its content type, falling back to the URL's extension.
+    pub(crate) fn detect(file: &RemoteFile) -> Option<Self> {
+        let name = guess_filename_from_url(&file.location)
+            .unwrap_or_default()
+            .to_lowercase();
+        let content_type = file
+            .content_type
+            .as_deref()
+            .and_then(|t| t.split(';').next())
+            .unwrap_or("")
+            .trim()
+            .to_lowercase();
+        match content_type.as_str() {
+            "application/zip" | "application/x-zip-compressed" => return Some(Self::Zip),
+            "application/x-tar" => return Some(Self::Tar),
+            "application/gzip" | "application/x-gzip" | "application/x-compressed-tar"
+                if name.ends_with(".tar.gz") || name.ends_with(".tgz") =>
+            {
+                return Some(Self::TarGz);
+            }
+            _ => {}
+        }
+        if name.ends_with(".zip") {
+            Some(Self::Zip)
+        } else if name.ends_with(".tar") {
+            Some(Self::Tar)
+        } else if name.ends_with(".tar.gz") || name.ends_with(".tgz") {
+            Some(Self::TarGz)
+        } else {
+            None
+        }
+    }
+
+    /// Unpacks `archive` into `dir` and returns the extracted files. Entries that would land
+    /// outside `dir` are skipped.
+    pub(crate) fn extract(self, archive: &Path, dir: &Path) -> Result<Vec<PathBuf>> {
+        let file = File::open(archive)
+            .map_err(|e| CdsError::io(format!("failed to open {}", archive.display()), e))?;
+        let failed = |e: Box<dyn std::error::Error + Send + Sync>| {
+            CdsError::download(format!("failed to extract {}", archive.display()), Some(e))
+        };
+        match self {
+            Self::Zip => extract_zip(file, dir).map_err(failed),
+            Self::Tar => extract_tar(file, dir).map_err(failed),
+            Self::TarGz => extract_tar(GzDecoder::new(file), dir).map_err(failed),
+        }
+    }
+}
+
+type ExtractResult = std::result::Result<Vec<PathBuf>, Box<dyn std::error::Error + Send + Sync>>;
+
+fn extract_zip(file: File, dir: &Path) -> ExtractResult {
+    let mut zip = zip::ZipArchive::new(file)?;
+    let mut paths = Vec::new();
+    for i in 0..zip.len() {
+        let mut entry = zip.by_index(i)?;
+        let Some(name) = entry.enclosed_name() else {
+            continue;
+        };
+        let path = dir.join(name);
+        if entry.is_dir() {
+            std::fs::create_dir_all(&path)?;
+            continue;
+        }
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        std::io::copy(&mut entry, &mut File::create(&path)?)?;
+        paths.push(path);
+    }
+    Ok(paths)
+}
+
+fn extract_tar(reader: impl Read, dir: &Path) -> ExtractResult {
+    let mut tar = tar::Archive::new(reader);
+    let mut paths = Vec::new();
+    for entry in tar.entries()? {
+        let mut entry = entry?;
+        let path = dir.join(entry.path()?);
+        let is_file = entry.header().entry_type().is_file();
+        // `unpack_in` refuses entries escaping `dir` and returns false for them.
+        if entry.unpack_in(dir)? && is_file {
+            paths.push(path);
+        }
+    }
+    Ok(paths)
+}
+
+impl Client {
+    /// Extracts zip and tar (`.tar`, `.tar.gz`) results after download (default `false`).
+    ///
+    /// When a downloaded file is an archive (by content type or extension), the target
+    /// passed to [`Client::download`] or [`Client::retrieve`] is used as a directory: the
+    /// archive is unpacked into it and then deleted. Other files are downloaded as usual.
+    pub fn with_extract(mut self, extract: bool) -> Self {
+        self.extract = extract;
+        self
+    }
+
+    /// Downloads `file` into the directory `dir` and returns the files written: the
+    /// contents of the archive if `file` is a zip or tar archive, else the file itself.
+    pub fn download_and_extract(&self, file: &RemoteFile, dir: &Path) -> Result<Vec<PathBuf>> {
+        std::fs::create_dir_all(dir).map_err(|e| {
+            CdsError::io(format!("failed to create directory {}", dir.display()), e)
+        })?;
+        let name = self
+            .target_filename(file)
+            .unwrap_or_else(|| "download".to_string());
+        let Some(kind) = ArchiveKind::detect(file) else {
+            return Ok(vec![self.download_file(file, &dir.join(name))?]);
+        };
+
+        let archive = self.download_file(file, &dir.join(name))?;
+        let paths = kind.extract(&archive, dir)?;
+        std::fs::remove_file(&archive)
+            .map_err(|e| CdsError::io(format!("failed to remove {}", archive.display()), e))?;
+        self.log_info(format_args!(
+            "extracted {} file(s) into {}",
+            paths.len(),
+            dir.display()
+        ));
+        Ok(paths)
+    }
+}
@@ -0,0 +1,51 @@
+use serde::Serialize;
+use serde_json::Value;
+use std::path::PathBuf;
+
+use crate::error::{CdsError, Result};
+use crate::util::value_strings;
+
+/// Expands `{field}` placeholders in a target path from the fields of `request`, e.g.
+/// `era5_{variable}_{year}{month}.grib` becomes `era5_2m_temperature_202403.grib`.
+///
+/// A field with several values is joined with `-` (`{year}` of `["2023", "2024"]` gives
+/// `2023-2024`). Colons are dropped (`13:00` gives `1300`), and other characters that are
+/// not allowed in file names, such as the `/` of a date range, become `_`. A placeholder
+/// naming a field missing from the request is an error.
+pub fn expand_target_template<T: Serialize>(template: &str, request: &T) -> Result<PathBuf> {
+    let request = serde_json::to_value(request)
+        .map_err(|e| CdsError::json("failed to serialize request", e))?;
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 1..];
+        let end = after.find('}').ok_or_else(|| {
+            CdsError::Config(format!("unterminated placeholder in {:?}", template))
+        })?;
+        let field = &after[..end];
+        let value = request.get(field).filter(|v| !v.is_null()).ok_or_else(|| {
+            CdsError::Config(format!(
+                "target template {:?} uses {{{}}}, which is not in the request",
+                template, field
+            ))
+        })?;
+        out.push_str(&file_name_part(value));
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(PathBuf::from(out))
+}
+
+fn file_name_part(value: &Value) -> String {
+    value_strings(value)
+        .join("-")
+        .chars()
+        .filter(|c| *c != ':')
+        .map(|c| match c {
+            '/' | '\\' | '*' | '?' | '"' | '<' | '>' | '|' => '_',
+            c if c.is_control() || c.is_whitespace() => '_',
+            c => c,
+        })
+        .collect()
+}
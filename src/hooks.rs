@@ -0,0 +1,42 @@
+use reqwest::blocking::{RequestBuilder, Response};
+use std::fmt;
+use std::sync::Arc;
+
+/// Intercepts the HTTP traffic of a [`Client`](crate::Client): API calls, polls, and
+/// download requests, including every retry attempt.
+///
+/// Register hooks with [`Client::with_request_hook`](crate::Client::with_request_hook), e.g. to
+/// add headers for a corporate gateway or to record request metrics.
+pub trait RequestHook: Send + Sync {
+    /// Called before each request is sent, after authentication was applied.
+    fn before_request(&self, request: RequestBuilder) -> RequestBuilder {
+        request
+    }
+    /// Called for each response received, before its status is checked.
+    fn after_response(&self, response: &Response) {
+        let _ = response;
+    }
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct Hooks(pub(crate) Vec<Arc<dyn RequestHook>>);
+
+impl Hooks {
+    pub(crate) fn before_request(&self, request: RequestBuilder) -> RequestBuilder {
+        self.0
+            .iter()
+            .fold(request, |request, hook| hook.before_request(request))
+    }
+
+    pub(crate) fn after_response(&self, response: &Response) {
+        for hook in &self.0 {
+            hook.after_response(response);
+        }
+    }
+}
+
+impl fmt::Debug for Hooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{} request hook(s)", self.0.len())
+    }
+}
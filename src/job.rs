@@ -0,0 +1,474 @@
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use crate::client::{Client, RemoteFile};
+use crate::error::{CdsError, ErrorResponse, Result, error_from_response};
+use crate::events::CdsEvent;
+use crate::legacy::{ApiReply, failure_from_reply, remote_file_from_reply};
+use crate::processing::{ProcessingJobStatus, ProcessingResults};
+use crate::protocol::{Action, Retrieval, results_url, status_url};
+use crate::stats::StatusClock;
+use crate::util::backoff;
+
+pub use crate::protocol::JobStatus;
+
+/// Progress of a job, reported on each poll by [`Job::wait`] to the
+/// [`ProgressReporter`](crate::ProgressReporter) and as [`CdsEvent::Progress`].
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub struct JobProgress {
+    pub status: JobStatus,
+    /// Percentage complete, if the server reports it.
+    pub percent: Option<u8>,
+    /// Position in the queue, if the server reports it.
+    pub queue_position: Option<u64>,
+    /// Time since the job entered its current state, as seen by polling.
+    pub elapsed: Duration,
+}
+
+/// Outcome of [`Client::retrieve_nowait`], and of `AsyncClient::retrieve_nowait` with an
+/// `AsyncJob` handle.
+#[derive(Debug, Clone)]
+pub enum Submission<J = Job> {
+    /// The job finished right away; its result was downloaded to the target, if one was
+    /// given.
+    Completed(RemoteFile),
+    /// The job is still queued or running: wait for it or download it later through `job`.
+    Pending { job: J, status: JobStatus },
+}
+
+impl Submission {
+    /// The result file, or [`CdsError::Pending`] if the job has not finished.
+    pub(crate) fn into_file(self) -> Result<RemoteFile> {
+        match self {
+            Self::Completed(file) => Ok(file),
+            Self::Pending { job, status } => Err(CdsError::Pending {
+                job_id: job.id,
+                status,
+            }),
+        }
+    }
+}
+
+/// Handle to a request submitted with [`Client::submit`].
+///
+/// The handle only stores the job ID and the URLs needed to follow it, so it can be
+/// recreated later from a persisted ID with [`Client::job`].
+#[derive(Debug, Clone)]
+pub struct Job {
+    client: Client,
+    id: String,
+    kind: JobKind,
+    /// Server log lines seen while polling, shared by clones of the handle.
+    logs: Arc<Mutex<Vec<String>>>,
+}
+
+#[derive(Debug, Clone)]
+pub(crate) enum JobKind {
+    /// `/resources` + `/tasks` workflow. The submission reply is kept because it may already
+    /// carry the result location.
+    Legacy {
+        base_url: String,
+        reply: Option<ApiReply>,
+    },
+    /// Retrieve API (OGC API - Processes) job.
+    Processing { monitor_url: String },
+}
+
+/// Dismisses the wrapped [`Job`] on drop; returned by [`Job::dismiss_on_drop`].
+#[derive(Debug)]
+pub struct DismissOnDrop(Option<Job>);
+
+impl DismissOnDrop {
+    /// Releases the job without dismissing it.
+    pub fn disarm(mut self) -> Job {
+        self.0.take().expect("job is only taken by disarm")
+    }
+}
+
+impl std::ops::Deref for DismissOnDrop {
+    type Target = Job;
+
+    fn deref(&self) -> &Job {
+        self.0.as_ref().expect("job is only taken by disarm")
+    }
+}
+
+impl Drop for DismissOnDrop {
+    fn drop(&mut self) {
+        if let Some(job) = self.0.take() {
+            job.dismiss_logged();
+        }
+    }
+}
+
+impl Job {
+    pub(crate) fn new(client: Client, id: String, kind: JobKind) -> Self {
+        Self {
+            client,
+            id,
+            kind,
+            logs: Arc::default(),
+        }
+    }
+
+    /// Server-side job (request) ID.
+    pub fn id(&self) -> &str {
+        &self.id
+    }
+
+    /// Log lines reported by the server for this job so far, such as warnings about request
+    /// cost. Updated by [`Job::status`] and [`Job::wait`] (Retrieve API only).
+    pub fn logs(&self) -> Vec<String> {
+        self.logs.lock().unwrap_or_else(|e| e.into_inner()).clone()
+    }
+
+    /// Fetches the current status once, without waiting.
+    pub fn status(&self) -> Result<JobStatus> {
+        match &self.kind {
+            JobKind::Legacy { base_url, reply } => {
+                let reply = match reply {
+                    Some(r) if self.id.is_empty() => r.clone(),
+                    _ => self.fetch_legacy(base_url)?,
+                };
+                Ok(JobStatus::from_api(&reply.state))
+            }
+            JobKind::Processing { monitor_url } => {
+                let status = self.fetch_processing(monitor_url)?;
+                Ok(JobStatus::from_api(&status.status))
+            }
+        }
+    }
+
+    /// Fetches the result location of a finished job, without waiting.
+    pub fn results(&self) -> Result<RemoteFile> {
+        match &self.kind {
+            JobKind::Legacy { base_url, reply } => {
+                let reply = match reply {
+                    Some(r) if self.id.is_empty() || r.state == "completed" => r.clone(),
+                    _ => self.fetch_legacy(base_url)?,
+                };
+                remote_file_from_reply(&reply, base_url)
+            }
+            JobKind::Processing { monitor_url } => {
+                let status = self.fetch_processing(monitor_url)?;
+                self.processing_results(monitor_url, &status)
+            }
+        }
+    }
+
+    /// Like [`Job::results`], returning every file of jobs that produce several.
+    pub(crate) fn all_results(&self) -> Result<Vec<RemoteFile>> {
+        match &self.kind {
+            JobKind::Legacy { .. } => Ok(vec![self.results()?]),
+            JobKind::Processing { monitor_url } => {
+                let status = self.fetch_processing(monitor_url)?;
+                self.processing_result_files(monitor_url, &status)
+            }
+        }
+    }
+
+    /// Polls until the job finishes and returns the result location.
+    #[cfg_attr(feature = "tracing", tracing::instrument(skip_all, fields(job_id = %self.id)))]
+    pub fn wait(&self) -> Result<RemoteFile> {
+        match &self.kind {
+            JobKind::Legacy { base_url, reply } => self.wait_legacy(base_url, reply.clone()),
+            JobKind::Processing { monitor_url } => {
+                self.follow_processing(monitor_url, true)?.into_file()
+            }
+        }
+    }
+
+    /// Waits for the job to finish, then downloads the result to `target`.
+    pub fn download(&self, target: &Path) -> Result<PathBuf> {
+        let file = self.wait()?;
+        let path = self.client.download(&file, target)?;
+        if self.client.delete_after_download {
+            self.dismiss_logged();
+        }
+        Ok(path)
+    }
+
+    /// Dismisses the job on the server, cancelling it if it has not finished yet.
+    pub fn dismiss(&self) -> Result<()> {
+        let url = match &self.kind {
+            JobKind::Legacy { base_url, .. } => {
+                if self.id.is_empty() {
+                    return Err(CdsError::Protocol(
+                        "missing request_id for legacy job".to_string(),
+                    ));
+                }
+                format!("{}/tasks/{}", base_url.trim_end_matches('/'), self.id)
+            }
+            JobKind::Processing { monitor_url } => monitor_url.clone(),
+        };
+        self.client.require_key()?;
+        // Dismissal usually follows a timeout or cancellation, which must not stop it.
+        let mut client = self.client.clone();
+        client.deadline_at = None;
+        client.cancel = None;
+        let client = &client;
+        let resp = client.robust_request(&client.retry, || {
+            client.send(client.prepare_request(client.http.delete(&url)))
+        })?;
+        client.log_debug(format_args!("DELETE {} -> {}", url, resp.status()));
+        if !resp.status().is_success() {
+            return Err(error_from_response(ErrorResponse {
+                status: resp.status(),
+                url,
+                headers: resp.headers().clone(),
+                body: resp.text().unwrap_or_default(),
+            }));
+        }
+        Ok(())
+    }
+
+    /// Returns a guard that dismisses the job when dropped, unless
+    /// [`disarmed`](DismissOnDrop::disarm) first.
+    pub fn dismiss_on_drop(self) -> DismissOnDrop {
+        DismissOnDrop(Some(self))
+    }
+
+    /// Notifies the event sink and state change callback that a poll saw `new`, after
+    /// `old` (`None` on the first poll).
+    fn state_changed(&self, old: Option<&JobStatus>, new: &JobStatus) {
+        self.client.emit(|| CdsEvent::StateChanged {
+            job_id: self.id.clone(),
+            status: new.clone(),
+        });
+        if let Some(callback) = &self.client.on_state_change.0 {
+            callback(old, new, self);
+        }
+    }
+
+    /// Sends `progress` to the event sink and, if progress display is on, the reporter.
+    fn report_progress(&self, progress: JobProgress) {
+        if self.client.progress {
+            self.client.reporter.0.job_progress(&self.id, &progress);
+        }
+        self.client.emit(|| CdsEvent::Progress {
+            job_id: self.id.clone(),
+            progress,
+        });
+    }
+
+    /// Best-effort [`Job::dismiss`] that only logs the outcome.
+    pub(crate) fn dismiss_logged(&self) {
+        match self.dismiss() {
+            Ok(()) => self
+                .client
+                .log_info(format_args!("Dismissed job {}", self.id)),
+            Err(e) => self
+                .client
+                .log_debug(format_args!("failed to dismiss job {}: {}", self.id, e)),
+        }
+    }
+
+    /// The result of the job if it has finished, without waiting; otherwise the handle and
+    /// the status seen.
+    pub(crate) fn check(&self) -> Result<Submission> {
+        match &self.kind {
+            JobKind::Legacy { base_url, reply } => {
+                let reply = match reply {
+                    Some(r) => r.clone(),
+                    None => self.fetch_legacy(base_url)?,
+                };
+                match reply.state.as_str() {
+                    "queued" | "running" if !self.id.is_empty() => Ok(Submission::Pending {
+                        job: self.clone(),
+                        status: JobStatus::from_api(&reply.state),
+                    }),
+                    "failed" => Err(failure_from_reply(&reply)),
+                    _ => remote_file_from_reply(&reply, base_url).map(Submission::Completed),
+                }
+            }
+            JobKind::Processing { monitor_url } => self.follow_processing(monitor_url, false),
+        }
+    }
+
+    fn wait_legacy(&self, base_url: &str, reply: Option<ApiReply>) -> Result<RemoteFile> {
+        let mut reply = match reply {
+            Some(r) => r,
+            None => self.fetch_legacy(base_url)?,
+        };
+
+        let mut sleep = self.client.poll_min;
+        let mut last_state: Option<String> = None;
+        let mut clock = StatusClock::start();
+
+        loop {
+            clock.observe(&self.client, &JobStatus::from_api(&reply.state));
+            if last_state.as_deref() != Some(reply.state.as_str()) {
+                self.state_changed(
+                    last_state.as_deref().map(JobStatus::from_api).as_ref(),
+                    &JobStatus::from_api(&reply.state),
+                );
+                last_state = Some(reply.state.clone());
+                self.client
+                    .log_info(format_args!("Request state: {}", reply.state));
+            }
+            self.report_progress(JobProgress {
+                status: JobStatus::from_api(&reply.state),
+                percent: None,
+                queue_position: None,
+                elapsed: clock.elapsed(),
+            });
+
+            #[cfg(feature = "tracing")]
+            tracing::debug!(state = %reply.state, "polled request");
+
+            match reply.state.as_str() {
+                "completed" => return remote_file_from_reply(&reply, base_url),
+                "queued" | "running" => {
+                    let rid = reply.request_id.clone().ok_or_else(|| {
+                        CdsError::Protocol(format!(
+                            "missing request_id while state={}",
+                            reply.state
+                        ))
+                    })?;
+                    self.client.sleep(sleep)?;
+                    sleep = backoff(sleep, self.client.poll_max);
+
+                    let task_url = format!("{}/tasks/{}", base_url.trim_end_matches('/'), rid);
+                    reply =
+                        self.client
+                            .api_json::<Value, ApiReply>("GET", &task_url, &Value::Null)?;
+                }
+                "failed" => return Err(failure_from_reply(&reply)),
+                other => {
+                    return Err(CdsError::Protocol(format!("unknown API state [{}]", other)));
+                }
+            }
+        }
+    }
+
+    /// Follows the job with a [`Retrieval`] until it finishes or, unless `wait`, until a
+    /// poll finds it still queued or running.
+    fn follow_processing(&self, monitor_url: &str, wait: bool) -> Result<Submission> {
+        self.client.require_key()?;
+        let mut retrieval = Retrieval::follow(monitor_url, &self.client.key)
+            .with_poll_interval(self.client.poll_min, self.client.poll_max);
+        let mut request = retrieval
+            .poll_request()
+            .expect("a followed retrieval starts polling");
+        let mut last_status: Option<JobStatus> = None;
+        let mut clock = StatusClock::start();
+        loop {
+            let response = self.client.send_protocol(&request)?;
+            let polls = retrieval.polls();
+            let action = retrieval.handle(response);
+            if retrieval.polls() > polls {
+                self.polled(&retrieval, &mut last_status, &mut clock);
+            }
+            request = match action? {
+                Action::Send(request) => request,
+                Action::Poll { after, request } => {
+                    if !wait {
+                        return Ok(Submission::Pending {
+                            job: self.clone(),
+                            status: retrieval.status().cloned().expect("set by the poll"),
+                        });
+                    }
+                    self.client.sleep(after)?;
+                    request
+                }
+                Action::Done(files) => {
+                    let file = files.into_iter().next();
+                    return Ok(Submission::Completed(
+                        file.expect("results hold at least one file"),
+                    ));
+                }
+            };
+        }
+    }
+
+    /// Reports a poll of `retrieval`: new log lines, state changes and progress.
+    fn polled(
+        &self,
+        retrieval: &Retrieval,
+        last_status: &mut Option<JobStatus>,
+        clock: &mut StatusClock,
+    ) {
+        let status = retrieval.status().cloned().expect("set by the poll");
+        #[cfg(feature = "tracing")]
+        tracing::debug!(status = %status, "polled job");
+        self.record_logs(retrieval.logs().to_vec());
+        clock.observe(&self.client, &status);
+        if last_status.as_ref() != Some(&status) {
+            self.state_changed(last_status.as_ref(), &status);
+            self.client.log_info(format_args!("Job status: {}", status));
+            *last_status = Some(status.clone());
+        }
+        self.report_progress(JobProgress {
+            status,
+            percent: retrieval.percent(),
+            queue_position: retrieval.queue_position(),
+            elapsed: clock.elapsed(),
+        });
+    }
+
+    fn fetch_legacy(&self, base_url: &str) -> Result<ApiReply> {
+        self.client.require_key()?;
+        if self.id.is_empty() {
+            return Err(CdsError::Protocol(
+                "missing request_id for legacy job".to_string(),
+            ));
+        }
+        let task_url = format!("{}/tasks/{}", base_url.trim_end_matches('/'), self.id);
+        self.client
+            .api_json::<Value, ApiReply>("GET", &task_url, &Value::Null)
+    }
+
+    fn fetch_processing(&self, monitor_url: &str) -> Result<ProcessingJobStatus> {
+        self.client.require_key()?;
+        let status = self.client.api_json::<Value, ProcessingJobStatus>(
+            "GET",
+            &status_url(monitor_url),
+            &Value::Null,
+        )?;
+        self.record_logs(status.log_lines());
+        Ok(status)
+    }
+
+    /// Keeps the server log and reports the lines not seen before.
+    fn record_logs(&self, lines: Vec<String>) {
+        let mut logs = self.logs.lock().unwrap_or_else(|e| e.into_inner());
+        // The server sends the whole log each time; anything past what we have is new.
+        for line in lines.iter().skip(logs.len()) {
+            self.client.log_info(format_args!("{}", line));
+            self.client.emit(|| CdsEvent::LogLine {
+                job_id: self.id.clone(),
+                line: line.clone(),
+            });
+        }
+        if lines.len() > logs.len() {
+            *logs = lines;
+        }
+    }
+
+    fn processing_results(
+        &self,
+        monitor_url: &str,
+        status: &ProcessingJobStatus,
+    ) -> Result<RemoteFile> {
+        let files = self.processing_result_files(monitor_url, status)?;
+        Ok(files
+            .into_iter()
+            .next()
+            .expect("results hold at least one file"))
+    }
+
+    fn processing_result_files(
+        &self,
+        monitor_url: &str,
+        status: &ProcessingJobStatus,
+    ) -> Result<Vec<RemoteFile>> {
+        let results_url = results_url(monitor_url, status);
+        let results: ProcessingResults =
+            self.client
+                .api_json::<Value, ProcessingResults>("GET", &results_url, &Value::Null)?;
+        results.to_remote_files(&results_url)
+    }
+}
@@ -0,0 +1,183 @@
+use serde_json::Value;
+
+use crate::client::Client;
+use crate::error::Result;
+use crate::job::{Job, JobStatus};
+use crate::util::append_query;
+
+/// Filters for [`Client::jobs`].
+#[derive(Debug, Clone, Default)]
+pub struct JobFilter {
+    limit: Option<usize>,
+    status: Vec<JobStatus>,
+    dataset: Option<String>,
+    oldest_first: bool,
+}
+
+impl JobFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Maximum number of jobs per page.
+    pub fn limit(mut self, limit: usize) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Only return jobs in the given status (may be called several times).
+    pub fn status(mut self, status: JobStatus) -> Self {
+        self.status.push(status);
+        self
+    }
+
+    /// Only return jobs submitted for the given dataset (process ID).
+    pub fn dataset(mut self, dataset: impl Into<String>) -> Self {
+        self.dataset = Some(dataset.into());
+        self
+    }
+
+    /// Sort by creation time ascending instead of the default newest-first order.
+    pub fn oldest_first(mut self, oldest_first: bool) -> Self {
+        self.oldest_first = oldest_first;
+        self
+    }
+
+    fn query(&self) -> Vec<(&'static str, String)> {
+        let mut params = Vec::new();
+        if let Some(limit) = self.limit {
+            params.push(("limit", limit.to_string()));
+        }
+        for status in &self.status {
+            params.push(("status", status_param(status).to_string()));
+        }
+        if let Some(dataset) = &self.dataset {
+            params.push(("processID", dataset.clone()));
+        }
+        let sortby = if self.oldest_first {
+            "created"
+        } else {
+            "-created"
+        };
+        params.push(("sortby", sortby.to_string()));
+        params
+    }
+}
+
+/// Summary of a job as returned by the jobs listing.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct JobInfo {
+    /// Job ID.
+    #[serde(rename = "jobID")]
+    pub id: String,
+    /// Dataset (process) the job was submitted for.
+    #[serde(default, rename = "processID")]
+    pub dataset: Option<String>,
+    /// Raw status string (`accepted`, `running`, `successful`, `failed`, `dismissed`, ...).
+    pub status: String,
+    /// Creation timestamp (RFC 3339).
+    #[serde(default)]
+    pub created: Option<String>,
+    /// Processing start timestamp (RFC 3339).
+    #[serde(default)]
+    pub started: Option<String>,
+    /// Completion timestamp (RFC 3339).
+    #[serde(default)]
+    pub finished: Option<String>,
+    /// Last update timestamp (RFC 3339).
+    #[serde(default)]
+    pub updated: Option<String>,
+}
+
+impl JobInfo {
+    /// Normalized status.
+    pub fn job_status(&self) -> JobStatus {
+        JobStatus::from_api(&self.status)
+    }
+
+    /// Turns this listing entry into a [`Job`] handle.
+    pub fn to_job(&self, client: &Client) -> Job {
+        client.job(&self.id)
+    }
+}
+
+/// One page of [`Client::jobs`] results.
+#[derive(Debug, Clone)]
+pub struct JobPage {
+    /// Jobs on this page.
+    pub jobs: Vec<JobInfo>,
+    next: Option<String>,
+}
+
+impl JobPage {
+    /// Returns `true` if the server advertised another page.
+    pub fn has_next(&self) -> bool {
+        self.next.is_some()
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct JobListResponse {
+    #[serde(default)]
+    jobs: Vec<JobInfo>,
+    #[serde(default)]
+    links: Vec<JobListLink>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct JobListLink {
+    #[serde(default)]
+    rel: Option<String>,
+    href: String,
+}
+
+impl Client {
+    /// Lists jobs of the authenticated user (`GET /retrieve/v1/jobs`).
+    pub fn jobs(&self, filter: &JobFilter) -> Result<JobPage> {
+        let params = filter.query();
+        let params: Vec<(&str, &str)> = params.iter().map(|(k, v)| (*k, v.as_str())).collect();
+        let url = append_query(&format!("{}/jobs", self.retrieve_base()), &params);
+        self.jobs_page(&url)
+    }
+
+    /// Fetches the page following `page`, if any.
+    pub fn next_jobs(&self, page: &JobPage) -> Result<Option<JobPage>> {
+        match &page.next {
+            Some(url) => self.jobs_page(url).map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Deletes (dismisses) a job, cancelling it if it has not finished yet.
+    pub fn delete_job(&self, id: &str) -> Result<JobInfo> {
+        self.require_key()?;
+        let url = format!("{}/jobs/{}", self.retrieve_base(), id);
+        self.api_json::<Value, JobInfo>("DELETE", &url, &Value::Null)
+    }
+
+    fn jobs_page(&self, url: &str) -> Result<JobPage> {
+        self.require_key()?;
+        let resp: JobListResponse =
+            self.api_json::<Value, JobListResponse>("GET", url, &Value::Null)?;
+        let next = resp
+            .links
+            .into_iter()
+            .find(|l| l.rel.as_deref() == Some("next"))
+            .map(|l| l.href);
+        Ok(JobPage {
+            jobs: resp.jobs,
+            next,
+        })
+    }
+}
+
+fn status_param(status: &JobStatus) -> &str {
+    match status {
+        JobStatus::Queued => "accepted",
+        JobStatus::Running => "running",
+        JobStatus::Successful => "successful",
+        JobStatus::Failed => "failed",
+        JobStatus::Dismissed => "dismissed",
+        JobStatus::Unknown(s) => s,
+    }
+}
@@ -1,10 +1,12 @@
-use anyhow::{Result, bail};
+use reqwest::StatusCode;
 use serde_json::Value;
+use std::sync::Mutex;
 
-use crate::client::RemoteFile;
-use crate::util::urljoin;
+use crate::error::{CdsError, Result};
+use crate::protocol::RemoteFile;
+use crate::util::{api_v2_variant, urljoin};
 
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Deserialize)]
 pub(crate) struct ApiReply {
     pub(crate) state: String,
     #[serde(default)]
@@ -24,7 +26,7 @@ pub(crate) struct ApiReply {
     pub(crate) error: Option<ApiError>,
 }
 
-#[derive(Debug, serde::Deserialize)]
+#[derive(Debug, Clone, serde::Deserialize)]
 pub(crate) struct ApiError {
     #[serde(default)]
     pub(crate) message: Option<String>,
@@ -41,6 +43,79 @@ struct ApiResultLocation {
     content_type: Option<String>,
 }
 
+/// Base URL of the legacy API, resolved on the first submission: CDS has been served
+/// under both `/api` and `/api/v2`, and some deployments only answer on the latter.
+///
+/// Shared by a client and its clones, so the fallback is probed once.
+#[derive(Debug, Default)]
+pub(crate) struct LegacyBase(Mutex<Option<(String, String)>>);
+
+impl LegacyBase {
+    /// The base resolved for the configured `base`, or `base` itself if none was.
+    pub(crate) fn get(&self, base: &str) -> String {
+        match &*self.0.lock().unwrap() {
+            Some((configured, resolved)) if configured == base => resolved.clone(),
+            _ => base.to_string(),
+        }
+    }
+
+    /// The `/api/v2` variant to try after `base` failed with `error`: only when the server
+    /// answered 404 and `base` was not resolved already.
+    pub(crate) fn fallback_for(&self, base: &str, error: &CdsError) -> Option<String> {
+        if error.status() != Some(StatusCode::NOT_FOUND) || self.get(base) != base {
+            return None;
+        }
+        api_v2_variant(base).filter(|alt| alt != base)
+    }
+
+    /// Settles the base after `base` failed with `primary` and `alt` gave `result`.
+    ///
+    /// `alt` is kept if it answered with anything but 404, so later submissions go straight
+    /// to it; if it did not answer either, the error carries both failures.
+    pub(crate) fn resolve<T>(
+        &self,
+        base: &str,
+        primary: CdsError,
+        alt: String,
+        result: Result<T>,
+    ) -> Result<(String, T)> {
+        let exists = match &result {
+            Ok(_) => true,
+            Err(e) => e.status().is_some_and(|s| s != StatusCode::NOT_FOUND),
+        };
+        if exists {
+            *self.0.lock().unwrap() = Some((base.to_string(), alt.clone()));
+        }
+        match result {
+            Ok(value) => Ok((alt, value)),
+            Err(fallback) if exists => Err(fallback),
+            Err(fallback) => Err(CdsError::BaseFallback {
+                primary: Box::new(primary),
+                fallback_url: alt,
+                fallback: Box::new(fallback),
+            }),
+        }
+    }
+}
+
+/// The error for a request that ended in the `failed` state.
+pub(crate) fn failure_from_reply(reply: &ApiReply) -> CdsError {
+    let error = reply.error.as_ref();
+    let message = error
+        .and_then(|e| e.message.as_deref())
+        .unwrap_or("request failed");
+    let reason = error.and_then(|e| e.reason.as_deref()).unwrap_or("");
+    CdsError::JobFailed {
+        message: format!(
+            "{}{}{}",
+            message,
+            if reason.is_empty() { "" } else { ". " },
+            reason
+        ),
+        logs: Vec::new(),
+    }
+}
+
 pub(crate) fn remote_file_from_reply(reply: &ApiReply, base_url: &str) -> Result<RemoteFile> {
     // 1) If API returns {"result": {"location":...,"contentLength":...}}
     if let Some(result) = &reply.result {
@@ -49,6 +124,7 @@ pub(crate) fn remote_file_from_reply(reply: &ApiReply, base_url: &str) -> Result
                 location: urljoin(base_url, &r.location),
                 content_length: r.content_length,
                 content_type: r.content_type,
+                checksum: None,
             });
         }
     }
@@ -59,8 +135,11 @@ pub(crate) fn remote_file_from_reply(reply: &ApiReply, base_url: &str) -> Result
             location: urljoin(base_url, location),
             content_length,
             content_type: reply.content_type.clone(),
+            checksum: None,
         });
     }
 
-    bail!("missing download info in API reply")
+    Err(CdsError::Protocol(
+        "missing download info in API reply".to_string(),
+    ))
 }
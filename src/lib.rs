@@ -34,16 +34,210 @@
 //! }
 //! ```
 //!
+//! ## Async
+//! Enable the `async` feature to get [`AsyncClient`], which exposes the same flow as `async fn`s
+//! and must be driven by a Tokio runtime.
+//!
+//! ## Errors
+//! All fallible APIs return [`Result`], whose error type [`CdsError`] distinguishes common
+//! failure causes (authentication, licences, failed jobs, downloads, ...). It converts into
+//! `anyhow::Error` with `?`, as in the example above.
+//!
 //! For full usage and configuration details, see the crate README.
 
 #![forbid(unsafe_code)]
+// WebAssembly builds only use the sans-IO core, leaving most helpers unused.
+#![cfg_attr(target_arch = "wasm32", allow(dead_code))]
+
+mod error;
+mod normalize;
+mod processing;
+pub mod protocol;
+mod util;
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+mod wasm;
 
+#[cfg(not(target_arch = "wasm32"))]
+mod area;
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(feature = "async")]
+mod async_client;
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(feature = "async")]
+mod async_job;
+#[cfg(not(target_arch = "wasm32"))]
+mod batch;
+#[cfg(not(target_arch = "wasm32"))]
+mod cache;
+#[cfg(not(target_arch = "wasm32"))]
+mod cancel;
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(feature = "vcr")]
+mod cassette;
+#[cfg(not(target_arch = "wasm32"))]
+mod catalogue;
+#[cfg(not(target_arch = "wasm32"))]
+mod checksum;
+#[cfg(not(target_arch = "wasm32"))]
+mod chunking;
+#[cfg(not(target_arch = "wasm32"))]
 mod client;
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(feature = "object-store")]
+mod cloud;
+#[cfg(not(target_arch = "wasm32"))]
+mod coalesce;
+#[cfg(not(target_arch = "wasm32"))]
 mod config;
+#[cfg(not(target_arch = "wasm32"))]
+mod constraints;
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(feature = "keyring")]
+mod credentials;
+#[cfg(not(target_arch = "wasm32"))]
+mod dates;
+#[cfg(not(target_arch = "wasm32"))]
 mod download;
-mod error;
+#[cfg(not(target_arch = "wasm32"))]
+mod events;
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(feature = "extract")]
+mod extract;
+#[cfg(not(target_arch = "wasm32"))]
+mod filename;
+#[cfg(not(target_arch = "wasm32"))]
+mod hooks;
+#[cfg(not(target_arch = "wasm32"))]
+mod job;
+#[cfg(not(target_arch = "wasm32"))]
+mod jobs;
+#[cfg(not(target_arch = "wasm32"))]
 mod legacy;
-mod processing;
-mod util;
+#[cfg(not(target_arch = "wasm32"))]
+mod licences;
+#[cfg(not(target_arch = "wasm32"))]
+mod magic;
+#[cfg(not(target_arch = "wasm32"))]
+mod manifest;
+#[cfg(not(target_arch = "wasm32"))]
+mod notify;
+#[cfg(not(target_arch = "wasm32"))]
+mod options;
+#[cfg(not(target_arch = "wasm32"))]
+mod process;
+#[cfg(not(target_arch = "wasm32"))]
+mod profile;
+#[cfg(not(target_arch = "wasm32"))]
+mod progress;
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(feature = "chrono")]
+mod range;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod requests;
+#[cfg(not(target_arch = "wasm32"))]
+mod retry;
+#[cfg(not(target_arch = "wasm32"))]
+mod scheduler;
+#[cfg(not(target_arch = "wasm32"))]
+mod stac;
+#[cfg(not(target_arch = "wasm32"))]
+mod state;
+#[cfg(not(target_arch = "wasm32"))]
+mod stats;
+#[cfg(not(target_arch = "wasm32"))]
+mod status;
+#[cfg(not(target_arch = "wasm32"))]
+mod store;
+#[cfg(not(target_arch = "wasm32"))]
+mod telemetry;
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(feature = "test-util")]
+pub mod test_util;
+#[cfg(not(target_arch = "wasm32"))]
+mod throttle;
+#[cfg(not(target_arch = "wasm32"))]
+mod wire;
+
+pub use error::{CdsError, ErrorResponse, ProblemDetails, Result};
+pub use normalize::normalize_request;
+pub use protocol::{JobStatus, RemoteFile};
+#[cfg(all(feature = "wasm", target_arch = "wasm32"))]
+pub use wasm::WasmClient;
 
-pub use client::{Client, ClientConfig, RemoteFile};
+#[cfg(not(target_arch = "wasm32"))]
+pub use area::Area;
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(feature = "async")]
+pub use async_client::AsyncClient;
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(feature = "async")]
+pub use async_job::AsyncJob;
+#[cfg(not(target_arch = "wasm32"))]
+pub use batch::{BatchItem, BatchResult, BatchResults};
+#[cfg(not(target_arch = "wasm32"))]
+pub use cancel::CancellationToken;
+#[cfg(not(target_arch = "wasm32"))]
+pub use catalogue::{DatasetInfo, DatasetLicenceLink};
+#[cfg(not(target_arch = "wasm32"))]
+pub use chunking::{ChunkBy, ChunkOutput, RequestChunk, split_request};
+#[cfg(not(target_arch = "wasm32"))]
+pub use client::{Client, ClientConfig};
+#[cfg(not(target_arch = "wasm32"))]
+pub use constraints::{ConstraintViolation, Constraints, ViolationKind};
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(feature = "keyring")]
+pub use credentials::{delete_keyring_key, keyring_key, migrate_rc_to_keyring, set_keyring_key};
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(feature = "chrono")]
+pub use dates::DateRange;
+#[cfg(not(target_arch = "wasm32"))]
+pub use dates::TimeSteps;
+#[cfg(not(target_arch = "wasm32"))]
+pub use download::{DownloadOutcome, OverwritePolicy, RemoteReader};
+#[cfg(not(target_arch = "wasm32"))]
+pub use events::CdsEvent;
+#[cfg(not(target_arch = "wasm32"))]
+pub use filename::expand_target_template;
+#[cfg(not(target_arch = "wasm32"))]
+pub use hooks::RequestHook;
+#[cfg(not(target_arch = "wasm32"))]
+pub use job::{DismissOnDrop, Job, JobProgress, Submission};
+#[cfg(not(target_arch = "wasm32"))]
+pub use jobs::{JobFilter, JobInfo, JobPage};
+#[cfg(not(target_arch = "wasm32"))]
+pub use licences::Licence;
+#[cfg(not(target_arch = "wasm32"))]
+pub use manifest::ManifestEntry;
+#[cfg(not(target_arch = "wasm32"))]
+pub use options::RetrieveOptions;
+#[cfg(not(target_arch = "wasm32"))]
+pub use process::{ProcessDescription, ProcessInput, ProcessOutput};
+#[cfg(not(target_arch = "wasm32"))]
+pub use profile::{AuthCheck, Profile};
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(feature = "progress")]
+pub use progress::IndicatifProgress;
+#[cfg(not(target_arch = "wasm32"))]
+pub use progress::{NoProgress, ProgressReporter};
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(feature = "chrono")]
+pub use range::Period;
+#[cfg(not(target_arch = "wasm32"))]
+pub use retry::RetryPolicy;
+#[cfg(not(target_arch = "wasm32"))]
+pub use scheduler::{EnqueueOptions, EntrySnapshot, EntryState, Scheduler, SchedulerSnapshot};
+#[cfg(not(target_arch = "wasm32"))]
+pub use stac::{Collection, Extent, SpatialExtent, StacLink, TemporalExtent};
+#[cfg(not(target_arch = "wasm32"))]
+pub use state::StateEntry;
+#[cfg(not(target_arch = "wasm32"))]
+pub use stats::RetrieveOutcome;
+#[cfg(not(target_arch = "wasm32"))]
+pub use status::{MessageSeverity, ServiceMessage};
+#[cfg(not(target_arch = "wasm32"))]
+pub use store::{Store, StoreRouter};
+#[cfg(not(target_arch = "wasm32"))]
+pub use telemetry::Metrics;
+#[cfg(not(target_arch = "wasm32"))]
+#[cfg(feature = "metrics")]
+pub use telemetry::MetricsFacade;
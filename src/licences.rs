@@ -0,0 +1,76 @@
+use serde_json::Value;
+
+use crate::client::Client;
+use crate::error::Result;
+
+/// A dataset licence, identified by ID and revision.
+#[derive(Debug, Clone, PartialEq, Eq, serde::Deserialize)]
+pub struct Licence {
+    /// Licence ID, e.g. `licence-to-use-copernicus-products`.
+    pub id: String,
+    /// Licence revision; accepting an older revision does not satisfy a newer one.
+    pub revision: u32,
+    /// Human-readable title, when provided.
+    #[serde(default, alias = "label")]
+    pub title: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ProcessLicences {
+    #[serde(default, rename = "cads:licences", alias = "licences")]
+    licences: Vec<Licence>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct AccountLicences {
+    #[serde(default)]
+    licences: Vec<Licence>,
+}
+
+impl Client {
+    /// Licences that must be accepted before `dataset` can be retrieved.
+    pub fn dataset_licences(&self, dataset: &str) -> Result<Vec<Licence>> {
+        let url = format!("{}/processes/{}", self.retrieve_base(), dataset);
+        let p: ProcessLicences =
+            self.api_json::<Value, ProcessLicences>("GET", &url, &Value::Null)?;
+        Ok(p.licences)
+    }
+
+    /// Licences already accepted by the authenticated user.
+    pub fn accepted_licences(&self) -> Result<Vec<Licence>> {
+        self.require_key()?;
+        let url = format!("{}/account/licences", self.profiles_base());
+        let a: AccountLicences =
+            self.api_json::<Value, AccountLicences>("GET", &url, &Value::Null)?;
+        Ok(a.licences)
+    }
+
+    /// Accepts revision `revision` of licence `id` on behalf of the authenticated user.
+    ///
+    /// Only call this after the user has actually agreed to the licence terms.
+    pub fn accept_licence(&self, id: &str, revision: u32) -> Result<()> {
+        self.require_key()?;
+        let url = format!("{}/account/licences/{}", self.profiles_base(), id);
+        let body = serde_json::json!({ "revision": revision });
+        self.api_json::<Value, Value>("PUT", &url, &body)?;
+        Ok(())
+    }
+
+    /// Licences required by `dataset` that the user has not accepted yet.
+    pub fn missing_licences(&self, dataset: &str) -> Result<Vec<Licence>> {
+        let accepted = self.accepted_licences()?;
+        Ok(self
+            .dataset_licences(dataset)?
+            .into_iter()
+            .filter(|l| {
+                !accepted
+                    .iter()
+                    .any(|a| a.id == l.id && a.revision >= l.revision)
+            })
+            .collect())
+    }
+
+    pub(crate) fn profiles_base(&self) -> String {
+        format!("{}/profiles/v1", self.url.trim_end_matches('/'))
+    }
+}
@@ -0,0 +1,106 @@
+use std::fs::File;
+use std::io::Read;
+use std::path::Path;
+
+use crate::client::{Client, RemoteFile};
+use crate::download::extension_for;
+use crate::error::{CdsError, Result};
+
+/// Data formats whose files start with a recognizable signature.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Format {
+    Grib,
+    /// Classic NetCDF or NetCDF-4 (HDF5).
+    NetCdf,
+    Zip,
+    Gzip,
+}
+
+impl Format {
+    /// The format expected for `file` saved as `target`, from the content type or else the
+    /// file extension.
+    fn expected(file: &RemoteFile, target: &Path) -> Option<Self> {
+        let ext = file
+            .content_type
+            .as_deref()
+            .and_then(extension_for)
+            .map(str::to_string)
+            .or_else(|| {
+                target
+                    .extension()
+                    .and_then(|e| e.to_str())
+                    .map(str::to_ascii_lowercase)
+            })?;
+        match ext.as_str() {
+            "grib" | "grb" | "grib1" | "grib2" | "grb2" => Some(Self::Grib),
+            "nc" | "nc4" | "netcdf" => Some(Self::NetCdf),
+            "zip" => Some(Self::Zip),
+            "gz" | "tgz" => Some(Self::Gzip),
+            _ => None,
+        }
+    }
+
+    fn matches(self, head: &[u8]) -> bool {
+        match self {
+            Self::Grib => head.starts_with(b"GRIB"),
+            Self::NetCdf => {
+                head.starts_with(b"CDF\x01")
+                    || head.starts_with(b"CDF\x02")
+                    || head.starts_with(b"CDF\x05")
+                    || head.starts_with(b"\x89HDF\r\n\x1a\n")
+            }
+            Self::Zip => head.starts_with(b"PK\x03\x04") || head.starts_with(b"PK\x05\x06"),
+            Self::Gzip => head.starts_with(b"\x1f\x8b"),
+        }
+    }
+
+    fn name(self) -> &'static str {
+        match self {
+            Self::Grib => "GRIB",
+            Self::NetCdf => "NetCDF",
+            Self::Zip => "zip",
+            Self::Gzip => "gzip",
+        }
+    }
+}
+
+impl Client {
+    /// Checks that downloaded files start with the signature of their format (GRIB, NetCDF
+    /// or HDF5, zip, gzip), taken from the content type or the target's extension (default
+    /// `false`).
+    ///
+    /// This catches an HTML error page saved as `download.grib`; the download then fails
+    /// with [`CdsError::Download`]. Files of other formats are not checked.
+    pub fn with_format_check(mut self, check: bool) -> Self {
+        self.format_check = check;
+        self
+    }
+
+    /// Fails if `target` does not look like the format expected for `file`, when the
+    /// format check is on.
+    pub(crate) fn verify_format(&self, file: &RemoteFile, target: &Path) -> Result<()> {
+        if !self.format_check {
+            return Ok(());
+        }
+        let Some(format) = Format::expected(file, target) else {
+            return Ok(());
+        };
+        let mut head = Vec::with_capacity(8);
+        File::open(target)
+            .and_then(|f| f.take(8).read_to_end(&mut head))
+            .map_err(|e| CdsError::io(format!("failed to read {}", target.display()), e))?;
+        if format.matches(&head) {
+            return Ok(());
+        }
+        let start = String::from_utf8_lossy(&head);
+        Err(CdsError::download(
+            format!(
+                "{} does not look like a {} file (it starts with {:?}); the server may have sent an error page",
+                target.display(),
+                format.name(),
+                start
+            ),
+            None,
+        ))
+    }
+}
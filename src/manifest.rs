@@ -0,0 +1,127 @@
+use serde::{Deserialize, Serialize};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::client::Client;
+use crate::error::{CdsError, Result};
+use crate::job::Job;
+use crate::state::request_hash;
+
+/// One completed retrieval, as written to the manifest (see [`Client::with_manifest`]).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestEntry {
+    pub dataset: String,
+    /// SHA-256 of the request as canonical JSON, as in the [state file](Client::with_state_file).
+    pub request_hash: String,
+    pub job_id: String,
+    pub path: PathBuf,
+    /// Size of the downloaded file in bytes.
+    pub size: u64,
+    /// Checksum published by the server for the file, if any.
+    #[serde(default)]
+    pub checksum: Option<String>,
+    /// When the `retrieve` call started, in seconds since the Unix epoch.
+    pub started_at: u64,
+    /// When the download finished, in seconds since the Unix epoch.
+    pub completed_at: u64,
+}
+
+impl ManifestEntry {
+    /// Reads all entries of the manifest at `path`.
+    pub fn read_all(path: &Path) -> Result<Vec<Self>> {
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| CdsError::io(format!("failed to read {}", path.display()), e))?;
+        text.lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(|line| {
+                serde_json::from_str(line).map_err(|e| {
+                    CdsError::json(format!("invalid manifest entry in {}", path.display()), e)
+                })
+            })
+            .collect()
+    }
+}
+
+/// Append-only JSON lines file of completed retrievals.
+#[derive(Debug)]
+pub(crate) struct Manifest {
+    file: Mutex<File>,
+}
+
+impl Manifest {
+    fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| CdsError::io(format!("failed to open {}", path.display()), e))?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    pub(crate) fn append(&self, entry: &ManifestEntry) -> Result<()> {
+        let mut line = serde_json::to_string(entry)
+            .map_err(|e| CdsError::json("failed to serialize manifest entry", e))?;
+        line.push('\n');
+        let mut file = self.file.lock().unwrap_or_else(|e| e.into_inner());
+        // One write per line, so concurrent writers never interleave within an entry.
+        file.write_all(line.as_bytes())
+            .map_err(|e| CdsError::io("failed to write manifest", e))
+    }
+}
+
+pub(crate) fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+impl Client {
+    /// Appends a [`ManifestEntry`] as a JSON line to the file at `path` for every file
+    /// downloaded by [`Client::retrieve`] (and the batch, chunked and range helpers built on
+    /// it), so pipelines can audit what was downloaded and find gaps.
+    ///
+    /// Read it back with [`ManifestEntry::read_all`].
+    pub fn with_manifest(mut self, path: &Path) -> Result<Self> {
+        self.manifest = Some(Arc::new(Manifest::open(path)?));
+        Ok(self)
+    }
+
+    /// Writes one manifest entry per downloaded file of `job`, if a manifest is configured.
+    pub(crate) fn record_manifest<T: Serialize>(
+        &self,
+        dataset: &str,
+        request: &T,
+        job: &Job,
+        paths: &[PathBuf],
+        checksum: Option<&str>,
+        started_at: u64,
+    ) -> Result<()> {
+        let Some(manifest) = &self.manifest else {
+            return Ok(());
+        };
+        let request_hash = request_hash(request)?;
+        let completed_at = unix_now();
+        for path in paths {
+            let size = std::fs::metadata(path)
+                .map_err(|e| CdsError::io(format!("failed to read {}", path.display()), e))?
+                .len();
+            manifest.append(&ManifestEntry {
+                dataset: dataset.to_string(),
+                request_hash: request_hash.clone(),
+                job_id: job.id().to_string(),
+                path: path.clone(),
+                size,
+                checksum: checksum.map(str::to_string),
+                started_at,
+                completed_at,
+            })?;
+        }
+        Ok(())
+    }
+}
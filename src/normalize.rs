@@ -0,0 +1,102 @@
+use serde_json::Value;
+
+/// Fields that take a single value.
+const SCALAR_FIELDS: &[&str] = &["data_format", "download_format", "format"];
+
+/// Fields holding numbers the server expects as numbers.
+const NUMERIC_FIELDS: &[&str] = &["area", "grid"];
+
+/// Brings a request into one canonical shape, so the server validates it the same way
+/// however a field was written: `"pressure_level": 1000`, `"1000"` and `["1000"]` are all
+/// sent as `["1000"]`.
+///
+/// - Numbers become strings; `null` values are removed.
+/// - Scalars become one-element lists, except for `data_format`, `download_format` and
+///   `format`, which stay single values.
+/// - `area`, `grid` and nested objects are left as they are.
+///
+/// [`Client::submit`](crate::Client::submit) applies this to every request unless disabled
+/// with [`Client::with_request_normalization`](crate::Client::with_request_normalization).
+pub fn normalize_request(request: &mut Value) {
+    let Value::Object(map) = request else {
+        return;
+    };
+    map.retain(|_, v| !v.is_null());
+    for (key, value) in map.iter_mut() {
+        if NUMERIC_FIELDS.contains(&key.as_str()) || value.is_object() {
+            continue;
+        }
+        if SCALAR_FIELDS.contains(&key.as_str()) {
+            if let Some(s) = scalar_string(value) {
+                *value = Value::String(s);
+            }
+            continue;
+        }
+        *value = match value.take() {
+            Value::Array(items) => Value::Array(
+                items
+                    .into_iter()
+                    .map(|item| scalar_string(&item).map(Value::String).unwrap_or(item))
+                    .collect(),
+            ),
+            other => match scalar_string(&other) {
+                Some(s) => Value::Array(vec![Value::String(s)]),
+                None => other,
+            },
+        };
+    }
+}
+
+fn scalar_string(value: &Value) -> Option<String> {
+    match value {
+        Value::String(s) => Some(s.clone()),
+        Value::Number(n) => Some(n.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn lists_fields_as_strings() {
+        let mut request = json!({
+            "variable": "2m_temperature",
+            "pressure_level": 1000,
+            "year": [2024, "2023"],
+            "time": null,
+            "data_format": ["grib"],
+            "format": 1,
+        });
+        normalize_request(&mut request);
+        assert_eq!(
+            request,
+            json!({
+                "variable": ["2m_temperature"],
+                "pressure_level": ["1000"],
+                "year": ["2024", "2023"],
+                "data_format": ["grib"],
+                "format": "1",
+            })
+        );
+    }
+
+    #[test]
+    fn leaves_numeric_and_nested_fields() {
+        let mut request = json!({
+            "area": [60, -10, 50, 2],
+            "grid": 0.25,
+            "options": {"level": 1},
+            "flag": true,
+        });
+        let expected = request.clone();
+        normalize_request(&mut request);
+        assert_eq!(request, expected);
+
+        let mut not_an_object = json!(["2024"]);
+        normalize_request(&mut not_an_object);
+        assert_eq!(not_an_object, json!(["2024"]));
+    }
+}
@@ -0,0 +1,141 @@
+use serde_json::{Value, json};
+use std::path::Path;
+use std::process::Command;
+use std::time::Duration;
+
+use crate::client::{Client, RemoteFile};
+use crate::error::CdsError;
+
+/// Time allowed for a webhook call.
+const WEBHOOK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where to announce finished retrievals, set with [`Client::with_webhook`] and
+/// [`Client::with_notify_command`].
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Notify {
+    pub(crate) webhook: Option<String>,
+    pub(crate) command: Option<String>,
+}
+
+impl Client {
+    /// POSTs a small JSON payload to `url` whenever a [`Client::retrieve`] call completes
+    /// or fails, e.g. a Slack incoming webhook (the payload has a `text` summary).
+    ///
+    /// The payload holds `status` (`completed` or `failed`), `dataset`, `job_id`, `path`,
+    /// `size` or `error`. Webhook failures are logged and never fail the retrieval.
+    pub fn with_webhook(mut self, url: impl Into<String>) -> Self {
+        self.notify.webhook = Some(url.into());
+        self
+    }
+
+    /// Runs `command` with the system shell whenever a [`Client::retrieve`] call completes
+    /// or fails, before the call returns.
+    ///
+    /// The command sees `CDSAPI_STATUS`, `CDSAPI_DATASET`, `CDSAPI_JOB_ID`, `CDSAPI_PATH`
+    /// and `CDSAPI_ERROR` in its environment (unset when unknown), and the webhook
+    /// payload in `CDSAPI_PAYLOAD`. Its failures are logged and never fail the retrieval.
+    pub fn with_notify_command(mut self, command: impl Into<String>) -> Self {
+        self.notify.command = Some(command.into());
+        self
+    }
+
+    /// Announces the outcome of a retrieval to the configured webhook and command.
+    pub(crate) fn notify_finished(
+        &self,
+        dataset: &str,
+        job_id: Option<&str>,
+        outcome: std::result::Result<&RemoteFile, &CdsError>,
+        target: Option<&Path>,
+    ) {
+        if self.notify.webhook.is_none() && self.notify.command.is_none() {
+            return;
+        }
+        let payload = payload(dataset, job_id, outcome, target);
+        if let Some(url) = &self.notify.webhook {
+            let sent = self
+                .http
+                .post(url)
+                .timeout(WEBHOOK_TIMEOUT)
+                .json(&payload)
+                .send()
+                .and_then(|resp| resp.error_for_status());
+            if let Err(e) = sent {
+                self.log_warning(format_args!("webhook {} failed: {}", url, e));
+            }
+        }
+        if let Some(command) = &self.notify.command {
+            self.run_notify_command(command, &payload);
+        }
+    }
+
+    fn run_notify_command(&self, command: &str, payload: &Value) {
+        #[cfg(windows)]
+        let mut cmd = {
+            let mut cmd = Command::new("cmd");
+            cmd.arg("/C").arg(command);
+            cmd
+        };
+        #[cfg(not(windows))]
+        let mut cmd = {
+            let mut cmd = Command::new("sh");
+            cmd.arg("-c").arg(command);
+            cmd
+        };
+        cmd.env("CDSAPI_PAYLOAD", payload.to_string());
+        for (var, field) in [
+            ("CDSAPI_STATUS", "status"),
+            ("CDSAPI_DATASET", "dataset"),
+            ("CDSAPI_JOB_ID", "job_id"),
+            ("CDSAPI_PATH", "path"),
+            ("CDSAPI_ERROR", "error"),
+        ] {
+            match payload[field].as_str() {
+                Some(value) => cmd.env(var, value),
+                None => cmd.env_remove(var),
+            };
+        }
+        match cmd.status() {
+            Ok(status) if status.success() => {}
+            Ok(status) => self.log_warning(format_args!(
+                "notify command `{}` exited with {}",
+                command, status
+            )),
+            Err(e) => self.log_warning(format_args!(
+                "failed to run notify command `{}`: {}",
+                command, e
+            )),
+        }
+    }
+}
+
+fn payload(
+    dataset: &str,
+    job_id: Option<&str>,
+    outcome: std::result::Result<&RemoteFile, &CdsError>,
+    target: Option<&Path>,
+) -> Value {
+    let path = target.map(|t| t.display().to_string());
+    let subject = match (&path, job_id) {
+        (Some(path), _) => format!("{} ({})", dataset, path),
+        (None, Some(id)) => format!("{} (job {})", dataset, id),
+        (None, None) => dataset.to_string(),
+    };
+    match outcome {
+        Ok(file) => json!({
+            "text": format!("CDS retrieval of {} completed", subject),
+            "status": "completed",
+            "dataset": dataset,
+            "job_id": job_id,
+            "path": path,
+            "size": file.content_length,
+        }),
+        Err(e) => json!({
+            "text": format!("CDS retrieval of {} failed: {}", subject, e),
+            "status": "failed",
+            "dataset": dataset,
+            "job_id": job_id,
+            "path": path,
+            "error": e.to_string(),
+        }),
+    }
+}
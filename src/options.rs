@@ -0,0 +1,100 @@
+use serde::Serialize;
+use std::path::Path;
+use std::time::Duration;
+
+use crate::client::{Client, RemoteFile};
+use crate::download::OverwritePolicy;
+use crate::error::Result;
+
+/// Per-call overrides for [`Client::retrieve_with`]; unset options keep the client's
+/// setting.
+#[derive(Debug, Clone, Default)]
+pub struct RetrieveOptions {
+    timeout: Option<Duration>,
+    deadline: Option<Duration>,
+    progress: Option<bool>,
+    overwrite: Option<OverwritePolicy>,
+    poll_interval: Option<(Duration, Duration)>,
+    delete_after_download: Option<bool>,
+}
+
+impl RetrieveOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Timeout of each API call (see [`Client::with_timeout`]).
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// Bound on the whole submit/poll/download cycle (see [`Client::with_deadline`]).
+    pub fn deadline(mut self, deadline: Duration) -> Self {
+        self.deadline = Some(deadline);
+        self
+    }
+
+    /// Shows or hides the download progress bar.
+    pub fn progress(mut self, progress: bool) -> Self {
+        self.progress = Some(progress);
+        self
+    }
+
+    /// What to do when the target file exists (see [`Client::with_overwrite_policy`]).
+    pub fn overwrite(mut self, policy: OverwritePolicy) -> Self {
+        self.overwrite = Some(policy);
+        self
+    }
+
+    /// Range of the job status polling interval (see [`Client::with_poll_interval`]).
+    pub fn poll_interval(mut self, min: Duration, max: Duration) -> Self {
+        self.poll_interval = Some((min, max));
+        self
+    }
+
+    /// Dismisses the job once its result is downloaded (see
+    /// [`Client::with_delete_after_download`]).
+    pub fn delete_after_download(mut self, delete: bool) -> Self {
+        self.delete_after_download = Some(delete);
+        self
+    }
+
+    fn apply(&self, mut client: Client) -> Client {
+        if let Some(timeout) = self.timeout {
+            client = client.with_timeout(timeout);
+        }
+        if let Some(deadline) = self.deadline {
+            client = client.with_deadline(deadline);
+        }
+        if let Some(progress) = self.progress {
+            client = client.with_progress(progress);
+        }
+        if let Some(policy) = self.overwrite {
+            client = client.with_overwrite_policy(policy);
+        }
+        if let Some((min, max)) = self.poll_interval {
+            client = client.with_poll_interval(min, max);
+        }
+        if let Some(delete) = self.delete_after_download {
+            client = client.with_delete_after_download(delete);
+        }
+        client
+    }
+}
+
+impl Client {
+    /// Like [`Client::retrieve`], with `options` overriding the client's settings for this
+    /// call only.
+    pub fn retrieve_with<T: Serialize>(
+        &self,
+        dataset: &str,
+        request: &T,
+        target: Option<&Path>,
+        options: &RetrieveOptions,
+    ) -> Result<RemoteFile> {
+        options
+            .apply(self.clone())
+            .retrieve(dataset, request, target)
+    }
+}
@@ -0,0 +1,104 @@
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::client::Client;
+use crate::error::Result;
+use crate::licences::Licence;
+use crate::util::value_strings;
+
+/// Description of the process behind a dataset, from the Retrieve API
+/// (`GET /retrieve/v1/processes/{id}`): the request fields it takes, with their JSON
+/// schemas, and what it produces.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ProcessDescription {
+    /// Dataset (process) ID.
+    pub id: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub version: Option<String>,
+    /// Request fields, by name.
+    #[serde(default)]
+    pub inputs: BTreeMap<String, ProcessInput>,
+    /// Results, by name.
+    #[serde(default)]
+    pub outputs: BTreeMap<String, ProcessOutput>,
+    /// Licences that must be accepted before retrieving.
+    #[serde(default, rename = "cads:licences", alias = "licences")]
+    pub licences: Vec<Licence>,
+    /// Any other fields returned by the server.
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+/// One request field of a [`ProcessDescription`].
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ProcessInput {
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// JSON Schema of the accepted values.
+    #[serde(default)]
+    pub schema: Value,
+    /// Any other fields returned by the server.
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+impl ProcessInput {
+    /// Values the field accepts, when its schema enumerates them (directly, or for the
+    /// items of a list); empty for free-form fields.
+    pub fn allowed_values(&self) -> Vec<String> {
+        let values = match self.schema.get("items") {
+            Some(items) => items.get("enum"),
+            None => self.schema.get("enum"),
+        };
+        values.map(value_strings).unwrap_or_default()
+    }
+
+    /// Whether the field takes a list of values rather than a single one.
+    pub fn is_list(&self) -> bool {
+        self.schema.get("type").and_then(Value::as_str) == Some("array")
+    }
+
+    /// The value used when the field is left out of a request, if the schema gives one.
+    pub fn default_value(&self) -> Option<&Value> {
+        self.schema.get("default")
+    }
+}
+
+/// One result of a [`ProcessDescription`].
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct ProcessOutput {
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    /// JSON Schema of the result.
+    #[serde(default)]
+    pub schema: Value,
+}
+
+impl ProcessDescription {
+    /// Formats the result can be delivered in: the allowed values of the `data_format`
+    /// field, or of the older `format` field.
+    pub fn output_formats(&self) -> Vec<String> {
+        ["data_format", "format"]
+            .iter()
+            .find_map(|name| self.inputs.get(*name))
+            .map(ProcessInput::allowed_values)
+            .unwrap_or_default()
+    }
+}
+
+impl Client {
+    /// Fetches the process description of `dataset`, the authoritative list of the request
+    /// fields it accepts and of their values.
+    pub fn process(&self, dataset: &str) -> Result<ProcessDescription> {
+        let url = format!("{}/processes/{}", self.retrieve_base(), dataset);
+        self.api_json::<Value, ProcessDescription>("GET", &url, &Value::Null)
+    }
+}
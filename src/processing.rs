@@ -1,6 +1,5 @@
-use anyhow::{Result, anyhow};
-
-use crate::client::RemoteFile;
+use crate::error::{CdsError, Result};
+use crate::protocol::RemoteFile;
 use crate::util::urljoin;
 
 #[derive(Debug, serde::Deserialize)]
@@ -32,9 +31,78 @@ pub(crate) struct ProcessingJobStatus {
     pub(crate) status: String,
     #[serde(default)]
     links: Vec<ProcessingLink>,
+    /// Server log (with `log=true`), at the top level or under `metadata`.
+    #[serde(default)]
+    log: Vec<serde_json::Value>,
+    #[serde(default)]
+    metadata: Option<ProcessingMetadata>,
+    /// Percentage complete (OGC `progress`), if reported.
+    #[serde(default)]
+    progress: Option<f64>,
+}
+
+#[derive(Debug, Default, serde::Deserialize)]
+struct ProcessingMetadata {
+    #[serde(default)]
+    log: Vec<serde_json::Value>,
+    /// Error details of a failed job.
+    #[serde(default)]
+    results: Option<serde_json::Value>,
+    /// Position of a queued job in the queue, if reported.
+    #[serde(default)]
+    position: Option<u64>,
 }
 
 impl ProcessingJobStatus {
+    /// Error message and traceback of a failed job, if the status carries them.
+    pub(crate) fn error_message(&self) -> Option<String> {
+        error_json_to_message(self.metadata.as_ref()?.results.as_ref()?)
+    }
+
+    /// All server log lines so far. Entries are `[timestamp, message]` pairs, plain strings,
+    /// or objects with a `message`.
+    pub(crate) fn log_lines(&self) -> Vec<String> {
+        let metadata_log = self.metadata.as_ref().map(|m| m.log.as_slice());
+        let entries = if self.log.is_empty() {
+            metadata_log.unwrap_or_default()
+        } else {
+            &self.log
+        };
+        entries
+            .iter()
+            .filter_map(|entry| match entry {
+                serde_json::Value::String(line) => Some(line.clone()),
+                serde_json::Value::Array(parts) => Some(
+                    parts
+                        .iter()
+                        .map(|p| {
+                            p.as_str()
+                                .map(str::to_string)
+                                .unwrap_or_else(|| p.to_string())
+                        })
+                        .collect::<Vec<_>>()
+                        .join(" "),
+                ),
+                serde_json::Value::Object(map) => map
+                    .get("message")
+                    .and_then(|m| m.as_str())
+                    .map(str::to_string),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Percentage complete, clamped to 0-100.
+    pub(crate) fn percent(&self) -> Option<u8> {
+        self.progress
+            .filter(|p| p.is_finite())
+            .map(|p| p.clamp(0.0, 100.0).round() as u8)
+    }
+
+    pub(crate) fn queue_position(&self) -> Option<u64> {
+        self.metadata.as_ref()?.position
+    }
+
     pub(crate) fn results_url(&self) -> Option<String> {
         self.links
             .iter()
@@ -43,15 +111,11 @@ impl ProcessingJobStatus {
     }
 }
 
+/// Body of a job's `results` endpoint: usually a single `asset`, but some datasets return
+/// several named assets, an `assets` map, or a list of values.
 #[derive(Debug, serde::Deserialize)]
-pub(crate) struct ProcessingResults {
-    asset: ProcessingAsset,
-}
-
-#[derive(Debug, serde::Deserialize)]
-struct ProcessingAsset {
-    value: ProcessingAssetValue,
-}
+#[serde(transparent)]
+pub(crate) struct ProcessingResults(serde_json::Map<String, serde_json::Value>);
 
 #[derive(Debug, serde::Deserialize)]
 struct ProcessingAssetValue {
@@ -60,19 +124,79 @@ struct ProcessingAssetValue {
     file_size: u64,
     #[serde(rename = "type")]
     content_type: String,
+    #[serde(default, rename = "file:checksum")]
+    checksum: Option<String>,
 }
 
 impl ProcessingResults {
+    #[cfg(feature = "async")]
     pub(crate) fn to_remote_file(&self, results_url: &str) -> Result<RemoteFile> {
-        let href = self.asset.value.href.trim();
-        if href.is_empty() {
-            return Err(anyhow!("missing results asset href"));
+        self.to_remote_files(results_url)?
+            .into_iter()
+            .next()
+            .ok_or_else(|| CdsError::Protocol("missing results asset".to_string()))
+    }
+
+    /// All result files.
+    pub(crate) fn to_remote_files(&self, results_url: &str) -> Result<Vec<RemoteFile>> {
+        let mut values = Vec::new();
+        for (name, asset) in &self.0 {
+            if name == "assets" {
+                if let Some(assets) = asset.as_object() {
+                    for asset in assets.values() {
+                        collect_asset_values(asset, &mut values);
+                    }
+                }
+            } else {
+                collect_asset_values(asset, &mut values);
+            }
+        }
+        if values.is_empty() {
+            return Err(CdsError::Protocol("missing results asset".to_string()));
         }
 
-        Ok(RemoteFile {
-            location: urljoin(results_url, href),
-            content_length: self.asset.value.file_size,
-            content_type: Some(self.asset.value.content_type.clone()),
+        values
+            .into_iter()
+            .map(|value| {
+                let value: ProcessingAssetValue = serde_json::from_value(value)
+                    .map_err(|e| CdsError::json("invalid results asset", e))?;
+                let href = value.href.trim();
+                if href.is_empty() {
+                    return Err(CdsError::Protocol("missing results asset href".to_string()));
+                }
+                Ok(RemoteFile {
+                    location: urljoin(results_url, href),
+                    content_length: value.file_size,
+                    content_type: Some(value.content_type),
+                    checksum: value.checksum,
+                })
+            })
+            .collect()
+    }
+}
+
+/// Joins the `title`, `traceback` and `detail` of an error body (problem details, as sent
+/// for failed jobs), skipping missing ones.
+pub(crate) fn error_json_to_message(error: &serde_json::Value) -> Option<String> {
+    let parts: Vec<String> = ["title", "traceback", "detail"]
+        .iter()
+        .filter_map(|key| match error.get(key)? {
+            serde_json::Value::String(s) if s.trim().is_empty() => None,
+            serde_json::Value::String(s) => Some(s.trim_end().to_string()),
+            serde_json::Value::Null => None,
+            other => Some(other.to_string()),
         })
+        .collect();
+    (!parts.is_empty()).then(|| parts.join("\n"))
+}
+
+/// Adds the `value` (object or list of objects) of an asset, or the asset itself when it
+/// carries an `href` directly.
+fn collect_asset_values(asset: &serde_json::Value, out: &mut Vec<serde_json::Value>) {
+    match asset.get("value") {
+        Some(serde_json::Value::Array(items)) => out.extend(items.iter().cloned()),
+        Some(value @ serde_json::Value::Object(_)) => out.push(value.clone()),
+        _ if asset.get("href").is_some() => out.push(asset.clone()),
+        _ => {}
     }
 }
@@ -0,0 +1,93 @@
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::client::Client;
+use crate::error::{CdsError, Result};
+
+/// Details of the authenticated account, from the profiles API.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct Profile {
+    /// Account ID.
+    #[serde(default, alias = "sub", alias = "user_uid")]
+    pub id: Option<String>,
+    #[serde(default)]
+    pub email: Option<String>,
+    /// Display name.
+    #[serde(default, alias = "full_name", alias = "fullname")]
+    pub name: Option<String>,
+    /// Account role (e.g. `user`, `admin`).
+    #[serde(default)]
+    pub role: Option<String>,
+    /// Maximum number of requests the account may have queued at once.
+    #[serde(default, alias = "max_queued_requests", alias = "maxQueuedRequests")]
+    pub max_queued: Option<u32>,
+    /// Any other fields returned by the server.
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+/// Outcome of [`Client::check_authentication`].
+#[derive(Debug)]
+#[non_exhaustive]
+pub enum AuthCheck {
+    /// The key was accepted; holds the account it belongs to.
+    Valid(Profile),
+    /// The server does not accept the key, e.g. because it is mistyped or was revoked.
+    InvalidToken { message: String },
+    /// The key was recognised but has expired.
+    ExpiredToken { message: String },
+    /// The server could not be reached (connection failure, timeout or server error), so
+    /// the key could not be checked.
+    NetworkError(CdsError),
+}
+
+impl AuthCheck {
+    /// Returns `true` for [`AuthCheck::Valid`].
+    pub fn is_valid(&self) -> bool {
+        matches!(self, Self::Valid(_))
+    }
+}
+
+impl Client {
+    /// Fetches the profile (details and limits) of the authenticated user.
+    pub fn profile(&self) -> Result<Profile> {
+        self.require_key()?;
+        let url = format!("{}/account", self.profiles_base());
+        self.api_json::<Value, Profile>("GET", &url, &Value::Null)
+    }
+
+    /// Checks the configured key with one cheap authenticated call (the account profile),
+    /// so applications can validate credentials at startup rather than after queueing a
+    /// request.
+    ///
+    /// The call is not retried. Other failures, such as an unexpected reply, are returned
+    /// as errors.
+    pub fn check_authentication(&self) -> Result<AuthCheck> {
+        let client = self.clone().with_retry_max(1);
+        let err = match client.profile() {
+            Ok(profile) => return Ok(AuthCheck::Valid(profile)),
+            Err(err) => err,
+        };
+        match &err {
+            CdsError::InvalidKey {
+                message,
+                expired: true,
+                ..
+            } => Ok(AuthCheck::ExpiredToken {
+                message: message.clone(),
+            }),
+            CdsError::Auth { message, .. } | CdsError::InvalidKey { message, .. } => {
+                Ok(AuthCheck::InvalidToken {
+                    message: message.clone(),
+                })
+            }
+            CdsError::Transport { .. } | CdsError::Timeout { .. } => {
+                Ok(AuthCheck::NetworkError(err))
+            }
+            _ if err.status().is_some_and(|s| s.is_server_error()) => {
+                Ok(AuthCheck::NetworkError(err))
+            }
+            _ => Err(err),
+        }
+    }
+}
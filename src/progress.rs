@@ -0,0 +1,202 @@
+use std::fmt;
+use std::sync::Arc;
+#[cfg(feature = "progress")]
+use std::time::Duration;
+
+use crate::job::JobProgress;
+
+#[cfg(feature = "progress")]
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+#[cfg(feature = "progress")]
+use std::collections::HashMap;
+#[cfg(feature = "progress")]
+use std::sync::Mutex;
+
+/// Receives download progress notifications.
+///
+/// `location` identifies the download (the result URL), so one reporter can track
+/// several downloads at once.
+pub trait ProgressReporter: Send + Sync {
+    /// A download started (or restarted) with `position` bytes already on disk.
+    fn started(&self, location: &str, total: u64, position: u64);
+    /// `bytes` more bytes were written.
+    fn advanced(&self, location: &str, bytes: u64);
+    /// The download is being resumed from `position` after an interruption.
+    fn resumed(&self, location: &str, position: u64) {
+        let _ = (location, position);
+    }
+    /// The download completed.
+    fn finished(&self, location: &str);
+    /// A poll saw the job `job_id` with `progress`; the last call for a job has a finished
+    /// status.
+    fn job_progress(&self, job_id: &str, progress: &JobProgress) {
+        let _ = (job_id, progress);
+    }
+}
+
+/// Reporter that ignores all notifications.
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoProgress;
+
+impl ProgressReporter for NoProgress {
+    fn started(&self, _location: &str, _total: u64, _position: u64) {}
+    fn advanced(&self, _location: &str, _bytes: u64) {}
+    fn finished(&self, _location: &str) {}
+}
+
+/// Default reporter rendering `indicatif` progress bars on stderr.
+///
+/// Concurrent downloads (e.g. from [`Client::retrieve_many`](crate::Client::retrieve_many))
+/// each get their own bar, with a line for the total while more than one is active.
+#[cfg(feature = "progress")]
+#[derive(Debug, Default)]
+pub struct IndicatifProgress {
+    multi: MultiProgress,
+    bars: Mutex<Bars>,
+    /// Spinners of jobs being polled, by job ID.
+    jobs: Mutex<HashMap<String, ProgressBar>>,
+}
+
+/// Bars of active downloads, by location.
+#[cfg(feature = "progress")]
+#[derive(Debug, Default)]
+struct Bars {
+    files: HashMap<String, ProgressBar>,
+    /// Sum over the active downloads, shown while there are several.
+    total: Option<ProgressBar>,
+}
+
+#[cfg(feature = "progress")]
+fn bytes_style(template: &str) -> ProgressStyle {
+    ProgressStyle::with_template(template)
+        .unwrap()
+        .progress_chars("=>-")
+}
+
+#[cfg(feature = "progress")]
+impl ProgressReporter for IndicatifProgress {
+    fn started(&self, location: &str, total: u64, position: u64) {
+        let pb = self.multi.add(ProgressBar::new(total));
+        pb.set_style(bytes_style(
+            "{spinner:.green} {bytes}/{total_bytes} ({bytes_per_sec}) {wide_bar} {eta}",
+        ));
+        pb.set_position(position);
+
+        let mut bars = self.bars.lock().unwrap();
+        if let Some(old) = bars.files.insert(location.to_string(), pb) {
+            // A restarted download replaces its bar.
+            if let Some(sum) = &bars.total {
+                sum.set_length(
+                    sum.length()
+                        .unwrap_or(0)
+                        .saturating_sub(old.length().unwrap_or(0)),
+                );
+                sum.set_position(sum.position().saturating_sub(old.position()));
+            }
+            old.finish_and_clear();
+            self.multi.remove(&old);
+        }
+        match &bars.total {
+            Some(sum) => {
+                sum.inc_length(total);
+                sum.inc(position);
+            }
+            None if bars.files.len() > 1 => {
+                let sum = self.multi.insert(0, ProgressBar::new(0));
+                sum.set_style(bytes_style(
+                    "Total {bytes}/{total_bytes} ({bytes_per_sec}) {wide_bar} {eta}",
+                ));
+                for pb in bars.files.values() {
+                    sum.inc_length(pb.length().unwrap_or(0));
+                    sum.inc(pb.position());
+                }
+                bars.total = Some(sum);
+            }
+            None => {}
+        }
+    }
+
+    fn advanced(&self, location: &str, bytes: u64) {
+        let bars = self.bars.lock().unwrap();
+        if let Some(pb) = bars.files.get(location) {
+            pb.inc(bytes);
+            if let Some(sum) = &bars.total {
+                sum.inc(bytes);
+            }
+        }
+    }
+
+    fn resumed(&self, location: &str, position: u64) {
+        let bars = self.bars.lock().unwrap();
+        if let Some(pb) = bars.files.get(location) {
+            if let Some(sum) = &bars.total {
+                sum.set_position((sum.position() + position).saturating_sub(pb.position()));
+            }
+            pb.set_position(position);
+        }
+    }
+
+    fn finished(&self, location: &str) {
+        let mut bars = self.bars.lock().unwrap();
+        if let Some(pb) = bars.files.remove(location) {
+            pb.finish_and_clear();
+            self.multi.remove(&pb);
+        }
+        if bars.files.is_empty() {
+            if let Some(sum) = bars.total.take() {
+                sum.finish_and_clear();
+                self.multi.remove(&sum);
+            }
+        }
+    }
+
+    fn job_progress(&self, job_id: &str, progress: &JobProgress) {
+        let mut jobs = self.jobs.lock().unwrap();
+        if progress.status.is_finished() {
+            if let Some(pb) = jobs.remove(job_id) {
+                pb.finish_and_clear();
+                self.multi.remove(&pb);
+            }
+            return;
+        }
+        let pb = jobs.entry(job_id.to_string()).or_insert_with(|| {
+            let pb = self.multi.add(ProgressBar::new_spinner());
+            pb.set_style(ProgressStyle::with_template("{spinner:.green} {msg}").unwrap());
+            pb.enable_steady_tick(Duration::from_millis(100));
+            pb
+        });
+        let mut msg = format!("Job {}: {}", job_id, progress.status);
+        if let Some(position) = progress.queue_position {
+            msg.push_str(&format!(" (position {})", position));
+        }
+        if let Some(percent) = progress.percent {
+            msg.push_str(&format!(" {}%", percent));
+        }
+        let secs = progress.elapsed.as_secs();
+        msg.push_str(&format!(" for {}:{:02}", secs / 60, secs % 60));
+        pb.set_message(msg);
+    }
+}
+
+/// Shared reporter handle stored on the clients.
+#[derive(Clone)]
+pub(crate) struct Reporter(pub(crate) Arc<dyn ProgressReporter>);
+
+impl Default for Reporter {
+    fn default() -> Self {
+        #[cfg(feature = "progress")]
+        {
+            Self(Arc::new(IndicatifProgress::default()))
+        }
+        #[cfg(not(feature = "progress"))]
+        {
+            Self(Arc::new(NoProgress))
+        }
+    }
+}
+
+impl fmt::Debug for Reporter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("ProgressReporter")
+    }
+}
@@ -0,0 +1,491 @@
+//! Transport-agnostic core of the Retrieve API workflow (sans-IO).
+//!
+//! [`Retrieval`] knows the URLs, request bodies and job state transitions of a
+//! submit/poll/results cycle but performs no I/O: it hands out [`HttpRequest`]s and is fed
+//! the matching [`HttpResponse`]s, so the workflow can run on any HTTP stack (hyper, ureq,
+//! a browser's `fetch`, ...). [`Client`](crate::Client) and `AsyncClient` follow their
+//! jobs with it too.
+//!
+//! ```no_run
+//! use cdsapi::protocol::{Action, HttpRequest, HttpResponse, Retrieval};
+//!
+//! # fn send(request: &HttpRequest) -> HttpResponse { unimplemented!() }
+//! let request = serde_json::json!({"product_type": ["reanalysis"]});
+//! let mut retrieval = Retrieval::new(
+//!     "https://cds.climate.copernicus.eu/api",
+//!     "<PERSONAL_ACCESS_TOKEN>",
+//!     "reanalysis-era5-single-levels",
+//!     &request,
+//! )?;
+//! let mut response = send(&retrieval.submit_request());
+//! let files = loop {
+//!     match retrieval.handle(response)? {
+//!         Action::Send(request) => response = send(&request),
+//!         Action::Poll { after, request } => {
+//!             std::thread::sleep(after);
+//!             response = send(&request);
+//!         }
+//!         Action::Done(files) => break files,
+//!         _ => unreachable!(),
+//!     }
+//! };
+//! # Ok::<(), cdsapi::CdsError>(())
+//! ```
+//!
+//! Only the Retrieve API (personal access tokens) is covered; legacy `UID:KEY` accounts
+//! use the clients.
+
+use reqwest::StatusCode;
+use reqwest::header::{HeaderMap, HeaderName, HeaderValue};
+use serde::Serialize;
+use serde_json::Value;
+use std::fmt;
+use std::time::Duration;
+
+use crate::error::{CdsError, ErrorResponse, Result, error_from_response};
+use crate::processing::{
+    ProcessingJob, ProcessingJobStatus, ProcessingResults, error_json_to_message,
+};
+use crate::util::{append_query, backoff};
+
+/// Root of the Retrieve API under the API URL `url`.
+pub(crate) fn retrieve_base(url: &str) -> String {
+    format!("{}/retrieve/v1", url.trim_end_matches('/'))
+}
+
+/// Where requests for `dataset` are submitted.
+pub(crate) fn execution_url(retrieve_base: &str, dataset: &str) -> String {
+    format!("{}/processes/{}/execution", retrieve_base, dataset)
+}
+
+/// Submission body wrapping `request`.
+pub(crate) fn execution_body<T: Serialize>(request: &T) -> Value {
+    serde_json::json!({ "inputs": request })
+}
+
+/// Status URL of the job `id`.
+pub(crate) fn job_url(retrieve_base: &str, id: &str) -> String {
+    format!("{}/jobs/{}", retrieve_base, id)
+}
+
+/// Status URL of a submitted job: its `monitor` link, or else built from its ID.
+pub(crate) fn monitor_url(job: &ProcessingJob, retrieve_base: &str) -> Result<String> {
+    job.monitor_url()
+        .or_else(|| job.job_id.as_deref().map(|id| job_url(retrieve_base, id)))
+        .ok_or_else(|| {
+            CdsError::Protocol("missing monitor link in job submission response".to_string())
+        })
+}
+
+/// Status URL asking for the job's log and request too.
+pub(crate) fn status_url(monitor_url: &str) -> String {
+    append_query(monitor_url, &[("log", "true"), ("request", "true")])
+}
+
+/// Results URL of a job: its `results` link, or else below its status URL.
+pub(crate) fn results_url(monitor_url: &str, status: &ProcessingJobStatus) -> String {
+    status
+        .results_url()
+        .unwrap_or_else(|| format!("{}/results", monitor_url.trim_end_matches('/')))
+}
+
+/// Message of [`CdsError::JobFailed`] for a job that ended with `status`.
+pub(crate) fn failure_message(status: &str, details: Option<String>) -> String {
+    match details {
+        Some(details) => format!("processing failed with status {}: {}", status, details),
+        None => format!("processing failed with status {}", status),
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct RemoteFile {
+    /// Download URL.
+    pub location: String,
+    /// Expected content length (bytes).
+    pub content_length: u64,
+    /// Optional content type.
+    pub content_type: Option<String>,
+    /// Checksum published with the result (`file:checksum`), if any.
+    pub checksum: Option<String>,
+}
+
+/// Lifecycle state of a submitted request, normalized across the legacy and Retrieve APIs.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum JobStatus {
+    /// Waiting in the CDS queue (`queued` / `accepted`).
+    Queued,
+    /// Being processed (`running`).
+    Running,
+    /// Finished; results are ready to download (`completed` / `successful`).
+    Successful,
+    /// Processing failed or the request was rejected (`failed` / `rejected`).
+    Failed,
+    /// Removed before completion (`dismissed` / `deleted`).
+    Dismissed,
+    /// Any state this crate does not know about.
+    Unknown(String),
+}
+
+impl JobStatus {
+    pub(crate) fn from_api(state: &str) -> Self {
+        match state {
+            "queued" | "accepted" => Self::Queued,
+            "running" => Self::Running,
+            "completed" | "successful" => Self::Successful,
+            "failed" | "rejected" => Self::Failed,
+            "dismissed" | "deleted" => Self::Dismissed,
+            other => Self::Unknown(other.to_string()),
+        }
+    }
+
+    /// Returns `true` once the job will not change state anymore.
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Successful | Self::Failed | Self::Dismissed)
+    }
+}
+
+impl fmt::Display for JobStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Queued => f.write_str("queued"),
+            Self::Running => f.write_str("running"),
+            Self::Successful => f.write_str("successful"),
+            Self::Failed => f.write_str("failed"),
+            Self::Dismissed => f.write_str("dismissed"),
+            Self::Unknown(s) => f.write_str(s),
+        }
+    }
+}
+
+/// An HTTP request to send, produced by [`Retrieval`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct HttpRequest {
+    /// `GET` or `POST`.
+    pub method: String,
+    pub url: String,
+    /// Headers to send, including authentication; add your own `User-Agent`.
+    pub headers: Vec<(String, String)>,
+    /// JSON body, for `POST`.
+    pub body: Option<Vec<u8>>,
+}
+
+/// An HTTP response to feed to [`Retrieval::handle`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HttpResponse {
+    pub status: u16,
+    pub headers: Vec<(String, String)>,
+    pub body: Vec<u8>,
+}
+
+impl HttpResponse {
+    pub fn new(status: u16, body: impl Into<Vec<u8>>) -> Self {
+        Self {
+            status,
+            headers: Vec::new(),
+            body: body.into(),
+        }
+    }
+
+    /// Adds a response header, e.g. `Retry-After`, used in error messages.
+    pub fn with_header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+}
+
+/// What to do next in a [`Retrieval`].
+#[derive(Debug, Clone)]
+#[non_exhaustive]
+pub enum Action {
+    /// Send `request` now and pass its response to [`Retrieval::handle`].
+    Send(HttpRequest),
+    /// The job is still queued or running: wait `after`, then send `request`.
+    Poll {
+        after: Duration,
+        request: HttpRequest,
+    },
+    /// The job finished; download these result files.
+    Done(Vec<RemoteFile>),
+}
+
+/// Submit/poll/results cycle of one request, driven by the caller's HTTP stack.
+///
+/// Start by sending [`Retrieval::submit_request`], then pass each response to
+/// [`Retrieval::handle`] until it returns [`Action::Done`] or an error. Requests are sent
+/// as given; normalization (see [`normalize_request`](crate::normalize_request)) is up to
+/// the caller.
+#[derive(Debug, Clone)]
+pub struct Retrieval {
+    retrieve_base: String,
+    key: String,
+    dataset: String,
+    body: Vec<u8>,
+    phase: Phase,
+    poll_max: Duration,
+    sleep: Duration,
+    job_id: Option<String>,
+    status: Option<JobStatus>,
+    percent: Option<u8>,
+    queue_position: Option<u64>,
+    polls: u64,
+    logs: Vec<String>,
+}
+
+#[derive(Debug, Clone)]
+enum Phase {
+    Submitting,
+    Polling {
+        monitor_url: String,
+    },
+    Results {
+        results_url: String,
+    },
+    /// Fetching the error details of a job that ended with `status`.
+    FailureDetails {
+        status: String,
+    },
+    Finished,
+}
+
+impl Retrieval {
+    /// Prepares the retrieval of `request` from `dataset` on the API at `url` (e.g.
+    /// `https://cds.climate.copernicus.eu/api`) with the personal access token `key`.
+    pub fn new<T: Serialize>(url: &str, key: &str, dataset: &str, request: &T) -> Result<Self> {
+        let body = serde_json::to_vec(&execution_body(request))
+            .map_err(|e| CdsError::json("failed to serialize request", e))?;
+        Ok(Self {
+            retrieve_base: retrieve_base(url),
+            key: key.trim().to_string(),
+            dataset: dataset.to_string(),
+            body,
+            phase: Phase::Submitting,
+            poll_max: Duration::from_secs(120),
+            sleep: Duration::from_secs(1),
+            job_id: None,
+            status: None,
+            percent: None,
+            queue_position: None,
+            polls: 0,
+            logs: Vec::new(),
+        })
+    }
+
+    /// Follows the job whose status URL is `monitor_url`, e.g. one submitted earlier, with
+    /// the personal access token `key`. Start by sending [`Retrieval::poll_request`].
+    ///
+    /// The [job ID](Retrieval::job_id) is taken from the last segment of the URL.
+    pub fn follow(monitor_url: &str, key: &str) -> Self {
+        let monitor_url = monitor_url.trim_end_matches('/').to_string();
+        Self {
+            retrieve_base: String::new(),
+            key: key.trim().to_string(),
+            dataset: String::new(),
+            body: Vec::new(),
+            job_id: monitor_url.rsplit('/').next().map(str::to_string),
+            phase: Phase::Polling { monitor_url },
+            poll_max: Duration::from_secs(120),
+            sleep: Duration::from_secs(1),
+            status: None,
+            percent: None,
+            queue_position: None,
+            polls: 0,
+            logs: Vec::new(),
+        }
+    }
+
+    /// Sets the range of the status polling interval (default 1s, growing to 120s).
+    pub fn with_poll_interval(mut self, min: Duration, max: Duration) -> Self {
+        self.sleep = min;
+        self.poll_max = max.max(min);
+        self
+    }
+
+    /// The request submitting the job; its response goes to [`Retrieval::handle`] first.
+    pub fn submit_request(&self) -> HttpRequest {
+        let mut request = self.request("POST", execution_url(&self.retrieve_base, &self.dataset));
+        request
+            .headers
+            .push(("Content-Type".to_string(), "application/json".to_string()));
+        request.body = Some(self.body.clone());
+        request
+    }
+
+    /// The request polling the job's status, once it is submitted and still followed.
+    pub fn poll_request(&self) -> Option<HttpRequest> {
+        match &self.phase {
+            Phase::Polling { monitor_url } => Some(self.request("GET", status_url(monitor_url))),
+            _ => None,
+        }
+    }
+
+    /// Server-side ID of the job, once submitted.
+    pub fn job_id(&self) -> Option<&str> {
+        self.job_id.as_deref()
+    }
+
+    /// Status seen by the last poll.
+    pub fn status(&self) -> Option<&JobStatus> {
+        self.status.as_ref()
+    }
+
+    /// Percentage complete reported by the last poll, if any.
+    pub fn percent(&self) -> Option<u8> {
+        self.percent
+    }
+
+    /// Queue position reported by the last poll, if any.
+    pub fn queue_position(&self) -> Option<u64> {
+        self.queue_position
+    }
+
+    /// Number of status responses handled so far; it grows by one with each poll.
+    pub fn polls(&self) -> u64 {
+        self.polls
+    }
+
+    /// Server log lines reported so far.
+    pub fn logs(&self) -> &[String] {
+        &self.logs
+    }
+
+    /// Takes the response to the last request and returns what to do next.
+    ///
+    /// Errors end the retrieval: later calls fail with [`CdsError::Protocol`].
+    pub fn handle(&mut self, response: HttpResponse) -> Result<Action> {
+        let phase = std::mem::replace(&mut self.phase, Phase::Finished);
+        self.advance(phase, response)
+    }
+
+    fn advance(&mut self, phase: Phase, response: HttpResponse) -> Result<Action> {
+        if let Phase::FailureDetails { status } = phase {
+            // Failed jobs answer with an error body, usually with a 4xx status.
+            let details = serde_json::from_slice::<Value>(&response.body)
+                .ok()
+                .and_then(|body| error_json_to_message(&body));
+            return Err(self.failed(&status, details));
+        }
+        let url = match &phase {
+            Phase::Submitting => execution_url(&self.retrieve_base, &self.dataset),
+            Phase::Polling { monitor_url } => status_url(monitor_url),
+            Phase::Results { results_url } => results_url.clone(),
+            Phase::FailureDetails { .. } | Phase::Finished => {
+                return Err(CdsError::Protocol("retrieval already finished".to_string()));
+            }
+        };
+        if !(200..300).contains(&response.status) {
+            return Err(error_from_response(error_response(&url, response)));
+        }
+
+        match phase {
+            Phase::Submitting => {
+                let job: ProcessingJob = parse(&url, &response)?;
+                let monitor_url = monitor_url(&job, &self.retrieve_base)?;
+                self.job_id = job.job_id;
+                let request = self.request("GET", status_url(&monitor_url));
+                self.phase = Phase::Polling { monitor_url };
+                Ok(Action::Send(request))
+            }
+            Phase::Polling { monitor_url } => {
+                let status: ProcessingJobStatus = parse(&url, &response)?;
+                self.polls += 1;
+                self.percent = status.percent();
+                self.queue_position = status.queue_position();
+                let lines = status.log_lines();
+                if lines.len() > self.logs.len() {
+                    self.logs = lines;
+                }
+                let job_status = JobStatus::from_api(&status.status);
+                self.status = Some(job_status.clone());
+                match job_status {
+                    JobStatus::Successful => {
+                        let results_url = results_url(&monitor_url, &status);
+                        let request = self.request("GET", results_url.clone());
+                        self.phase = Phase::Results { results_url };
+                        Ok(Action::Send(request))
+                    }
+                    JobStatus::Queued | JobStatus::Running => {
+                        let after = self.sleep;
+                        self.sleep = backoff(self.sleep, self.poll_max);
+                        let request = self.request("GET", url);
+                        self.phase = Phase::Polling { monitor_url };
+                        Ok(Action::Poll { after, request })
+                    }
+                    JobStatus::Failed | JobStatus::Dismissed => match status.error_message() {
+                        Some(details) => Err(self.failed(&status.status, Some(details))),
+                        None => {
+                            let request = self.request("GET", results_url(&monitor_url, &status));
+                            self.phase = Phase::FailureDetails {
+                                status: status.status,
+                            };
+                            Ok(Action::Send(request))
+                        }
+                    },
+                    JobStatus::Unknown(other) => Err(CdsError::Protocol(format!(
+                        "unknown processing status [{}]",
+                        other
+                    ))),
+                }
+            }
+            Phase::Results { results_url } => {
+                let results: ProcessingResults = parse(&url, &response)?;
+                Ok(Action::Done(results.to_remote_files(&results_url)?))
+            }
+            Phase::FailureDetails { .. } | Phase::Finished => unreachable!("handled above"),
+        }
+    }
+
+    fn request(&self, method: &str, url: String) -> HttpRequest {
+        HttpRequest {
+            method: method.to_string(),
+            url,
+            headers: vec![("PRIVATE-TOKEN".to_string(), self.key.clone())],
+            body: None,
+        }
+    }
+
+    fn failed(&self, status: &str, details: Option<String>) -> CdsError {
+        CdsError::JobFailed {
+            message: failure_message(status, details),
+            logs: self.logs.clone(),
+        }
+    }
+}
+
+/// Name/value pairs of `headers`, skipping values that are not valid strings.
+pub(crate) fn header_pairs(headers: &HeaderMap) -> Vec<(String, String)> {
+    headers
+        .iter()
+        .filter_map(|(name, value)| Some((name.to_string(), value.to_str().ok()?.to_string())))
+        .collect()
+}
+
+fn parse<T: serde::de::DeserializeOwned>(url: &str, response: &HttpResponse) -> Result<T> {
+    serde_json::from_slice(&response.body).map_err(|e| {
+        CdsError::json(
+            format!(
+                "failed to parse API JSON (url={}, status={})",
+                url, response.status
+            ),
+            e,
+        )
+    })
+}
+
+fn error_response(url: &str, response: HttpResponse) -> ErrorResponse {
+    let mut headers = HeaderMap::new();
+    for (name, value) in &response.headers {
+        if let (Ok(name), Ok(value)) = (
+            HeaderName::from_bytes(name.as_bytes()),
+            HeaderValue::from_str(value),
+        ) {
+            headers.append(name, value);
+        }
+    }
+    ErrorResponse {
+        status: StatusCode::from_u16(response.status).unwrap_or(StatusCode::BAD_GATEWAY),
+        url: url.to_string(),
+        headers,
+        body: String::from_utf8_lossy(&response.body).into_owned(),
+    }
+}
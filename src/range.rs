@@ -0,0 +1,112 @@
+use serde::Serialize;
+use std::collections::HashSet;
+use std::path::PathBuf;
+
+use crate::batch::BatchItem;
+use crate::client::Client;
+use crate::dates::DateRange;
+use crate::error::{CdsError, Result};
+use crate::filename::expand_target_template;
+
+/// Granularity of the requests made by [`Client::retrieve_range`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Period {
+    Day,
+    Month,
+    Year,
+}
+
+impl Client {
+    /// Retrieves `request` once per `period` of `dates`, with up to `concurrency` requests in
+    /// flight, and returns the target files in date order.
+    ///
+    /// Each request is `request` with `year`/`month`/`day` set to the days of its period.
+    /// Targets come from `target_template`, whose `{year}`, `{month}`, `{day}` and `{date}`
+    /// (`YYYY-MM-DD`) placeholders are filled from the first day of the period, e.g.
+    /// `era5_{year}-{month}.grib` for monthly files. Other placeholders are
+    /// [expanded](crate::expand_target_template) from the request, e.g. `{variable}`.
+    ///
+    /// An interrupted run can simply be restarted. With [`Client::with_state_file`], existing
+    /// targets are skipped unless their job is still pending in the state file, in which
+    /// case the recorded job is followed and the download resumed. Without one, existing
+    /// targets are requested again and kept only if their size matches the result (see
+    /// [`Client::with_skip_unchanged`]); partial ones are resumed.
+    pub fn retrieve_range<T: Serialize>(
+        &self,
+        dataset: &str,
+        request: &T,
+        dates: &DateRange,
+        period: Period,
+        target_template: &str,
+        concurrency: usize,
+    ) -> Result<Vec<PathBuf>> {
+        let template = serde_json::to_value(request)
+            .map_err(|e| CdsError::json("failed to serialize request", e))?;
+        let periods = match period {
+            Period::Day => dates.by_day(),
+            Period::Month => dates.by_month(),
+            Period::Year => dates.by_year(),
+        };
+        let pending: HashSet<PathBuf> = match &self.state {
+            Some(state) => state
+                .entries()?
+                .into_iter()
+                .filter_map(|entry| entry.target)
+                .collect(),
+            None => HashSet::new(),
+        };
+
+        let mut paths = Vec::new();
+        let mut items = Vec::new();
+        for range in &periods {
+            let mut request = template.clone();
+            range.apply_to(&mut request);
+            let target = expand_target_template(&fill_dates(target_template, range), &request)?;
+            if paths.contains(&target) {
+                return Err(CdsError::Config(format!(
+                    "target template {:?} maps several periods to {}",
+                    target_template,
+                    target.display()
+                )));
+            }
+            paths.push(target.clone());
+            // Only a state file tells complete targets from partial ones up front.
+            if self.state.is_some() && target.exists() && !pending.contains(&target) {
+                self.log_info(format_args!(
+                    "{} already exists, skipping",
+                    target.display()
+                ));
+                continue;
+            }
+            items.push(BatchItem {
+                dataset: dataset.to_string(),
+                request,
+                target,
+            });
+        }
+
+        let client = match self.state {
+            Some(_) => self.clone(),
+            None => self.clone().with_skip_unchanged(true),
+        };
+        let mut first_error = None;
+        for done in client.retrieve_many(items, concurrency) {
+            if let Err(e) = done.result {
+                first_error.get_or_insert(e);
+            }
+        }
+        match first_error {
+            Some(e) => Err(e),
+            None => Ok(paths),
+        }
+    }
+}
+
+fn fill_dates(template: &str, range: &DateRange) -> String {
+    let first = range.dates()[0];
+    template
+        .replace("{year}", &first.format("%Y").to_string())
+        .replace("{month}", &first.format("%m").to_string())
+        .replace("{day}", &first.format("%d").to_string())
+        .replace("{date}", &first.format("%Y-%m-%d").to_string())
+}
@@ -0,0 +1,34 @@
+//! Strongly typed request builders for popular datasets.
+//!
+//! The builders serialize to the JSON the CDS expects, so they can be passed to
+//! [`Client::retrieve`](crate::Client::retrieve) and friends like any `serde_json::Value`:
+//!
+//! ```no_run
+//! use cdsapi::requests::{DatasetRequest, era5::Era5PressureLevels};
+//!
+//! let request = Era5PressureLevels::new()
+//!     .variables(["geopotential"])
+//!     .years([2024])
+//!     .months([3])
+//!     .days([1])
+//!     .hours([13])
+//!     .pressure_levels([1000]);
+//! let client = cdsapi::Client::from_env()?;
+//! client.retrieve(request.dataset(), &request, Some("download.grib".as_ref()))?;
+//! # Ok::<(), cdsapi::CdsError>(())
+//! ```
+//!
+//! Requests can also be kept in JSON, TOML or YAML files and loaded with [`Request`].
+
+pub mod era5;
+mod template;
+
+pub use template::{Request, RequestFormat};
+
+use serde::Serialize;
+
+/// A typed request that knows the dataset it is meant for.
+pub trait DatasetRequest: Serialize {
+    /// Dataset (process) ID, e.g. `reanalysis-era5-single-levels`.
+    fn dataset(&self) -> &'static str;
+}
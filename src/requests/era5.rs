@@ -0,0 +1,194 @@
+//! Builders for the ERA5 reanalysis datasets on hourly single and pressure levels.
+
+use serde::Serialize;
+use std::marker::PhantomData;
+
+use super::DatasetRequest;
+use crate::area::Area;
+#[cfg(feature = "chrono")]
+use crate::dates::DateRange;
+use crate::dates::TimeSteps;
+
+/// `product_type` of an ERA5 request.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ProductType {
+    Reanalysis,
+    EnsembleMembers,
+    EnsembleMean,
+    EnsembleSpread,
+}
+
+/// `data_format` of an ERA5 request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DataFormat {
+    #[default]
+    Grib,
+    Netcdf,
+}
+
+/// `download_format` of an ERA5 request.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DownloadFormat {
+    #[default]
+    Unarchived,
+    Zip,
+}
+
+/// Marker for `reanalysis-era5-single-levels`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SingleLevels;
+
+/// Marker for `reanalysis-era5-pressure-levels`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PressureLevels;
+
+/// Request for `reanalysis-era5-single-levels`.
+pub type Era5SingleLevels = Era5Request<SingleLevels>;
+
+/// Request for `reanalysis-era5-pressure-levels`.
+pub type Era5PressureLevels = Era5Request<PressureLevels>;
+
+/// ERA5 hourly request; use the [`Era5SingleLevels`] and [`Era5PressureLevels`] aliases.
+///
+/// Numeric values are formatted the way the CDS expects (`"03"` for March, `"13:00"` for
+/// 13 UTC). Without [`product_types`](Self::product_types), the request asks for
+/// `reanalysis`.
+#[derive(Debug, Clone, Serialize)]
+pub struct Era5Request<D> {
+    product_type: Vec<ProductType>,
+    variable: Vec<String>,
+    year: Vec<String>,
+    month: Vec<String>,
+    day: Vec<String>,
+    time: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pressure_level: Vec<String>,
+    data_format: DataFormat,
+    download_format: DownloadFormat,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    area: Option<Area>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    grid: Option<[f64; 2]>,
+    #[serde(skip)]
+    dataset: PhantomData<D>,
+}
+
+impl<D> Default for Era5Request<D> {
+    fn default() -> Self {
+        Self {
+            product_type: vec![ProductType::Reanalysis],
+            variable: Vec::new(),
+            year: Vec::new(),
+            month: Vec::new(),
+            day: Vec::new(),
+            time: Vec::new(),
+            pressure_level: Vec::new(),
+            data_format: DataFormat::default(),
+            download_format: DownloadFormat::default(),
+            area: None,
+            grid: None,
+            dataset: PhantomData,
+        }
+    }
+}
+
+impl<D> Era5Request<D> {
+    /// Creates an empty `reanalysis` request in GRIB format.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn product_types(mut self, types: impl IntoIterator<Item = ProductType>) -> Self {
+        self.product_type = types.into_iter().collect();
+        self
+    }
+
+    /// Variable names, e.g. `2m_temperature` or `geopotential`.
+    pub fn variables<S: Into<String>>(mut self, variables: impl IntoIterator<Item = S>) -> Self {
+        self.variable = variables.into_iter().map(Into::into).collect();
+        self
+    }
+
+    pub fn years(mut self, years: impl IntoIterator<Item = u16>) -> Self {
+        self.year = years.into_iter().map(|y| y.to_string()).collect();
+        self
+    }
+
+    /// Months, 1 to 12.
+    pub fn months(mut self, months: impl IntoIterator<Item = u8>) -> Self {
+        self.month = months.into_iter().map(|m| format!("{:02}", m)).collect();
+        self
+    }
+
+    /// Days of the month, 1 to 31.
+    pub fn days(mut self, days: impl IntoIterator<Item = u8>) -> Self {
+        self.day = days.into_iter().map(|d| format!("{:02}", d)).collect();
+        self
+    }
+
+    /// Hours (UTC), 0 to 23.
+    pub fn hours(mut self, hours: impl IntoIterator<Item = u8>) -> Self {
+        self.time = hours.into_iter().map(|h| format!("{:02}:00", h)).collect();
+        self
+    }
+
+    /// Sets `time` from `steps`, e.g. [`TimeSteps::hourly(3)`](TimeSteps::hourly).
+    pub fn times(mut self, steps: TimeSteps) -> Self {
+        self.time = steps.into();
+        self
+    }
+
+    /// Sets `year`, `month` and `day` from `dates`; see [`DateRange::is_exact`].
+    #[cfg(feature = "chrono")]
+    pub fn dates(mut self, dates: &DateRange) -> Self {
+        self.year = dates.years();
+        self.month = dates.months();
+        self.day = dates.days();
+        self
+    }
+
+    pub fn data_format(mut self, format: DataFormat) -> Self {
+        self.data_format = format;
+        self
+    }
+
+    pub fn download_format(mut self, format: DownloadFormat) -> Self {
+        self.download_format = format;
+        self
+    }
+
+    /// Restricts the request to `area`.
+    pub fn area(mut self, area: Area) -> Self {
+        self.area = Some(area);
+        self
+    }
+
+    /// Output grid resolution as `[lat, lon]` increments in degrees.
+    pub fn grid(mut self, grid: [f64; 2]) -> Self {
+        self.grid = Some(grid);
+        self
+    }
+}
+
+impl Era5Request<PressureLevels> {
+    /// Pressure levels in hPa, e.g. `[500, 850, 1000]`.
+    pub fn pressure_levels(mut self, levels: impl IntoIterator<Item = u16>) -> Self {
+        self.pressure_level = levels.into_iter().map(|l| l.to_string()).collect();
+        self
+    }
+}
+
+impl DatasetRequest for Era5Request<SingleLevels> {
+    fn dataset(&self) -> &'static str {
+        "reanalysis-era5-single-levels"
+    }
+}
+
+impl DatasetRequest for Era5Request<PressureLevels> {
+    fn dataset(&self) -> &'static str {
+        "reanalysis-era5-pressure-levels"
+    }
+}
@@ -0,0 +1,149 @@
+use serde::{Serialize, Serializer};
+use serde_json::Value;
+use std::path::Path;
+
+use crate::error::{CdsError, Result};
+
+/// File format of a request template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RequestFormat {
+    Json,
+    Toml,
+    /// Requires the `yaml` feature.
+    Yaml,
+}
+
+impl RequestFormat {
+    /// Guesses the format from a file extension (`json`, `toml`, `yaml`/`yml`).
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()?.to_lowercase().as_str() {
+            "json" => Some(Self::Json),
+            "toml" => Some(Self::Toml),
+            "yaml" | "yml" => Some(Self::Yaml),
+            _ => None,
+        }
+    }
+}
+
+/// A request loaded from a template file, so request definitions can live outside Rust
+/// code (and be shared with Python `cdsapi` users).
+///
+/// The file holds either the request itself, or a `dataset` and a `request` table:
+///
+/// ```yaml
+/// dataset: reanalysis-era5-single-levels
+/// request:
+///   product_type: [reanalysis]
+///   variable: [2m_temperature]
+///   year: ["${YEAR}"]
+/// ```
+///
+/// `${NAME}` placeholders are replaced before parsing, from the variables passed to
+/// [`Request::from_path_with_vars`] or else from the environment.
+///
+/// A `Request` serializes as its request body, so it can be passed to
+/// [`Client::retrieve`](crate::Client::retrieve) directly.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Request {
+    /// Dataset named in the file, if any.
+    pub dataset: Option<String>,
+    pub request: Value,
+}
+
+impl Request {
+    /// Loads a JSON, TOML or YAML template, chosen by the file extension.
+    pub fn from_path(path: impl AsRef<Path>) -> Result<Self> {
+        Self::from_path_with_vars(path, &[])
+    }
+
+    /// Like [`Request::from_path`], substituting `${NAME}` placeholders from `vars` first.
+    pub fn from_path_with_vars(path: impl AsRef<Path>, vars: &[(&str, &str)]) -> Result<Self> {
+        let path = path.as_ref();
+        let format = RequestFormat::from_path(path).ok_or_else(|| {
+            CdsError::Config(format!(
+                "cannot tell the format of {} (expected .json, .toml, .yaml or .yml)",
+                path.display()
+            ))
+        })?;
+        let text = std::fs::read_to_string(path)
+            .map_err(|e| CdsError::io(format!("failed to read {}", path.display()), e))?;
+        Self::parse(&text, format, vars).map_err(|e| {
+            CdsError::Config(format!(
+                "invalid request template {}: {}",
+                path.display(),
+                e
+            ))
+        })
+    }
+
+    /// Parses a template from `text`.
+    pub fn parse(text: &str, format: RequestFormat, vars: &[(&str, &str)]) -> Result<Self> {
+        let text = substitute(text, vars)?;
+        let value: Value = match format {
+            RequestFormat::Json => {
+                serde_json::from_str(&text).map_err(|e| CdsError::Config(e.to_string()))?
+            }
+            RequestFormat::Toml => {
+                toml::from_str(&text).map_err(|e| CdsError::Config(e.to_string()))?
+            }
+            #[cfg(feature = "yaml")]
+            RequestFormat::Yaml => {
+                serde_yaml::from_str(&text).map_err(|e| CdsError::Config(e.to_string()))?
+            }
+            #[cfg(not(feature = "yaml"))]
+            RequestFormat::Yaml => {
+                return Err(CdsError::Config(
+                    "YAML templates require the `yaml` feature".to_string(),
+                ));
+            }
+        };
+
+        let Value::Object(mut map) = value else {
+            return Err(CdsError::Config(
+                "a request must be a table/object".to_string(),
+            ));
+        };
+        if let (Some(Value::String(_)), Some(Value::Object(_))) =
+            (map.get("dataset"), map.get("request"))
+        {
+            let dataset = map
+                .remove("dataset")
+                .and_then(|d| d.as_str().map(str::to_string));
+            let request = map.remove("request").unwrap_or_default();
+            return Ok(Self { dataset, request });
+        }
+        Ok(Self {
+            dataset: None,
+            request: Value::Object(map),
+        })
+    }
+}
+
+impl Serialize for Request {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        self.request.serialize(serializer)
+    }
+}
+
+/// Replaces `${NAME}` with the value from `vars`, else from the environment.
+fn substitute(text: &str, vars: &[(&str, &str)]) -> Result<String> {
+    let mut out = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find("${") {
+        out.push_str(&rest[..start]);
+        let after = &rest[start + 2..];
+        let end = after
+            .find('}')
+            .ok_or_else(|| CdsError::Config("unterminated ${...} placeholder".to_string()))?;
+        let name = &after[..end];
+        let value = match vars.iter().find(|(n, _)| *n == name) {
+            Some((_, v)) => v.to_string(),
+            None => std::env::var(name)
+                .map_err(|_| CdsError::Config(format!("no value for placeholder ${{{}}}", name)))?,
+        };
+        out.push_str(&value);
+        rest = &after[end + 1..];
+    }
+    out.push_str(rest);
+    Ok(out)
+}
@@ -0,0 +1,252 @@
+use reqwest::StatusCode;
+use std::collections::BTreeSet;
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// Statuses retried by default: request timeout, rate limiting, and transient server errors.
+const DEFAULT_RETRIABLE: [u16; 6] = [408, 429, 500, 502, 503, 504];
+
+/// Controls how failed HTTP requests and interrupted downloads are retried.
+///
+/// The delay before retry `n` (starting at 1) is `base_delay * factor^(n-1)`, capped at
+/// `max_delay`, then randomized by `±jitter` (a fraction between 0 and 1). A `Retry-After`
+/// header sent by the server takes precedence.
+///
+/// Retrying stops once the time spent would exceed
+/// [`max_elapsed`](RetryPolicy::with_max_elapsed), or after `max_attempts`, whichever
+/// comes first.
+///
+/// The default, used for API calls, waits 120s between attempts, with no backoff and no
+/// jitter, and gives up after 30 minutes (or 500 attempts). Downloads are resumed
+/// following [`RetryPolicy::for_downloads`] unless configured otherwise.
+///
+/// ```
+/// use std::time::Duration;
+///
+/// let policy = cdsapi::RetryPolicy::new()
+///     .with_max_attempts(10)
+///     .with_base_delay(Duration::from_secs(1))
+///     .with_factor(2.0)
+///     .with_max_delay(Duration::from_secs(60))
+///     .with_jitter(0.2)
+///     .with_max_elapsed(Duration::from_secs(30 * 60));
+/// assert_eq!(policy.delay(3).as_secs(), 4);
+/// ```
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    max_attempts: usize,
+    max_elapsed: Option<Duration>,
+    base_delay: Duration,
+    max_delay: Duration,
+    factor: f64,
+    jitter: f64,
+    statuses: BTreeSet<u16>,
+    predicate: RetryPredicate,
+}
+
+/// Custom test set with [`RetryPolicy::with_retry_predicate`].
+#[derive(Clone, Default)]
+struct RetryPredicate(Option<Arc<dyn Fn(StatusCode) -> bool + Send + Sync>>);
+
+impl fmt::Debug for RetryPredicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(if self.0.is_some() {
+            "retry predicate"
+        } else {
+            "no retry predicate"
+        })
+    }
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: 500,
+            max_elapsed: Some(Duration::from_secs(30 * 60)),
+            base_delay: Duration::from_secs(120),
+            max_delay: Duration::from_secs(120),
+            factor: 1.0,
+            jitter: 0.0,
+            statuses: DEFAULT_RETRIABLE.into_iter().collect(),
+            predicate: RetryPredicate::default(),
+        }
+    }
+}
+
+impl RetryPolicy {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Default policy for resuming interrupted downloads: 10 attempts, waiting 2s, 4s, 8s,
+    /// ... up to 60s, with 20% jitter, within 30 minutes without progress.
+    pub fn for_downloads() -> Self {
+        Self::default()
+            .with_max_attempts(10)
+            .with_base_delay(Duration::from_secs(2))
+            .with_factor(2.0)
+            .with_max_delay(Duration::from_secs(60))
+            .with_jitter(0.2)
+    }
+
+    /// Maximum number of attempts, including the first one.
+    pub fn with_max_attempts(mut self, attempts: usize) -> Self {
+        self.max_attempts = attempts.max(1);
+        self
+    }
+
+    /// Gives up once retrying would take longer than `budget` in total, whatever the
+    /// attempt count: no retry is made if its delay ends past the budget (default 30
+    /// minutes).
+    ///
+    /// For API calls the clock starts with the first attempt. For downloads it restarts
+    /// whenever data arrives, so only time spent failing to make progress counts.
+    pub fn with_max_elapsed(mut self, budget: Duration) -> Self {
+        self.max_elapsed = Some(budget);
+        self
+    }
+
+    /// Delay before the first retry.
+    pub fn with_base_delay(mut self, delay: Duration) -> Self {
+        self.base_delay = delay;
+        self
+    }
+
+    /// Upper bound for the delay between attempts (before jitter).
+    pub fn with_max_delay(mut self, delay: Duration) -> Self {
+        self.max_delay = delay;
+        self
+    }
+
+    /// Multiplier applied to the delay after each retry (`1.0` = constant delay).
+    pub fn with_factor(mut self, factor: f64) -> Self {
+        self.factor = factor.max(1.0);
+        self
+    }
+
+    /// Randomizes each delay by up to `±jitter` of its value (clamped to `0.0..=1.0`).
+    pub fn with_jitter(mut self, jitter: f64) -> Self {
+        self.jitter = jitter.clamp(0.0, 1.0);
+        self
+    }
+
+    /// Replaces the set of HTTP statuses that are retried (by default 408, 429, 500, 502,
+    /// 503 and 504).
+    pub fn with_retriable_statuses(mut self, statuses: impl IntoIterator<Item = u16>) -> Self {
+        self.statuses = statuses.into_iter().collect();
+        self
+    }
+
+    /// Also retries `status`, e.g. 520, 522 or 524 from a Cloudflare gateway.
+    pub fn with_retriable_status(mut self, status: u16) -> Self {
+        self.statuses.insert(status);
+        self
+    }
+
+    /// Stops retrying `status`, e.g. 429 to fail fast when rate limited.
+    pub fn without_retriable_status(mut self, status: u16) -> Self {
+        self.statuses.remove(&status);
+        self
+    }
+
+    /// Decides which statuses are retried with `predicate` instead of the status set.
+    pub fn with_retry_predicate(
+        mut self,
+        predicate: impl Fn(StatusCode) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.predicate = RetryPredicate(Some(Arc::new(predicate)));
+        self
+    }
+
+    pub fn max_attempts(&self) -> usize {
+        self.max_attempts
+    }
+
+    pub fn max_elapsed(&self) -> Option<Duration> {
+        self.max_elapsed
+    }
+
+    /// Whether to give up after `tries` failed attempts, retrying since `since`, rather
+    /// than wait `delay` and try again.
+    pub(crate) fn exhausted(&self, tries: usize, since: Instant, delay: Duration) -> bool {
+        tries >= self.max_attempts
+            || self
+                .max_elapsed
+                .is_some_and(|budget| since.elapsed() + delay > budget)
+    }
+
+    /// Whether a response with `status` should be retried.
+    pub fn is_retriable(&self, status: StatusCode) -> bool {
+        match &self.predicate.0 {
+            Some(predicate) => predicate(status),
+            None => self.statuses.contains(&status.as_u16()),
+        }
+    }
+
+    /// Delay to wait before retry number `retry` (1-based), without jitter.
+    pub fn delay(&self, retry: usize) -> Duration {
+        let exp = retry.saturating_sub(1).min(i32::MAX as usize) as i32;
+        let secs = self.base_delay.as_secs_f64() * self.factor.powi(exp);
+        Duration::try_from_secs_f64(secs)
+            .unwrap_or(self.max_delay)
+            .min(self.max_delay)
+    }
+
+    /// [`RetryPolicy::delay`] with jitter applied.
+    pub(crate) fn jittered_delay(&self, retry: usize) -> Duration {
+        let delay = self.delay(retry);
+        if self.jitter == 0.0 {
+            return delay;
+        }
+        let spread = (fastrand::f64() * 2.0 - 1.0) * self.jitter;
+        delay.mul_f64(1.0 + spread)
+    }
+
+    /// Keeps the constant-delay behavior of `with_sleep_max`.
+    pub(crate) fn set_sleep(&mut self, sleep: Duration) {
+        self.base_delay = sleep;
+        self.max_delay = sleep;
+    }
+}
+
+/// Start of the current run of download failures, for [`RetryPolicy::exhausted`]: the
+/// clock restarts whenever the download got further than at the previous failure.
+#[derive(Debug)]
+pub(crate) struct Stall {
+    position: u64,
+    since: Instant,
+}
+
+impl Stall {
+    pub(crate) fn new(position: u64) -> Self {
+        Self {
+            position,
+            since: Instant::now(),
+        }
+    }
+
+    /// When the failures at `position` started.
+    pub(crate) fn since(&mut self, position: u64) -> Instant {
+        if position > self.position {
+            self.position = position;
+            self.since = Instant::now();
+        }
+        self.since
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn default_policy_gives_up_after_time_budget() {
+        let policy = RetryPolicy::default();
+        let delay = policy.delay(1);
+        assert!(!policy.exhausted(1, Instant::now(), delay));
+        let started = Instant::now() - Duration::from_secs(29 * 60);
+        assert!(policy.exhausted(1, started, delay));
+        assert!(policy.exhausted(500, Instant::now(), delay));
+    }
+}
@@ -0,0 +1,499 @@
+use std::cmp::Reverse;
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Condvar, Mutex, MutexGuard};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use crate::batch::{BatchItem, BatchResult};
+use crate::client::{Client, RemoteFile};
+use crate::error::{CdsError, Result};
+use crate::job::JobStatus;
+
+/// Where a [`Scheduler`] entry is in its lifecycle.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum EntryState {
+    /// Waiting for a worker and a free queue slot, or for its retry delay to pass.
+    Pending,
+    /// Being submitted (`job_id` is `None`), or queued/running on the server.
+    Running {
+        job_id: Option<String>,
+        status: Option<JobStatus>,
+    },
+    /// The job finished and its result is being downloaded.
+    Downloading { job_id: String },
+    /// Downloaded to the entry's target.
+    Done,
+    /// Failed for good, after all attempts or because an entry it runs after failed.
+    Failed { message: String },
+}
+
+impl EntryState {
+    /// Returns `true` for [`EntryState::Done`] and [`EntryState::Failed`].
+    pub fn is_finished(&self) -> bool {
+        matches!(self, Self::Done | Self::Failed { .. })
+    }
+}
+
+/// Ordering of an entry added with [`Scheduler::enqueue_with`].
+///
+/// Entries start in priority order, highest first, and in enqueue order among equal
+/// priorities; an entry waits until every entry it runs [`after`](EnqueueOptions::after)
+/// is done.
+#[derive(Debug, Clone, Default)]
+pub struct EnqueueOptions {
+    priority: i32,
+    after: Vec<usize>,
+}
+
+impl EnqueueOptions {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Priority of the entry (default 0). Higher values start first, e.g. the date of the
+    /// data for a newest-first backfill.
+    pub fn priority(mut self, priority: i32) -> Self {
+        self.priority = priority;
+        self
+    }
+
+    /// Starts the entry only once the entry at `index` is done. If that entry fails, this
+    /// one fails too, with [`CdsError::Cancelled`].
+    pub fn after(mut self, index: usize) -> Self {
+        self.after.push(index);
+        self
+    }
+}
+
+/// One entry of a [`SchedulerSnapshot`].
+#[derive(Debug, Clone)]
+pub struct EntrySnapshot {
+    /// Value returned by [`Scheduler::enqueue`].
+    pub index: usize,
+    pub item: BatchItem,
+    pub state: EntryState,
+    /// See [`EnqueueOptions::priority`].
+    pub priority: i32,
+    /// Entries that must be done before this one starts.
+    pub after: Vec<usize>,
+    /// Attempts started so far.
+    pub attempts: usize,
+    /// Why the previous attempt failed, if it did.
+    pub last_error: Option<String>,
+}
+
+/// Progress of a [`Scheduler`] at one point in time.
+#[derive(Debug, Clone)]
+pub struct SchedulerSnapshot {
+    /// All entries, in enqueue order.
+    pub entries: Vec<EntrySnapshot>,
+}
+
+impl SchedulerSnapshot {
+    /// Entries waiting to start (or to be retried).
+    pub fn pending(&self) -> usize {
+        self.count(|s| matches!(s, EntryState::Pending))
+    }
+
+    /// Entries submitted, running or downloading.
+    pub fn active(&self) -> usize {
+        self.count(|s| {
+            matches!(
+                s,
+                EntryState::Running { .. } | EntryState::Downloading { .. }
+            )
+        })
+    }
+
+    pub fn done(&self) -> usize {
+        self.count(|s| matches!(s, EntryState::Done))
+    }
+
+    pub fn failed(&self) -> usize {
+        self.count(|s| matches!(s, EntryState::Failed { .. }))
+    }
+
+    /// Returns `true` once every entry is done or failed.
+    pub fn is_finished(&self) -> bool {
+        self.entries.iter().all(|e| e.state.is_finished())
+    }
+
+    fn count(&self, f: impl Fn(&EntryState) -> bool) -> usize {
+        self.entries.iter().filter(|e| f(&e.state)).count()
+    }
+}
+
+/// Runs many retrievals in the background on a pool of worker threads.
+///
+/// Unlike [`Client::retrieve_many`], entries can be added while the pool runs, at most
+/// [`max_queued`](Scheduler::with_max_queued) jobs are submitted and unfinished at a time
+/// (so the CDS per-user queue limit is respected), failed entries are resubmitted, and
+/// progress can be inspected at any time with [`Scheduler::snapshot`].
+///
+/// ```no_run
+/// use cdsapi::{BatchItem, Client, Scheduler};
+/// use serde_json::json;
+///
+/// # fn main() -> cdsapi::Result<()> {
+/// let client = Client::from_env()?;
+/// let max_queued = client.profile()?.max_queued.unwrap_or(4) as usize;
+/// let scheduler = Scheduler::new(client, 8).with_max_queued(max_queued);
+/// for month in ["01", "02", "03"] {
+///     let request = json!({"variable": ["2m_temperature"], "year": ["2024"], "month": [month]});
+///     let target = format!("t2m_2024{}.grib", month);
+///     scheduler.enqueue(BatchItem::new("reanalysis-era5-single-levels", &request, target)?);
+/// }
+/// for result in scheduler.join() {
+///     if let Err(e) = result.result {
+///         eprintln!("{}: {}", result.item.target.display(), e);
+///     }
+/// }
+/// # Ok(())
+/// # }
+/// ```
+///
+/// Dropping the scheduler without calling [`Scheduler::join`] does not stop it: the
+/// workers finish the queued entries in the background.
+#[derive(Debug)]
+pub struct Scheduler {
+    shared: Arc<Shared>,
+}
+
+#[derive(Debug)]
+struct Shared {
+    client: Client,
+    state: Mutex<State>,
+    changed: Condvar,
+}
+
+#[derive(Debug)]
+struct State {
+    max_queued: usize,
+    max_attempts: usize,
+    retry_delay: Duration,
+    /// Entries to start, with the earliest time they may start.
+    queue: VecDeque<(usize, Instant)>,
+    entries: Vec<EntrySnapshot>,
+    results: Vec<Option<Result<RemoteFile>>>,
+    /// Queue slots taken by entries between submission and job completion.
+    slots: usize,
+    closed: bool,
+}
+
+impl Scheduler {
+    /// Starts `workers` threads retrieving entries with `client`.
+    ///
+    /// By default at most `workers` jobs are queued at once, and each entry is attempted
+    /// up to 3 times, 60s apart.
+    pub fn new(client: Client, workers: usize) -> Self {
+        let workers = workers.max(1);
+        let shared = Arc::new(Shared {
+            client,
+            state: Mutex::new(State {
+                max_queued: workers,
+                max_attempts: 3,
+                retry_delay: Duration::from_secs(60),
+                queue: VecDeque::new(),
+                entries: Vec::new(),
+                results: Vec::new(),
+                slots: 0,
+                closed: false,
+            }),
+            changed: Condvar::new(),
+        });
+        for _ in 0..workers {
+            let shared = Arc::clone(&shared);
+            thread::spawn(move || shared.work());
+        }
+        Self { shared }
+    }
+
+    /// Limits how many jobs may be submitted and not yet finished at once, e.g. to the
+    /// account's [`Profile::max_queued`](crate::Profile::max_queued). Downloads of
+    /// finished jobs do not count.
+    pub fn with_max_queued(self, max_queued: usize) -> Self {
+        self.shared.lock().max_queued = max_queued.max(1);
+        self
+    }
+
+    /// How many times an entry is attempted before it is marked failed (default 3).
+    ///
+    /// Only failures that may go away are retried: failed jobs, quota errors, server
+    /// errors, and interrupted or corrupt downloads.
+    pub fn with_max_attempts(self, attempts: usize) -> Self {
+        self.shared.lock().max_attempts = attempts.max(1);
+        self
+    }
+
+    /// Delay before a failed entry is resubmitted (default 60s).
+    pub fn with_retry_delay(self, delay: Duration) -> Self {
+        self.shared.lock().retry_delay = delay;
+        self
+    }
+
+    /// Adds an entry with the default priority and returns its index in snapshots and
+    /// results.
+    pub fn enqueue(&self, item: BatchItem) -> usize {
+        self.push(item, EnqueueOptions::default())
+    }
+
+    /// Like [`Scheduler::enqueue`], with a priority and entries to run after.
+    ///
+    /// Fails if `options` refers to an entry that has not been enqueued yet.
+    pub fn enqueue_with(&self, item: BatchItem, options: &EnqueueOptions) -> Result<usize> {
+        let len = self.shared.lock().entries.len();
+        if let Some(index) = options.after.iter().find(|&&i| i >= len) {
+            return Err(CdsError::Config(format!(
+                "cannot run after entry {}: only {} entries are enqueued",
+                index, len
+            )));
+        }
+        Ok(self.push(item, options.clone()))
+    }
+
+    fn push(&self, item: BatchItem, options: EnqueueOptions) -> usize {
+        let mut state = self.shared.lock();
+        let index = state.entries.len();
+        state.entries.push(EntrySnapshot {
+            index,
+            item,
+            state: EntryState::Pending,
+            priority: options.priority,
+            after: options.after,
+            attempts: 0,
+            last_error: None,
+        });
+        state.results.push(None);
+        state.queue.push_back((index, Instant::now()));
+        state.cancel_orphans();
+        drop(state);
+        self.shared.changed.notify_all();
+        index
+    }
+
+    /// Current state of every entry.
+    pub fn snapshot(&self) -> SchedulerSnapshot {
+        SchedulerSnapshot {
+            entries: self.shared.lock().entries.clone(),
+        }
+    }
+
+    /// Waits for every entry to finish and returns their results in enqueue order.
+    pub fn join(self) -> Vec<BatchResult> {
+        let mut state = self.shared.lock();
+        while !state.entries.iter().all(|e| e.state.is_finished()) {
+            state = self.shared.wait(state);
+        }
+        let results = std::mem::take(&mut state.results);
+        let entries = std::mem::take(&mut state.entries);
+        entries
+            .into_iter()
+            .zip(results)
+            .map(|(entry, result)| BatchResult {
+                index: entry.index,
+                item: entry.item,
+                result: result.expect("finished entries have a result"),
+            })
+            .collect()
+    }
+}
+
+impl Drop for Scheduler {
+    fn drop(&mut self) {
+        self.shared.lock().closed = true;
+        self.shared.changed.notify_all();
+    }
+}
+
+impl Shared {
+    fn lock(&self) -> MutexGuard<'_, State> {
+        self.state.lock().unwrap()
+    }
+
+    fn wait<'a>(&self, state: MutexGuard<'a, State>) -> MutexGuard<'a, State> {
+        self.changed.wait(state).unwrap()
+    }
+
+    fn work(self: Arc<Self>) {
+        while let Some(index) = self.next_entry() {
+            let item = self.lock().entries[index].item.clone();
+            let slot = Arc::new(AtomicBool::new(true));
+            let result = self.attempt_client(index, &slot).retrieve(
+                &item.dataset,
+                &item.request,
+                Some(&item.target),
+            );
+            self.release_slot(&slot);
+            self.finish_attempt(index, result);
+        }
+    }
+
+    /// Blocks until an entry may start and takes a queue slot for it, or returns `None`
+    /// once the scheduler is dropped and the queue is empty.
+    fn next_entry(&self) -> Option<usize> {
+        let mut state = self.lock();
+        loop {
+            let now = Instant::now();
+            let ready = state
+                .queue
+                .iter()
+                .enumerate()
+                .filter(|&(_, &(index, at))| at <= now && state.dependencies_done(index))
+                .max_by_key(|&(pos, &(index, _))| (state.entries[index].priority, Reverse(pos)))
+                .map(|(pos, _)| pos);
+            if let Some(pos) = ready.filter(|_| state.slots < state.max_queued) {
+                let (index, _) = state.queue.remove(pos).unwrap();
+                state.slots += 1;
+                let entry = &mut state.entries[index];
+                entry.attempts += 1;
+                entry.state = EntryState::Running {
+                    job_id: None,
+                    status: None,
+                };
+                return Some(index);
+            }
+            if state.closed && state.queue.is_empty() {
+                return None;
+            }
+            let next_retry = state
+                .queue
+                .iter()
+                .map(|&(_, at)| at)
+                .filter(|&at| at > now)
+                .min();
+            state = match next_retry {
+                Some(at) => self.changed.wait_timeout(state, at - now).unwrap().0,
+                None => self.wait(state),
+            };
+        }
+    }
+
+    /// `client` with a state change callback that tracks the entry and frees its queue
+    /// slot once the job finishes, before the download.
+    fn attempt_client(self: &Arc<Self>, index: usize, slot: &Arc<AtomicBool>) -> Client {
+        let previous = self.client.on_state_change.0.clone();
+        let shared = Arc::downgrade(self);
+        let slot = Arc::clone(slot);
+        self.client
+            .clone()
+            .with_on_state_change(move |old, new, job| {
+                if let Some(shared) = shared.upgrade() {
+                    shared.lock().entries[index].state = match new {
+                        JobStatus::Successful => EntryState::Downloading {
+                            job_id: job.id().to_string(),
+                        },
+                        status => EntryState::Running {
+                            job_id: Some(job.id().to_string()),
+                            status: Some(status.clone()),
+                        },
+                    };
+                    if new.is_finished() {
+                        shared.release_slot(&slot);
+                    }
+                    shared.changed.notify_all();
+                }
+                if let Some(previous) = &previous {
+                    previous(old, new, job);
+                }
+            })
+    }
+
+    fn release_slot(&self, slot: &AtomicBool) {
+        if slot.swap(false, Ordering::SeqCst) {
+            self.lock().slots -= 1;
+            self.changed.notify_all();
+        }
+    }
+
+    fn finish_attempt(&self, index: usize, result: Result<RemoteFile>) {
+        let mut state = self.lock();
+        let retry_delay = state.retry_delay;
+        let retry = state.entries[index].attempts < state.max_attempts
+            && matches!(&result, Err(e) if worth_retrying(e));
+        let entry = &mut state.entries[index];
+        match result {
+            Err(e) if retry => {
+                self.client.log_info(format_args!(
+                    "{} failed (attempt {}): {}; retrying in {:?}",
+                    entry.item.target.display(),
+                    entry.attempts,
+                    e,
+                    retry_delay
+                ));
+                entry.state = EntryState::Pending;
+                entry.last_error = Some(e.to_string());
+                state.queue.push_back((index, Instant::now() + retry_delay));
+            }
+            result => {
+                entry.state = match &result {
+                    Ok(_) => EntryState::Done,
+                    Err(e) => EntryState::Failed {
+                        message: e.to_string(),
+                    },
+                };
+                if let Err(e) = &result {
+                    entry.last_error = Some(e.to_string());
+                }
+                state.results[index] = Some(result);
+            }
+        }
+        state.cancel_orphans();
+        drop(state);
+        self.changed.notify_all();
+    }
+}
+
+impl State {
+    fn dependencies_done(&self, index: usize) -> bool {
+        let entry = &self.entries[index];
+        entry
+            .after
+            .iter()
+            .all(|&dep| self.entries[dep].state == EntryState::Done)
+    }
+
+    /// Fails queued entries that depend on a failed entry, transitively.
+    ///
+    /// Called whenever an entry may have failed or been added, before waiters are
+    /// notified, so that [`Scheduler::join`] sees the cancelled entries finish.
+    fn cancel_orphans(&mut self) {
+        loop {
+            let orphan = self
+                .queue
+                .iter()
+                .enumerate()
+                .find_map(|(pos, &(index, _))| {
+                    let failed = self.entries[index].after.iter().find(|&&dep| {
+                        matches!(self.entries[dep].state, EntryState::Failed { .. })
+                    })?;
+                    Some((pos, index, *failed))
+                });
+            let Some((pos, index, failed)) = orphan else {
+                return;
+            };
+            self.queue.remove(pos);
+            let message = format!("entry {} it runs after failed", failed);
+            let entry = &mut self.entries[index];
+            entry.state = EntryState::Failed {
+                message: message.clone(),
+            };
+            entry.last_error = Some(message);
+            self.results[index] = Some(Err(CdsError::Cancelled { job_id: None }));
+        }
+    }
+}
+
+/// Whether a failed attempt may succeed when resubmitted.
+fn worth_retrying(e: &CdsError) -> bool {
+    match e {
+        CdsError::JobFailed { .. }
+        | CdsError::QuotaExceeded { .. }
+        | CdsError::Download { .. }
+        | CdsError::Integrity { .. }
+        | CdsError::Transport { .. } => true,
+        CdsError::Http { .. } => e.status().is_some_and(|s| s.is_server_error()),
+        _ => false,
+    }
+}
@@ -0,0 +1,122 @@
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::client::Client;
+use crate::error::Result;
+
+/// A dataset as a STAC collection from the catalogue, with its extents and links.
+///
+/// [`DatasetInfo`](crate::DatasetInfo) summarizes the same documents; this keeps the
+/// STAC structure for data-discovery tools.
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct Collection {
+    /// Dataset ID, as passed to [`Client::retrieve`].
+    pub id: String,
+    #[serde(default)]
+    pub title: Option<String>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// SPDX identifier of the licence, or `proprietary` / `other`.
+    #[serde(default)]
+    pub license: Option<String>,
+    #[serde(default)]
+    pub extent: Extent,
+    #[serde(default)]
+    pub links: Vec<StacLink>,
+    /// Any other fields returned by the server (e.g. `cads:` extensions).
+    #[serde(flatten)]
+    pub extra: BTreeMap<String, Value>,
+}
+
+/// Spatial and temporal coverage of a [`Collection`].
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct Extent {
+    #[serde(default)]
+    pub spatial: SpatialExtent,
+    #[serde(default)]
+    pub temporal: TemporalExtent,
+}
+
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct SpatialExtent {
+    /// Bounding boxes as `[west, south, east, north]` in degrees; the first one covers the
+    /// whole collection.
+    #[serde(default)]
+    pub bbox: Vec<Vec<f64>>,
+}
+
+#[derive(Debug, Clone, Default, serde::Deserialize)]
+pub struct TemporalExtent {
+    /// Intervals as `[start, end]` RFC 3339 timestamps; `None` is open-ended (e.g. a
+    /// dataset still being updated). The first one covers the whole collection.
+    #[serde(default)]
+    pub interval: Vec<[Option<String>; 2]>,
+}
+
+/// A link of a [`Collection`].
+#[derive(Debug, Clone, serde::Deserialize)]
+pub struct StacLink {
+    pub href: String,
+    /// Relation, e.g. `license`, `self` or `describedby`.
+    #[serde(default)]
+    pub rel: Option<String>,
+    /// Media type of the target.
+    #[serde(default, rename = "type")]
+    pub media_type: Option<String>,
+    #[serde(default)]
+    pub title: Option<String>,
+}
+
+impl Collection {
+    /// The bounding box of the whole collection, if published.
+    pub fn bbox(&self) -> Option<&[f64]> {
+        self.extent.spatial.bbox.first().map(Vec::as_slice)
+    }
+
+    /// Start and end of the whole collection, if published.
+    pub fn interval(&self) -> Option<&[Option<String>; 2]> {
+        self.extent.temporal.interval.first()
+    }
+
+    /// Links with relation `rel`.
+    pub fn links_with_rel<'a>(&'a self, rel: &'a str) -> impl Iterator<Item = &'a StacLink> {
+        self.links
+            .iter()
+            .filter(move |l| l.rel.as_deref() == Some(rel))
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct CollectionsPage {
+    #[serde(default)]
+    collections: Vec<Collection>,
+    #[serde(default)]
+    links: Vec<StacLink>,
+}
+
+impl Client {
+    /// Lists the STAC collections of the catalogue, following pagination.
+    pub fn collections(&self) -> Result<Vec<Collection>> {
+        let mut url = Some(format!("{}/collections", self.catalogue_base()));
+        let mut out = Vec::new();
+        while let Some(u) = url {
+            let page: CollectionsPage =
+                self.api_json::<Value, CollectionsPage>("GET", &u, &Value::Null)?;
+            out.extend(page.collections);
+            url = page
+                .links
+                .into_iter()
+                .find(|l| l.rel.as_deref() == Some("next"))
+                .map(|l| l.href);
+        }
+        Ok(out)
+    }
+
+    /// Fetches the STAC collection of the dataset `id`.
+    pub fn collection(&self, id: &str) -> Result<Collection> {
+        let url = format!("{}/collections/{}", self.catalogue_base(), id);
+        self.api_json::<Value, Collection>("GET", &url, &Value::Null)
+    }
+}
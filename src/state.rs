@@ -0,0 +1,165 @@
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+
+use crate::client::Client;
+use crate::error::{CdsError, Result};
+use crate::job::Job;
+
+/// A submitted job recorded in a state file (see [`Client::with_state_file`]).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StateEntry {
+    pub job_id: String,
+    pub dataset: String,
+    /// SHA-256 of the request as canonical JSON.
+    pub request_hash: String,
+    /// Download target, if the job was submitted by a `retrieve` call with a target.
+    #[serde(default)]
+    pub target: Option<PathBuf>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct StateFile {
+    #[serde(default)]
+    jobs: Vec<StateEntry>,
+}
+
+/// JSON file of jobs that were submitted but not downloaded yet.
+#[derive(Debug)]
+pub(crate) struct StateStore {
+    path: PathBuf,
+    lock: Mutex<()>,
+}
+
+impl StateStore {
+    pub(crate) fn new(path: PathBuf) -> Self {
+        Self {
+            path,
+            lock: Mutex::new(()),
+        }
+    }
+
+    pub(crate) fn entries(&self) -> Result<Vec<StateEntry>> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        Ok(self.load()?.jobs)
+    }
+
+    fn update(&self, f: impl FnOnce(&mut Vec<StateEntry>)) -> Result<()> {
+        let _guard = self.lock.lock().unwrap_or_else(|e| e.into_inner());
+        let mut state = self.load()?;
+        f(&mut state.jobs);
+        self.save(&state)
+    }
+
+    fn load(&self) -> Result<StateFile> {
+        match fs::read_to_string(&self.path) {
+            Ok(text) => serde_json::from_str(&text)
+                .map_err(|e| CdsError::json(format!("failed to parse {}", self.path.display()), e)),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(StateFile::default()),
+            Err(e) => Err(CdsError::io(
+                format!("failed to read {}", self.path.display()),
+                e,
+            )),
+        }
+    }
+
+    fn save(&self, state: &StateFile) -> Result<()> {
+        let text = serde_json::to_string_pretty(state)
+            .map_err(|e| CdsError::json("failed to serialize state", e))?;
+        // Write-then-rename so a crash never leaves a truncated state file behind.
+        let mut tmp = self.path.clone().into_os_string();
+        tmp.push(".tmp");
+        fs::write(&tmp, text)
+            .map_err(|e| CdsError::io(format!("failed to write {}", self.path.display()), e))?;
+        fs::rename(&tmp, &self.path)
+            .map_err(|e| CdsError::io(format!("failed to write {}", self.path.display()), e))
+    }
+}
+
+pub(crate) fn request_hash<T: Serialize>(request: &T) -> Result<String> {
+    // serde_json maps are sorted by key, so this is a canonical encoding.
+    let json = serde_json::to_value(request)
+        .map_err(|e| CdsError::json("failed to serialize request", e))?
+        .to_string();
+    Ok(Sha256::digest(json.as_bytes())
+        .iter()
+        .map(|b| format!("{:02x}", b))
+        .collect())
+}
+
+impl Client {
+    /// Records submitted jobs in the JSON file at `path` until their results are downloaded.
+    ///
+    /// A `retrieve` call whose dataset, request and target match a recorded job follows that
+    /// job instead of submitting again, and [`Client::resume`] picks up all recorded jobs
+    /// after a crash or reboot.
+    pub fn with_state_file(mut self, path: impl Into<PathBuf>) -> Self {
+        self.state = Some(Arc::new(StateStore::new(path.into())));
+        self
+    }
+
+    /// Waits for and downloads every job recorded in the state file at `state_path`.
+    ///
+    /// Returns the downloaded files. Jobs recorded without a target are only forgotten once
+    /// they finish.
+    pub fn resume(&self, state_path: &Path) -> Result<Vec<PathBuf>> {
+        let state = Arc::new(StateStore::new(state_path.to_path_buf()));
+        let mut client = self.clone();
+        client.state = Some(state.clone());
+
+        let mut downloaded = Vec::new();
+        for entry in state.entries()? {
+            client.log_info(format_args!("Resuming job {}", entry.job_id));
+            let job = client.job(&entry.job_id);
+            let file = job.wait()?;
+            if let Some(target) = &entry.target {
+                downloaded.push(client.download(&file, target)?);
+            }
+            client.forget_job(&entry.job_id)?;
+        }
+        Ok(downloaded)
+    }
+
+    /// Submits `request`, or reuses the job recorded for the same request and target.
+    pub(crate) fn submit_tracked<T: Serialize>(
+        &self,
+        dataset: &str,
+        request: &T,
+        target: Option<&Path>,
+    ) -> Result<Job> {
+        let Some(state) = &self.state else {
+            return self.submit(dataset, request);
+        };
+
+        let hash = request_hash(request)?;
+        let recorded = state.entries()?.into_iter().find(|e| {
+            e.dataset == dataset && e.request_hash == hash && e.target.as_deref() == target
+        });
+        if let Some(entry) = recorded {
+            self.log_info(format_args!("Reusing recorded job {}", entry.job_id));
+            return Ok(self.job(&entry.job_id));
+        }
+
+        let job = self.submit(dataset, request)?;
+        if !job.id().is_empty() {
+            let entry = StateEntry {
+                job_id: job.id().to_string(),
+                dataset: dataset.to_string(),
+                request_hash: hash,
+                target: target.map(Path::to_path_buf),
+            };
+            state.update(|jobs| jobs.push(entry))?;
+        }
+        Ok(job)
+    }
+
+    /// Removes a finished job from the state file, if one is configured.
+    pub(crate) fn forget_job(&self, job_id: &str) -> Result<()> {
+        match &self.state {
+            Some(state) => state.update(|jobs| jobs.retain(|e| e.job_id != job_id)),
+            None => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,168 @@
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::client::{Client, RemoteFile};
+use crate::error::Result;
+use crate::job::JobStatus;
+use crate::telemetry;
+
+/// A finished retrieval or download with transfer statistics, returned by
+/// [`Client::retrieve_with_stats`] and [`Client::download_with_stats`].
+#[derive(Debug, Clone)]
+pub struct RetrieveOutcome {
+    pub file: RemoteFile,
+    /// Where the result was written, if anywhere.
+    pub path: Option<PathBuf>,
+    /// Bytes received over the network, excluding data already present locally.
+    pub bytes_transferred: u64,
+    /// How often an interrupted download was resumed.
+    pub resumes: u32,
+    /// Time the job spent queued on the server.
+    pub queue_time: Duration,
+    /// Time the job spent running on the server.
+    pub processing_time: Duration,
+    /// Time spent downloading.
+    pub download_time: Duration,
+}
+
+/// Counters filled in while a call runs; attached to a per-call clone of the client.
+#[derive(Debug, Default)]
+pub(crate) struct StatsRecorder(Mutex<Stats>);
+
+#[derive(Debug, Default)]
+struct Stats {
+    bytes: u64,
+    resumes: u32,
+    queue_time: Duration,
+    processing_time: Duration,
+    download_time: Duration,
+}
+
+impl StatsRecorder {
+    fn with<R>(&self, f: impl FnOnce(&mut Stats) -> R) -> R {
+        f(&mut self.0.lock().unwrap_or_else(|e| e.into_inner()))
+    }
+
+    fn outcome(&self, file: RemoteFile, path: Option<PathBuf>) -> RetrieveOutcome {
+        self.with(|s| RetrieveOutcome {
+            file,
+            path,
+            bytes_transferred: s.bytes,
+            resumes: s.resumes,
+            queue_time: s.queue_time,
+            processing_time: s.processing_time,
+            download_time: s.download_time,
+        })
+    }
+}
+
+impl Client {
+    /// Like [`Client::retrieve`], also reporting bytes transferred, resumes and the time
+    /// spent queued, processing and downloading, e.g. for dashboards tracking CDS
+    /// performance.
+    ///
+    /// Queue and processing times are measured from status polls, so they are accurate to
+    /// the poll interval; time before the first poll counts as queued.
+    pub fn retrieve_with_stats<T: Serialize>(
+        &self,
+        dataset: &str,
+        request: &T,
+        target: Option<&Path>,
+    ) -> Result<RetrieveOutcome> {
+        let (client, stats) = self.recording();
+        let file = client.retrieve(dataset, request, target)?;
+        Ok(stats.outcome(file, target.map(Path::to_path_buf)))
+    }
+
+    /// Like [`Client::download`], also reporting bytes transferred, resumes and the
+    /// download time.
+    pub fn download_with_stats(&self, file: &RemoteFile, target: &Path) -> Result<RetrieveOutcome> {
+        let (client, stats) = self.recording();
+        let path = client.download(file, target)?;
+        Ok(stats.outcome(file.clone(), Some(path)))
+    }
+
+    fn recording(&self) -> (Client, Arc<StatsRecorder>) {
+        let stats = Arc::new(StatsRecorder::default());
+        let mut client = self.clone();
+        client.stats = Some(Arc::clone(&stats));
+        (client, stats)
+    }
+
+    pub(crate) fn record_bytes(&self, bytes: u64) {
+        self.metrics.counter(telemetry::BYTES_DOWNLOADED, bytes);
+        if let Some(stats) = &self.stats {
+            stats.with(|s| s.bytes += bytes);
+        }
+    }
+
+    pub(crate) fn record_resume(&self) {
+        self.metrics.counter(telemetry::DOWNLOAD_RESUMES, 1);
+        if let Some(stats) = &self.stats {
+            stats.with(|s| s.resumes += 1);
+        }
+    }
+
+    pub(crate) fn record_download_time(&self, elapsed: Duration) {
+        self.metrics
+            .histogram(telemetry::DOWNLOAD_SECONDS, elapsed.as_secs_f64());
+        if let Some(stats) = &self.stats {
+            stats.with(|s| s.download_time += elapsed);
+        }
+    }
+}
+
+/// Measures how long a job stays queued and running while [`Job::wait`](crate::Job::wait)
+/// polls it.
+#[derive(Debug)]
+pub(crate) struct StatusClock {
+    status: JobStatus,
+    since: Instant,
+}
+
+impl StatusClock {
+    /// Starts timing a job assumed to be queued.
+    pub(crate) fn start() -> Self {
+        Self {
+            status: JobStatus::Queued,
+            since: Instant::now(),
+        }
+    }
+
+    /// Time since the job entered its current state.
+    pub(crate) fn elapsed(&self) -> Duration {
+        self.since.elapsed()
+    }
+
+    /// Records a poll that saw `status`, closing the previous phase if it changed.
+    pub(crate) fn observe(&mut self, client: &Client, status: &JobStatus) {
+        client.metrics.counter(telemetry::POLL_CYCLES, 1);
+        if *status == self.status {
+            return;
+        }
+        let elapsed = self.since.elapsed();
+        match self.status {
+            JobStatus::Queued => {
+                client
+                    .metrics
+                    .histogram(telemetry::QUEUE_SECONDS, elapsed.as_secs_f64());
+                if let Some(stats) = &client.stats {
+                    stats.with(|s| s.queue_time += elapsed);
+                }
+            }
+            JobStatus::Running => {
+                client
+                    .metrics
+                    .histogram(telemetry::PROCESSING_SECONDS, elapsed.as_secs_f64());
+                if let Some(stats) = &client.stats {
+                    stats.with(|s| s.processing_time += elapsed);
+                }
+            }
+            _ => {}
+        }
+        self.status = status.clone();
+        self.since = Instant::now();
+    }
+}
@@ -0,0 +1,129 @@
+use serde_json::Value;
+use std::collections::BTreeMap;
+use std::fmt;
+
+use crate::client::Client;
+use crate::error::Result;
+use crate::util::split_key_basic;
+
+/// Severity of a [`ServiceMessage`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MessageSeverity {
+    Info,
+    Success,
+    Warning,
+    Critical,
+    /// Any severity this crate does not know about.
+    Other(String),
+}
+
+impl MessageSeverity {
+    fn from_api(severity: &str) -> Self {
+        match severity {
+            "info" => Self::Info,
+            "success" => Self::Success,
+            "warning" => Self::Warning,
+            "critical" | "error" => Self::Critical,
+            other => Self::Other(other.to_string()),
+        }
+    }
+}
+
+impl fmt::Display for MessageSeverity {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Info => f.write_str("info"),
+            Self::Success => f.write_str("success"),
+            Self::Warning => f.write_str("warning"),
+            Self::Critical => f.write_str("critical"),
+            Self::Other(s) => f.write_str(s),
+        }
+    }
+}
+
+/// A service announcement (planned maintenance, degraded datasets, ...).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ServiceMessage {
+    pub severity: MessageSeverity,
+    pub content: String,
+    /// Publication date, as sent by the server.
+    pub date: Option<String>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct CatalogueMessages {
+    #[serde(default)]
+    messages: Vec<CatalogueMessage>,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct CatalogueMessage {
+    #[serde(default)]
+    severity: Option<String>,
+    #[serde(default)]
+    content: String,
+    #[serde(default)]
+    date: Option<String>,
+    #[serde(default = "default_live")]
+    live: bool,
+}
+
+fn default_live() -> bool {
+    true
+}
+
+impl Client {
+    /// Fetches the current service status messages.
+    ///
+    /// Uses `status.json` for legacy keys and the catalogue `messages` endpoint otherwise.
+    pub fn status(&self) -> Result<Vec<ServiceMessage>> {
+        if split_key_basic(&self.key).is_some() {
+            // {"info": ["..."], "warning": ["..."], ...}
+            let url = format!("{}/status.json", self.url.trim_end_matches('/'));
+            let status: BTreeMap<String, Vec<String>> =
+                self.api_json::<Value, BTreeMap<String, Vec<String>>>("GET", &url, &Value::Null)?;
+            return Ok(status
+                .into_iter()
+                .flat_map(|(severity, messages)| {
+                    let severity = MessageSeverity::from_api(&severity);
+                    messages.into_iter().map(move |content| ServiceMessage {
+                        severity: severity.clone(),
+                        content,
+                        date: None,
+                    })
+                })
+                .collect());
+        }
+
+        let url = format!("{}/messages", self.catalogue_base());
+        let resp: CatalogueMessages =
+            self.api_json::<Value, CatalogueMessages>("GET", &url, &Value::Null)?;
+        Ok(resp
+            .messages
+            .into_iter()
+            .filter(|m| m.live)
+            .map(|m| ServiceMessage {
+                severity: MessageSeverity::from_api(m.severity.as_deref().unwrap_or("info")),
+                content: m.content,
+                date: m.date,
+            })
+            .collect())
+    }
+
+    /// Prints warning and critical status messages; failures to fetch them are ignored.
+    pub(crate) fn report_status(&self) {
+        match self.status() {
+            Ok(messages) => {
+                for m in messages {
+                    if matches!(
+                        m.severity,
+                        MessageSeverity::Warning | MessageSeverity::Critical
+                    ) {
+                        self.log_warning(format_args!("{}: {}", m.severity, m.content));
+                    }
+                }
+            }
+            Err(e) => self.log_debug(format_args!("could not fetch service status: {}", e)),
+        }
+    }
+}
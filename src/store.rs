@@ -0,0 +1,138 @@
+use serde::Serialize;
+use std::fmt;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::client::{Client, RemoteFile};
+use crate::error::Result;
+
+/// Copernicus data stores sharing the CDS API.
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
+pub enum Store {
+    /// Climate Data Store.
+    Cds,
+    /// Atmosphere Data Store (CAMS).
+    Ads,
+    /// Early Warning Data Store (CEMS).
+    Ewds,
+    /// Any other deployment, by base API URL.
+    Custom(String),
+}
+
+impl Store {
+    /// Base API URL of the store.
+    pub fn url(&self) -> &str {
+        match self {
+            Self::Cds => "https://cds.climate.copernicus.eu/api",
+            Self::Ads => "https://ads.atmosphere.copernicus.eu/api",
+            Self::Ewds => "https://ewds.climate.copernicus.eu/api",
+            Self::Custom(url) => url,
+        }
+    }
+}
+
+impl fmt::Display for Store {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cds => f.write_str("cds"),
+            Self::Ads => f.write_str("ads"),
+            Self::Ewds => f.write_str("ewds"),
+            Self::Custom(url) => f.write_str(url),
+        }
+    }
+}
+
+/// Maps dataset IDs to the store that serves them, by ID prefix.
+///
+/// The default table sends `cams-*` to ADS and `cems-*` / `efas-*` to EWDS; everything else
+/// stays on the client's configured URL. Rules added with [`StoreRouter::route`] take
+/// precedence, and the longest matching prefix wins.
+#[derive(Debug, Clone)]
+pub struct StoreRouter {
+    rules: Vec<(String, Store)>,
+}
+
+impl Default for StoreRouter {
+    fn default() -> Self {
+        Self {
+            rules: vec![
+                ("cams-".to_string(), Store::Ads),
+                ("cems-".to_string(), Store::Ewds),
+                ("efas-".to_string(), Store::Ewds),
+            ],
+        }
+    }
+}
+
+impl StoreRouter {
+    /// The built-in routing table.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// A table without built-in rules.
+    pub fn empty() -> Self {
+        Self { rules: Vec::new() }
+    }
+
+    /// Sends datasets whose ID starts with `prefix` to `store` (an exact ID works too).
+    pub fn route(mut self, prefix: impl Into<String>, store: Store) -> Self {
+        let prefix = prefix.into();
+        self.rules.retain(|(p, _)| *p != prefix);
+        self.rules.push((prefix, store));
+        self
+    }
+
+    /// Store serving `dataset`, if a rule matches.
+    pub fn store_for(&self, dataset: &str) -> Option<&Store> {
+        self.rules
+            .iter()
+            .filter(|(prefix, _)| dataset.starts_with(prefix.as_str()))
+            .max_by_key(|(prefix, _)| prefix.len())
+            .map(|(_, store)| store)
+    }
+}
+
+impl Client {
+    /// Dispatches [`Client::retrieve`] and [`Client::submit`] to the store serving each
+    /// dataset, according to `router`.
+    pub fn with_store_routing(mut self, router: StoreRouter) -> Self {
+        self.router = Some(Arc::new(router));
+        self
+    }
+
+    /// A copy of the client pointed at the store `router` assigns to `dataset`, when that
+    /// differs from the configured URL.
+    pub(crate) fn routed(&self, dataset: &str) -> Option<Client> {
+        let store = self.router.as_ref()?.store_for(dataset)?;
+        if self.url.trim_end_matches('/') == store.url().trim_end_matches('/') {
+            return None;
+        }
+        self.log_debug(format_args!("routing {} to {}", dataset, store));
+        Some(self.clone().with_store(store.clone()))
+    }
+
+    /// Creates a client for `store`, taking the key from the usual configuration sources.
+    pub fn for_store(store: Store) -> Result<Self> {
+        Self::new(Some(store.url().to_string()), None, None)
+    }
+
+    /// Points the client at `store`, keeping the key and all other settings.
+    pub fn with_store(mut self, store: Store) -> Self {
+        self.url = store.url().to_string();
+        self
+    }
+
+    /// Like [`Client::retrieve`], against `store` instead of the configured URL.
+    pub fn retrieve_from<T: Serialize>(
+        &self,
+        store: Store,
+        dataset: &str,
+        request: &T,
+        target: Option<&Path>,
+    ) -> Result<RemoteFile> {
+        self.clone()
+            .with_store(store)
+            .retrieve(dataset, request, target)
+    }
+}
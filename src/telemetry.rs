@@ -0,0 +1,86 @@
+use std::fmt;
+use std::sync::Arc;
+
+use crate::client::Client;
+
+/// Receives counters and histograms from a [`Client`], e.g. to export them to Prometheus.
+///
+/// Register an implementation with [`Client::with_metrics`]. With the `metrics` feature,
+/// [`MetricsFacade`] forwards everything to the [`metrics`](https://docs.rs/metrics) crate.
+///
+/// Counters:
+/// - `cdsapi_jobs_submitted_total`
+/// - `cdsapi_http_retries_total`: API requests retried after an error or retriable status
+/// - `cdsapi_poll_cycles_total`: job status polls
+/// - `cdsapi_download_resumes_total`: interrupted downloads resumed
+/// - `cdsapi_bytes_downloaded_total`
+///
+/// Histograms, in seconds:
+/// - `cdsapi_queue_seconds`: time a job spent queued
+/// - `cdsapi_processing_seconds`: time a job spent running
+/// - `cdsapi_download_seconds`: duration of each download
+pub trait Metrics: Send + Sync {
+    fn increment_counter(&self, name: &'static str, value: u64);
+    fn record_histogram(&self, name: &'static str, value: f64);
+}
+
+pub(crate) const JOBS_SUBMITTED: &str = "cdsapi_jobs_submitted_total";
+pub(crate) const HTTP_RETRIES: &str = "cdsapi_http_retries_total";
+pub(crate) const POLL_CYCLES: &str = "cdsapi_poll_cycles_total";
+pub(crate) const DOWNLOAD_RESUMES: &str = "cdsapi_download_resumes_total";
+pub(crate) const BYTES_DOWNLOADED: &str = "cdsapi_bytes_downloaded_total";
+pub(crate) const QUEUE_SECONDS: &str = "cdsapi_queue_seconds";
+pub(crate) const PROCESSING_SECONDS: &str = "cdsapi_processing_seconds";
+pub(crate) const DOWNLOAD_SECONDS: &str = "cdsapi_download_seconds";
+
+/// Forwards to the global recorder of the `metrics` crate.
+#[cfg(feature = "metrics")]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MetricsFacade;
+
+#[cfg(feature = "metrics")]
+impl Metrics for MetricsFacade {
+    fn increment_counter(&self, name: &'static str, value: u64) {
+        ::metrics::counter!(name).increment(value);
+    }
+
+    fn record_histogram(&self, name: &'static str, value: f64) {
+        ::metrics::histogram!(name).record(value);
+    }
+}
+
+#[derive(Clone, Default)]
+pub(crate) struct MetricsSink(Option<Arc<dyn Metrics>>);
+
+impl MetricsSink {
+    pub(crate) fn counter(&self, name: &'static str, value: u64) {
+        if let Some(metrics) = &self.0 {
+            metrics.increment_counter(name, value);
+        }
+    }
+
+    pub(crate) fn histogram(&self, name: &'static str, value: f64) {
+        if let Some(metrics) = &self.0 {
+            metrics.record_histogram(name, value);
+        }
+    }
+}
+
+impl fmt::Debug for MetricsSink {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(if self.0.is_some() {
+            "metrics"
+        } else {
+            "no metrics"
+        })
+    }
+}
+
+impl Client {
+    /// Reports counters and histograms (retries, bytes, polls, queue time, ...) to
+    /// `metrics`. See [`Metrics`] for the names.
+    pub fn with_metrics(mut self, metrics: impl Metrics + 'static) -> Self {
+        self.metrics = MetricsSink(Some(Arc::new(metrics)));
+        self
+    }
+}
@@ -0,0 +1,519 @@
+//! In-process mock of the CDS Retrieve API for tests (cargo feature `test-util`).
+//!
+//! [`MockServer`] listens on a local port and serves job submission, status polling,
+//! results and downloads (with `Range` support), so workflows can be tested without the
+//! real API. The legacy `/resources` + `/tasks` API is served under `/api/v2` only, so
+//! [`MockServer::legacy_client`] also exercises the fallback from `/api`:
+//!
+//! ```no_run
+//! use cdsapi::test_util::MockServer;
+//!
+//! let server = MockServer::start()
+//!     .with_states(&["accepted", "running", "successful"])
+//!     .with_result(b"GRIB...".to_vec())
+//!     .with_interrupted_downloads(1, 4);
+//! let client = server.client()?;
+//! let target = std::path::Path::new("out.grib");
+//! client.retrieve("reanalysis-era5-single-levels", &serde_json::json!({}), Some(target))?;
+//! assert_eq!(std::fs::read("out.grib")?, b"GRIB...");
+//! # Ok::<(), cdsapi::CdsError>(())
+//! ```
+
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::io::{BufRead, BufReader, Read, Write};
+use std::net::{SocketAddr, TcpListener, TcpStream};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+#[cfg(feature = "async")]
+use crate::async_client::AsyncClient;
+use crate::client::{Client, ClientConfig};
+use crate::error::Result;
+use crate::retry::RetryPolicy;
+
+/// A request received by a [`MockServer`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MockRequest {
+    pub method: String,
+    /// Path and query, e.g. `/api/retrieve/v1/jobs/job-1?log=true`.
+    pub path: String,
+    /// Headers, with lowercase names.
+    pub headers: Vec<(String, String)>,
+    pub body: String,
+}
+
+impl MockRequest {
+    /// Value of the header `name` (lowercase), if present.
+    pub fn header(&self, name: &str) -> Option<&str> {
+        self.headers
+            .iter()
+            .find(|(n, _)| n == name)
+            .map(|(_, v)| v.as_str())
+    }
+}
+
+#[derive(Debug)]
+struct MockState {
+    states: Vec<String>,
+    log: Vec<String>,
+    result: Vec<u8>,
+    content_type: String,
+    submit_error: Option<(u16, String)>,
+    /// Remaining downloads to cut off, and after how many bytes.
+    interrupt: (usize, usize),
+    ignore_ranges: bool,
+    /// Number of status polls per job.
+    polls: HashMap<String, usize>,
+    next_job: usize,
+    requests: Vec<MockRequest>,
+}
+
+/// Local mock of the CDS Retrieve API; stops when dropped.
+///
+/// Jobs go through the configured [`states`](MockServer::with_states) on successive status
+/// polls (by default they are `successful` right away) and their result is the configured
+/// [`bytes`](MockServer::with_result). Use [`MockServer::client`] to get a [`Client`] talking
+/// to it.
+#[derive(Debug)]
+pub struct MockServer {
+    addr: SocketAddr,
+    state: Arc<Mutex<MockState>>,
+    shutdown: Arc<AtomicBool>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl MockServer {
+    /// Starts the server on a free local port.
+    pub fn start() -> Self {
+        let listener = TcpListener::bind("127.0.0.1:0").expect("failed to bind mock server");
+        let addr = listener.local_addr().expect("mock server address");
+        let state = Arc::new(Mutex::new(MockState {
+            states: vec!["successful".to_string()],
+            log: Vec::new(),
+            result: b"mock result".to_vec(),
+            content_type: "application/x-grib".to_string(),
+            submit_error: None,
+            interrupt: (0, 0),
+            ignore_ranges: false,
+            polls: HashMap::new(),
+            next_job: 0,
+            requests: Vec::new(),
+        }));
+        let shutdown = Arc::new(AtomicBool::new(false));
+
+        let thread = {
+            let state = Arc::clone(&state);
+            let shutdown = Arc::clone(&shutdown);
+            thread::spawn(move || {
+                for stream in listener.incoming() {
+                    if shutdown.load(Ordering::SeqCst) {
+                        break;
+                    }
+                    let Ok(stream) = stream else { continue };
+                    let state = Arc::clone(&state);
+                    thread::spawn(move || {
+                        let _ = handle(stream, addr, &state);
+                    });
+                }
+            })
+        };
+        Self {
+            addr,
+            state,
+            shutdown,
+            thread: Some(thread),
+        }
+    }
+
+    /// Sets the status sequence reported by successive polls of each job, e.g.
+    /// `["accepted", "running", "successful"]`; the last one repeats.
+    pub fn with_states(self, states: &[&str]) -> Self {
+        self.lock().states = states.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    /// Sets the server log of every job; each status poll reveals one more line.
+    pub fn with_log(self, lines: &[&str]) -> Self {
+        self.lock().log = lines.iter().map(|s| s.to_string()).collect();
+        self
+    }
+
+    /// Sets the result file content of every job.
+    pub fn with_result(self, bytes: Vec<u8>) -> Self {
+        self.lock().result = bytes;
+        self
+    }
+
+    /// Sets the content type reported for results (default `application/x-grib`).
+    pub fn with_content_type(self, content_type: &str) -> Self {
+        self.lock().content_type = content_type.to_string();
+        self
+    }
+
+    /// Makes job submission fail with HTTP `status` and `body`.
+    pub fn with_submit_error(self, status: u16, body: &str) -> Self {
+        self.lock().submit_error = Some((status, body.to_string()));
+        self
+    }
+
+    /// Cuts off the next `count` download responses after `after` bytes, to exercise resume.
+    pub fn with_interrupted_downloads(self, count: usize, after: usize) -> Self {
+        self.lock().interrupt = (count, after);
+        self
+    }
+
+    /// Makes downloads ignore `Range` headers and always send the whole file with status
+    /// 200, like some proxies do.
+    pub fn with_ignored_ranges(self) -> Self {
+        self.lock().ignore_ranges = true;
+        self
+    }
+
+    /// Base API URL, e.g. `http://127.0.0.1:4711/api`.
+    pub fn url(&self) -> String {
+        format!("http://{}/api", self.addr)
+    }
+
+    /// A quiet client for this server with a token key, short poll intervals and fast
+    /// retries.
+    pub fn client(&self) -> Result<Client> {
+        self.client_with_key("mock-token")
+    }
+
+    /// Like [`MockServer::client`], with a `UID:KEY` key, so the client uses the legacy API.
+    pub fn legacy_client(&self) -> Result<Client> {
+        self.client_with_key("12345:mock-key")
+    }
+
+    /// An [`AsyncClient`] for this server, set up like [`MockServer::client`].
+    #[cfg(feature = "async")]
+    pub fn async_client(&self) -> Result<AsyncClient> {
+        let client = AsyncClient::new(Some(self.url()), Some("mock-token".to_string()), None)?;
+        Ok(client
+            .with_quiet(true)
+            .with_progress(false)
+            .with_fixed_poll_interval(Duration::from_millis(10))
+            .with_retry_policy(fast_retries())
+            .with_download_retry_policy(fast_retries()))
+    }
+
+    fn client_with_key(&self, key: &str) -> Result<Client> {
+        let client = Client::from_config(ClientConfig {
+            url: self.url(),
+            key: key.to_string(),
+            verify: true,
+            quiet: true,
+            debug: false,
+            ca_bundle: None,
+            client_cert: None,
+            client_key: None,
+            proxy: None,
+        })?;
+        Ok(client
+            .with_progress(false)
+            .with_fixed_poll_interval(Duration::from_millis(10))
+            .with_retry_policy(fast_retries())
+            .with_download_retry_policy(fast_retries()))
+    }
+
+    /// Requests received so far, in order.
+    pub fn requests(&self) -> Vec<MockRequest> {
+        self.lock().requests.clone()
+    }
+
+    fn lock(&self) -> std::sync::MutexGuard<'_, MockState> {
+        self.state.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.shutdown.store(true, Ordering::SeqCst);
+        // Wake up the accept loop so it sees the flag.
+        let _ = TcpStream::connect(self.addr);
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+/// Three attempts, 10ms apart.
+fn fast_retries() -> RetryPolicy {
+    RetryPolicy::new()
+        .with_max_attempts(3)
+        .with_base_delay(Duration::from_millis(10))
+        .with_max_delay(Duration::from_millis(10))
+}
+
+struct Reply {
+    status: u16,
+    headers: Vec<(&'static str, String)>,
+    body: Vec<u8>,
+    /// Bytes of `body` to send before dropping the connection.
+    truncate: Option<usize>,
+}
+
+impl Reply {
+    fn json(status: u16, body: Value) -> Self {
+        Self {
+            status,
+            headers: vec![("Content-Type", "application/json".to_string())],
+            body: body.to_string().into_bytes(),
+            truncate: None,
+        }
+    }
+}
+
+fn handle(stream: TcpStream, addr: SocketAddr, state: &Mutex<MockState>) -> std::io::Result<()> {
+    let mut reader = BufReader::new(stream.try_clone()?);
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    let mut parts = line.split_whitespace();
+    let (Some(method), Some(path)) = (parts.next(), parts.next()) else {
+        return Ok(());
+    };
+    let (method, path) = (method.to_string(), path.to_string());
+
+    let mut headers = Vec::new();
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 || line.trim().is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+        }
+    }
+    let request = MockRequest {
+        method,
+        path,
+        headers,
+        body: String::new(),
+    };
+    let length = request
+        .header("content-length")
+        .and_then(|v| v.parse::<usize>().ok())
+        .unwrap_or(0);
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    let request = MockRequest {
+        body: String::from_utf8_lossy(&body).into_owned(),
+        ..request
+    };
+
+    let reply = {
+        let mut state = state.lock().unwrap_or_else(|e| e.into_inner());
+        state.requests.push(request.clone());
+        route(&request, &format!("http://{}", addr), &mut state)
+    };
+    write_reply(stream, &request.method, reply)
+}
+
+fn route(request: &MockRequest, origin: &str, state: &mut MockState) -> Reply {
+    let path = request.path.split('?').next().unwrap_or("");
+    let jobs = format!("{}/api/retrieve/v1/jobs", origin);
+    let segments: Vec<&str> = path.trim_matches('/').split('/').collect();
+    match (request.method.as_str(), segments.as_slice()) {
+        ("POST", ["api", "retrieve", "v1", "processes", _, "execution"]) => {
+            if let Some((status, body)) = &state.submit_error {
+                return Reply {
+                    status: *status,
+                    headers: vec![("Content-Type", "application/json".to_string())],
+                    body: body.clone().into_bytes(),
+                    truncate: None,
+                };
+            }
+            state.next_job += 1;
+            let id = format!("job-{}", state.next_job);
+            state.polls.insert(id.clone(), 0);
+            Reply::json(
+                201,
+                json!({
+                    "jobID": id,
+                    "status": "accepted",
+                    "links": [{"rel": "monitor", "href": format!("{}/{}", jobs, id)}],
+                }),
+            )
+        }
+        ("GET", ["api", "retrieve", "v1", "jobs", id]) => {
+            let Some(polls) = state.polls.get_mut(*id) else {
+                return not_found();
+            };
+            let status = state
+                .states
+                .get((*polls).min(state.states.len().saturating_sub(1)))
+                .cloned()
+                .unwrap_or_else(|| "successful".to_string());
+            *polls += 1;
+            let log: Vec<_> = state
+                .log
+                .iter()
+                .take(*polls)
+                .map(|line| json!(["2024-01-01T00:00:00", line]))
+                .collect();
+            let mut links = vec![json!({"rel": "self", "href": format!("{}/{}", jobs, id)})];
+            if status == "successful" {
+                links.push(json!({"rel": "results", "href": format!("{}/{}/results", jobs, id)}));
+            }
+            Reply::json(
+                200,
+                json!({"jobID": id, "status": status, "links": links, "metadata": {"log": log}}),
+            )
+        }
+        ("GET", ["api", "retrieve", "v1", "jobs", id, "results"]) => {
+            if !state.polls.contains_key(*id) {
+                return not_found();
+            }
+            if state.states.last().is_some_and(|s| s == "failed") {
+                return Reply::json(
+                    400,
+                    json!({
+                        "type": "job results failed",
+                        "title": "job failed",
+                        "status": 400,
+                        "traceback": "ValueError: no data for the requested dates",
+                    }),
+                );
+            }
+            Reply::json(
+                200,
+                json!({"asset": {"value": {
+                    "href": format!("{}/downloads/{}", origin, id),
+                    "file:size": state.result.len(),
+                    "type": state.content_type,
+                }}}),
+            )
+        }
+        ("DELETE", ["api", "retrieve", "v1", "jobs", id]) => {
+            if state.polls.remove(*id).is_none() {
+                return not_found();
+            }
+            Reply::json(200, json!({"jobID": id, "status": "dismissed"}))
+        }
+        ("POST", ["api", "v2", "resources", _]) => {
+            if let Some((status, body)) = &state.submit_error {
+                return Reply {
+                    status: *status,
+                    headers: vec![("Content-Type", "application/json".to_string())],
+                    body: body.clone().into_bytes(),
+                    truncate: None,
+                };
+            }
+            state.next_job += 1;
+            let id = format!("job-{}", state.next_job);
+            state.polls.insert(id.clone(), 0);
+            Reply::json(202, json!({"state": "queued", "request_id": id}))
+        }
+        ("GET", ["api", "v2", "tasks", id]) => {
+            let Some(polls) = state.polls.get_mut(*id) else {
+                return not_found();
+            };
+            let status = state
+                .states
+                .get((*polls).min(state.states.len().saturating_sub(1)))
+                .cloned()
+                .unwrap_or_else(|| "successful".to_string());
+            *polls += 1;
+            match status.as_str() {
+                "successful" => Reply::json(
+                    200,
+                    json!({
+                        "state": "completed",
+                        "request_id": id,
+                        "location": format!("{}/downloads/{}", origin, id),
+                        "content_length": state.result.len(),
+                        "content_type": state.content_type,
+                    }),
+                ),
+                "failed" => Reply::json(
+                    200,
+                    json!({
+                        "state": "failed",
+                        "request_id": id,
+                        "error": {
+                            "message": "job failed",
+                            "reason": "no data for the requested dates",
+                        },
+                    }),
+                ),
+                "accepted" => Reply::json(200, json!({"state": "queued", "request_id": id})),
+                other => Reply::json(200, json!({"state": other, "request_id": id})),
+            }
+        }
+        ("DELETE", ["api", "v2", "tasks", id]) => {
+            if state.polls.remove(*id).is_none() {
+                return not_found();
+            }
+            Reply::json(200, json!({"state": "deleted", "request_id": id}))
+        }
+        ("GET" | "HEAD", ["downloads", id]) if state.polls.contains_key(*id) => {
+            download(request, state)
+        }
+        _ => not_found(),
+    }
+}
+
+fn download(request: &MockRequest, state: &mut MockState) -> Reply {
+    let total = state.result.len();
+    let start = request
+        .header("range")
+        .filter(|_| !state.ignore_ranges)
+        .and_then(|r| r.strip_prefix("bytes="))
+        .and_then(|r| r.split('-').next())
+        .and_then(|s| s.parse::<usize>().ok());
+    let mut headers = vec![
+        ("Content-Type", state.content_type.clone()),
+        ("Accept-Ranges", "bytes".to_string()),
+        ("ETag", format!("\"{}\"", total)),
+    ];
+    let (status, body) = match start {
+        Some(start) if start >= total => {
+            headers.push(("Content-Range", format!("bytes */{}", total)));
+            (416, Vec::new())
+        }
+        Some(start) => {
+            headers.push((
+                "Content-Range",
+                format!("bytes {}-{}/{}", start, total - 1, total),
+            ));
+            (206, state.result[start..].to_vec())
+        }
+        None => (200, state.result.clone()),
+    };
+    let mut truncate = None;
+    if request.method == "GET" && state.interrupt.0 > 0 && state.interrupt.1 < body.len() {
+        state.interrupt.0 -= 1;
+        truncate = Some(state.interrupt.1);
+    }
+    Reply {
+        status,
+        headers,
+        body,
+        truncate,
+    }
+}
+
+fn not_found() -> Reply {
+    Reply::json(404, json!({"title": "not found"}))
+}
+
+fn write_reply(mut stream: TcpStream, method: &str, reply: Reply) -> std::io::Result<()> {
+    let mut head = format!(
+        "HTTP/1.1 {} Mock\r\nContent-Length: {}\r\nConnection: close\r\n",
+        reply.status,
+        reply.body.len()
+    );
+    for (name, value) in &reply.headers {
+        head.push_str(&format!("{}: {}\r\n", name, value));
+    }
+    head.push_str("\r\n");
+    stream.write_all(head.as_bytes())?;
+    if method != "HEAD" {
+        let end = reply.truncate.unwrap_or(reply.body.len());
+        stream.write_all(&reply.body[..end])?;
+    }
+    stream.flush()
+}
@@ -0,0 +1,129 @@
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use crate::client::Client;
+use crate::error::Result;
+
+/// Caps a byte rate: callers report the bytes they received and are told how long to pause.
+#[derive(Debug)]
+pub(crate) struct RateLimit {
+    bytes_per_sec: u64,
+    /// When the bytes reported so far will have been "paid for" at the allowed rate.
+    next: Mutex<Instant>,
+}
+
+impl RateLimit {
+    pub(crate) fn new(bytes_per_sec: u64) -> Self {
+        Self {
+            bytes_per_sec: bytes_per_sec.max(1),
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Accounts for `bytes` and returns how long to wait before reading more.
+    fn consume(&self, bytes: u64) -> Duration {
+        let now = Instant::now();
+        let mut next = self.next.lock().unwrap_or_else(|e| e.into_inner());
+        // Idle time earns no credit, so a pause is never followed by a burst.
+        *next =
+            (*next).max(now) + Duration::from_secs_f64(bytes as f64 / self.bytes_per_sec as f64);
+        next.saturating_duration_since(now)
+    }
+}
+
+/// Caps a request rate, allowing short bursts: a token bucket holding up to `burst`
+/// tokens, refilled at `per_sec` tokens per second.
+#[derive(Debug)]
+pub(crate) struct TokenBucket {
+    per_sec: f64,
+    burst: f64,
+    /// Tokens left (negative while callers are waiting for refills) and when it was counted.
+    tokens: Mutex<(f64, Instant)>,
+}
+
+impl TokenBucket {
+    pub(crate) fn new(per_sec: f64, burst: u32) -> Self {
+        let burst = f64::from(burst.max(1));
+        Self {
+            per_sec: per_sec.max(f64::MIN_POSITIVE),
+            burst,
+            tokens: Mutex::new((burst, Instant::now())),
+        }
+    }
+
+    /// Takes a token and returns how long to wait before using it.
+    fn take(&self) -> Duration {
+        let now = Instant::now();
+        let mut tokens = self.tokens.lock().unwrap_or_else(|e| e.into_inner());
+        let refill = now.saturating_duration_since(tokens.1).as_secs_f64() * self.per_sec;
+        tokens.0 = (tokens.0 + refill).min(self.burst) - 1.0;
+        tokens.1 = now;
+        if tokens.0 >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-tokens.0 / self.per_sec)
+        }
+    }
+}
+
+impl Client {
+    /// Limits API requests made by this client and its clones to `per_sec` per second on
+    /// average, allowing bursts of up to `burst` requests, so batch tooling does not trip
+    /// the server's rate limits (HTTP 429) in the first place.
+    ///
+    /// Every HTTP attempt counts, including retries and the requests opening downloads.
+    pub fn with_api_rate_limit(mut self, per_sec: f64, burst: u32) -> Self {
+        self.api_rate_limit = Some(Arc::new(TokenBucket::new(per_sec, burst)));
+        self
+    }
+
+    /// Waits until the API rate limit allows another request.
+    pub(crate) fn pace_request(&self) -> Result<()> {
+        match &self.api_rate_limit {
+            Some(bucket) => {
+                let delay = bucket.take();
+                if delay.is_zero() {
+                    Ok(())
+                } else {
+                    self.sleep(delay)
+                }
+            }
+            None => Ok(()),
+        }
+    }
+
+    /// Caps the speed of each download to `bytes_per_sec`. Parallel segments of one download
+    /// (see [`Client::with_download_connections`]) share the cap.
+    pub fn with_max_download_rate(mut self, bytes_per_sec: u64) -> Self {
+        self.max_download_rate = Some(bytes_per_sec);
+        self
+    }
+
+    /// Caps the combined speed of all downloads made by this client and its clones, e.g.
+    /// by [`Client::retrieve_many`] workers, to `bytes_per_sec`.
+    pub fn with_max_total_download_rate(mut self, bytes_per_sec: u64) -> Self {
+        self.total_download_rate = Some(Arc::new(RateLimit::new(bytes_per_sec)));
+        self
+    }
+
+    /// A fresh per-download limit, if one is configured.
+    pub(crate) fn download_rate_limit(&self) -> Option<RateLimit> {
+        self.max_download_rate.map(RateLimit::new)
+    }
+
+    /// Pauses after receiving `bytes` as long as the per-download `limit` and the client-wide
+    /// limit require.
+    pub(crate) fn throttle(&self, limit: Option<&RateLimit>, bytes: u64) -> Result<()> {
+        let delay = [limit, self.total_download_rate.as_deref()]
+            .into_iter()
+            .flatten()
+            .map(|limit| limit.consume(bytes))
+            .max()
+            .unwrap_or_default();
+        if delay.is_zero() {
+            Ok(())
+        } else {
+            self.sleep(delay)
+        }
+    }
+}
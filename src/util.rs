@@ -1,13 +1,38 @@
-use reqwest::StatusCode;
-use std::time::Duration;
+use reqwest::header::{CONTENT_DISPOSITION, HeaderMap, HeaderValue, RETRY_AFTER, USER_AGENT};
+use serde_json::Value;
+use std::time::{Duration, SystemTime};
+use url::Url;
 
-pub(crate) fn retriable_status(code: u16) -> bool {
-    matches!(code, 500 | 502 | 503 | 504 | 429 | 408)
+use crate::error::{CdsError, Result};
+
+pub(crate) fn default_headers() -> HeaderMap {
+    let mut headers = HeaderMap::new();
+    headers.insert(
+        USER_AGENT,
+        HeaderValue::from_str(&format!("cdsapi-rs/{}", env!("CARGO_PKG_VERSION")))
+            .unwrap_or(HeaderValue::from_static("cdsapi-rs")),
+    );
+    headers
+}
+
+/// Longest wait honored from a `Retry-After` header.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(15 * 60);
+
+/// Delay requested by a `Retry-After` header (delta-seconds or HTTP date), capped.
+pub(crate) fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    let delay = match value.parse::<u64>() {
+        Ok(secs) => Duration::from_secs(secs),
+        Err(_) => httpdate::parse_http_date(value)
+            .ok()?
+            .duration_since(SystemTime::now())
+            .unwrap_or(Duration::ZERO),
+    };
+    Some(delay.min(MAX_RETRY_AFTER))
 }
 
 pub(crate) fn backoff(current: Duration, max: Duration) -> Duration {
-    let next = Duration::from_secs_f64((current.as_secs_f64() * 1.5).max(1.0));
-    if next > max { max } else { next }
+    current.mul_f64(1.5).min(max)
 }
 
 pub(crate) fn guess_filename_from_url(url: &str) -> Option<String> {
@@ -21,6 +46,69 @@ pub(crate) fn guess_filename_from_url(url: &str) -> Option<String> {
     })
 }
 
+/// File name from a `Content-Disposition` header, preferring the RFC 5987 `filename*`
+/// parameter, [sanitized](sanitize_filename).
+pub(crate) fn content_disposition_filename(headers: &HeaderMap) -> Option<String> {
+    let value = headers.get(CONTENT_DISPOSITION)?.to_str().ok()?;
+    let mut plain = None;
+    let mut extended = None;
+    for param in value.split(';').map(str::trim) {
+        let Some((name, raw)) = param.split_once('=') else {
+            continue;
+        };
+        match name.trim().to_ascii_lowercase().as_str() {
+            // filename*=UTF-8''na%C3%AFve.nc
+            "filename*" => {
+                extended = raw.trim().splitn(3, '\'').nth(2).and_then(percent_decode);
+            }
+            "filename" => plain = Some(raw.trim().trim_matches('"').to_string()),
+            _ => {}
+        }
+    }
+    sanitize_filename(&extended.or(plain)?)
+}
+
+/// Reduces a server-provided name to a plain file name, so it cannot point outside the
+/// target directory: path components and control characters are dropped, and `.`/`..` or
+/// empty names are rejected.
+pub(crate) fn sanitize_filename(name: &str) -> Option<String> {
+    let name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    let name: String = name.chars().filter(|c| !c.is_control()).collect();
+    let name = name.trim();
+    if name.is_empty() || name == "." || name == ".." {
+        None
+    } else {
+        Some(name.to_string())
+    }
+}
+
+fn percent_decode(s: &str) -> Option<String> {
+    let bytes = s.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        if bytes[i] == b'%' {
+            let hex = std::str::from_utf8(bytes.get(i + 1..i + 3)?).ok()?;
+            out.push(u8::from_str_radix(hex, 16).ok()?);
+            i += 3;
+        } else {
+            out.push(bytes[i]);
+            i += 1;
+        }
+    }
+    String::from_utf8(out).ok()
+}
+
+/// Flattens a request value (scalar or list) into its string representations.
+pub(crate) fn value_strings(v: &Value) -> Vec<String> {
+    match v {
+        Value::Array(items) => items.iter().flat_map(value_strings).collect(),
+        Value::String(s) => vec![s.clone()],
+        Value::Null => Vec::new(),
+        other => vec![other.to_string()],
+    }
+}
+
 pub(crate) fn split_key_basic(key: &str) -> Option<(String, String)> {
     let parts: Vec<&str> = key.splitn(2, ':').collect();
     if parts.len() == 2 && !parts[0].trim().is_empty() && !parts[1].trim().is_empty() {
@@ -30,57 +118,104 @@ pub(crate) fn split_key_basic(key: &str) -> Option<(String, String)> {
     }
 }
 
-pub(crate) fn urljoin(base: &str, path: &str) -> String {
-    if path.starts_with("http://") || path.starts_with("https://") {
-        return path.to_string();
+/// Resolves `href` (absolute, or relative as in a results link) against `base`, like a
+/// browser would. Falls back to plain concatenation if `base` is not a valid URL.
+pub(crate) fn urljoin(base: &str, href: &str) -> String {
+    if let Ok(url) = Url::parse(href) {
+        return url.into();
     }
-    let base = base.trim_end_matches('/');
-    if path.starts_with('/') {
-        format!("{}{}", base, path)
-    } else {
-        format!("{}/{}", base, path)
+    match Url::parse(base).and_then(|base| base.join(href)) {
+        Ok(url) => url.into(),
+        Err(_) => format!(
+            "{}/{}",
+            base.trim_end_matches('/'),
+            href.trim_start_matches('/')
+        ),
     }
 }
 
+/// Adds `params` to the query of `url`, percent-encoding them and keeping any existing
+/// query and fragment.
 pub(crate) fn append_query(url: &str, params: &[(&str, &str)]) -> String {
-    // We only need a tiny helper for "log=true&request=true".
-    let mut out = url.to_string();
-    let sep = if url.contains('?') { '&' } else { '?' };
-    out.push(sep);
-    let mut first = true;
-    for (k, v) in params {
-        if !first {
-            out.push('&');
-        }
-        first = false;
-        out.push_str(k);
-        out.push('=');
-        out.push_str(v);
-    }
-    out
+    let Ok(mut parsed) = Url::parse(url) else {
+        let pairs: Vec<String> = params.iter().map(|(k, v)| format!("{}={}", k, v)).collect();
+        let sep = if url.contains('?') { '&' } else { '?' };
+        return format!("{}{}{}", url, sep, pairs.join("&"));
+    };
+    parsed.query_pairs_mut().extend_pairs(params);
+    parsed.into()
 }
 
+/// The `/api/v2` variant of a base URL, which some deployments require: `.../api` becomes
+/// `.../api/v2`, and a host root gets `/api/v2` appended.
 pub(crate) fn api_v2_variant(base: &str) -> Option<String> {
-    // Common cases:
-    // - https://.../api      -> https://.../api/v2
-    // - https://.../api/     -> https://.../api/v2
-    let b = base.trim_end_matches('/');
-    if b.ends_with("/api") {
-        return Some(format!("{}/v2", b));
+    let mut url = Url::parse(base).ok()?;
+    let segments: Vec<String> = url
+        .path_segments()?
+        .filter(|s| !s.is_empty())
+        .map(str::to_string)
+        .collect();
+    let suffix: &[&str] = match segments.iter().position(|s| s == "api") {
+        Some(i) if i + 1 == segments.len() => &["v2"],
+        Some(_) => return None,
+        None => &["api", "v2"],
+    };
+    url.path_segments_mut().ok()?.pop_if_empty().extend(suffix);
+    Some(url.into())
+}
+
+/// Rejects base URLs that cannot work: not http(s), no host, or with a query or fragment.
+pub(crate) fn check_base_url(base: &str) -> Result<()> {
+    let invalid = |problem: &str| {
+        Err(CdsError::Config(format!(
+            "invalid API url `{}`: {} (expected e.g. https://cds.climate.copernicus.eu/api)",
+            base, problem
+        )))
+    };
+    let url = match Url::parse(base) {
+        Ok(url) => url,
+        Err(e) => return invalid(&e.to_string()),
+    };
+    if !matches!(url.scheme(), "http" | "https") {
+        return invalid("the scheme must be http or https");
+    }
+    if url.host_str().is_none_or(str::is_empty) {
+        return invalid("missing host");
     }
-    // If user set host root, try appending /api/v2
-    if !b.contains("/api/") && !b.ends_with("/api/v2") {
-        return Some(format!("{}/api/v2", b));
+    if url.query().is_some() || url.fragment().is_some() {
+        return invalid("unexpected query or fragment");
     }
-    None
+    Ok(())
 }
 
-pub(crate) fn extract_http_status(err: &anyhow::Error) -> Option<StatusCode> {
-    // We format errors including "HTTP <code>" in api_json.
-    // Best-effort parse for 404 detection.
-    let s = err.to_string();
-    if s.contains("HTTP 404") {
-        return Some(StatusCode::NOT_FOUND);
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(retry_after: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_str(retry_after).unwrap());
+        headers
+    }
+
+    #[test]
+    fn retry_after_reads_seconds_and_dates() {
+        assert_eq!(retry_after(&HeaderMap::new()), None);
+        assert_eq!(
+            retry_after(&headers(" 120 ")),
+            Some(Duration::from_secs(120))
+        );
+        assert_eq!(retry_after(&headers("soon")), None);
+
+        let later = SystemTime::now() + Duration::from_secs(300);
+        let delay = retry_after(&headers(&httpdate::fmt_http_date(later))).unwrap();
+        assert!(delay > Duration::from_secs(290) && delay <= Duration::from_secs(300));
+        let past = httpdate::fmt_http_date(SystemTime::now() - Duration::from_secs(60));
+        assert_eq!(retry_after(&headers(&past)), Some(Duration::ZERO));
+    }
+
+    #[test]
+    fn retry_after_is_capped() {
+        assert_eq!(retry_after(&headers("86400")), Some(MAX_RETRY_AFTER));
     }
-    None
 }
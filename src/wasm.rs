@@ -0,0 +1,145 @@
+//! Browser client, driving [`Retrieval`] over reqwest's `fetch` backend.
+
+use futures_util::StreamExt;
+use gloo_timers::future::sleep;
+use js_sys::{Array, Uint8Array};
+use serde::Serialize;
+use std::time::Duration;
+use web_sys::{Blob, BlobPropertyBag};
+
+use crate::error::{CdsError, Result};
+use crate::protocol::{Action, HttpRequest, HttpResponse, RemoteFile, Retrieval, header_pairs};
+
+/// CDS client for `wasm32-unknown-unknown`, sending requests with the browser's `fetch`.
+///
+/// Only the Retrieve API (personal access tokens) is supported, and the API must allow
+/// cross-origin requests from the page, or be reached through a proxy that does.
+#[derive(Debug, Clone)]
+pub struct WasmClient {
+    url: String,
+    key: String,
+    http: reqwest::Client,
+    poll_interval: (Duration, Duration),
+}
+
+impl WasmClient {
+    /// Creates a client for the API at `url` (e.g. `https://cds.climate.copernicus.eu/api`)
+    /// with the personal access token `key`.
+    pub fn new(url: impl Into<String>, key: impl Into<String>) -> Self {
+        Self {
+            url: url.into(),
+            key: key.into(),
+            http: reqwest::Client::new(),
+            poll_interval: (Duration::from_secs(1), Duration::from_secs(120)),
+        }
+    }
+
+    /// Sets the range of the status polling interval (default 1s, growing to 120s).
+    pub fn with_poll_interval(mut self, min: Duration, max: Duration) -> Self {
+        self.poll_interval = (min, max);
+        self
+    }
+
+    /// Submits `request` for `dataset`, waits for the job and returns its result files.
+    pub async fn retrieve<T: Serialize>(
+        &self,
+        dataset: &str,
+        request: &T,
+    ) -> Result<Vec<RemoteFile>> {
+        self.retrieve_with(dataset, request, |_| {}).await
+    }
+
+    /// Like [`WasmClient::retrieve`], calling `on_update` after each response so the page
+    /// can show the job ID, [`status`](Retrieval::status) and [`logs`](Retrieval::logs).
+    pub async fn retrieve_with<T: Serialize>(
+        &self,
+        dataset: &str,
+        request: &T,
+        mut on_update: impl FnMut(&Retrieval),
+    ) -> Result<Vec<RemoteFile>> {
+        let (min, max) = self.poll_interval;
+        let mut retrieval =
+            Retrieval::new(&self.url, &self.key, dataset, request)?.with_poll_interval(min, max);
+        let mut response = self.send(&retrieval.submit_request()).await?;
+        loop {
+            let action = retrieval.handle(response)?;
+            on_update(&retrieval);
+            response = match action {
+                Action::Send(request) => self.send(&request).await?,
+                Action::Poll { after, request } => {
+                    sleep(after).await;
+                    self.send(&request).await?
+                }
+                Action::Done(files) => return Ok(files),
+            };
+        }
+    }
+
+    /// Downloads `file` into a [`Blob`], e.g. to hand to `URL.createObjectURL`.
+    ///
+    /// The body is read chunk by chunk, so the data is only held once, by the blob parts.
+    pub async fn download_blob(&self, file: &RemoteFile) -> Result<Blob> {
+        let resp = self
+            .http
+            .get(&file.location)
+            .send()
+            .await
+            .map_err(|e| CdsError::transport("failed to download file", e))?
+            .error_for_status()
+            .map_err(|e| CdsError::transport("failed to download file", e))?;
+
+        let parts = Array::new();
+        let mut downloaded: u64 = 0;
+        let mut stream = resp.bytes_stream();
+        while let Some(chunk) = stream.next().await {
+            let chunk =
+                chunk.map_err(|e| CdsError::download("download interrupted", Some(Box::new(e))))?;
+            downloaded += chunk.len() as u64;
+            parts.push(&Uint8Array::from(chunk.as_ref()));
+        }
+        if downloaded < file.content_length {
+            return Err(CdsError::download(
+                format!(
+                    "download failed: downloaded {} byte(s) out of {}",
+                    downloaded, file.content_length
+                ),
+                None,
+            ));
+        }
+
+        let options = BlobPropertyBag::new();
+        if let Some(content_type) = &file.content_type {
+            options.set_type(content_type);
+        }
+        Blob::new_with_u8_array_sequence_and_options(&parts, &options)
+            .map_err(|e| CdsError::download(format!("failed to create blob: {:?}", e), None))
+    }
+
+    async fn send(&self, request: &HttpRequest) -> Result<HttpResponse> {
+        let method = reqwest::Method::from_bytes(request.method.as_bytes())
+            .map_err(|_| CdsError::Protocol(format!("invalid method {}", request.method)))?;
+        let mut builder = self.http.request(method, &request.url);
+        for (name, value) in &request.headers {
+            builder = builder.header(name, value);
+        }
+        if let Some(body) = &request.body {
+            builder = builder.body(body.clone());
+        }
+        let resp = builder
+            .send()
+            .await
+            .map_err(|e| CdsError::transport(format!("request to {} failed", request.url), e))?;
+
+        let status = resp.status().as_u16();
+        let headers = header_pairs(resp.headers());
+        let body = resp
+            .bytes()
+            .await
+            .map_err(|e| CdsError::transport(format!("failed to read {}", request.url), e))?;
+        Ok(HttpResponse {
+            status,
+            headers,
+            body: body.to_vec(),
+        })
+    }
+}
@@ -0,0 +1,139 @@
+use reqwest::header::HeaderMap;
+use reqwest::{Method, StatusCode};
+use serde_json::{Map, Value, json};
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use crate::error::{CdsError, Result};
+
+/// Bodies longer than this are cut off in the log.
+const MAX_BODY: usize = 4096;
+
+/// Headers whose values are replaced by `***` in the log.
+const SECRET_HEADERS: &[&str] = &[
+    "authorization",
+    "proxy-authorization",
+    "private-token",
+    "cookie",
+    "set-cookie",
+];
+
+/// Records HTTP exchanges as JSON lines (see [`Client::with_wire_log`](crate::Client::with_wire_log)).
+///
+/// With the `tracing` feature, the same records are also emitted as TRACE events with target
+/// `cdsapi::wire`.
+#[derive(Debug)]
+pub(crate) struct WireLog {
+    file: Mutex<File>,
+}
+
+impl WireLog {
+    /// Appends to the file at `path`, creating it if needed.
+    pub(crate) fn open(path: &Path) -> Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .map_err(|e| CdsError::io(format!("failed to open {}", path.display()), e))?;
+        Ok(Self {
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Whether records would go anywhere; building them is skipped otherwise.
+    pub(crate) fn enabled(log: Option<&Self>) -> bool {
+        #[cfg(feature = "tracing")]
+        if tracing::enabled!(target: "cdsapi::wire", tracing::Level::TRACE) {
+            return true;
+        }
+        log.is_some()
+    }
+
+    pub(crate) fn request(
+        log: Option<&Self>,
+        method: &Method,
+        url: &str,
+        headers: &HeaderMap,
+        body: Option<&[u8]>,
+    ) {
+        let mut record = record("request", url);
+        record.insert("method".into(), json!(method.as_str()));
+        record.insert("headers".into(), sanitize(headers));
+        if let Some(body) = body {
+            record.insert(
+                "body".into(),
+                json!(truncate(&String::from_utf8_lossy(body))),
+            );
+        }
+        write(log, record);
+    }
+
+    pub(crate) fn response(log: Option<&Self>, url: &str, status: StatusCode, headers: &HeaderMap) {
+        let mut record = record("response", url);
+        record.insert("status".into(), json!(status.as_u16()));
+        record.insert("headers".into(), sanitize(headers));
+        write(log, record);
+    }
+
+    pub(crate) fn response_body(log: Option<&Self>, url: &str, body: &str) {
+        let mut record = record("response_body", url);
+        record.insert("body".into(), json!(truncate(body)));
+        write(log, record);
+    }
+
+    pub(crate) fn error(log: Option<&Self>, url: &str, error: &reqwest::Error) {
+        let mut record = record("error", url);
+        record.insert("error".into(), json!(error.to_string()));
+        write(log, record);
+    }
+}
+
+fn record(event: &str, url: &str) -> Map<String, Value> {
+    let ts = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs_f64())
+        .unwrap_or_default();
+    let mut record = Map::new();
+    record.insert("ts".into(), json!(ts));
+    record.insert("event".into(), json!(event));
+    record.insert("url".into(), json!(url));
+    record
+}
+
+fn write(log: Option<&WireLog>, record: Map<String, Value>) {
+    let line = Value::Object(record).to_string();
+    #[cfg(feature = "tracing")]
+    tracing::trace!(target: "cdsapi::wire", "{}", line);
+    if let Some(log) = log {
+        let mut file = log.file.lock().unwrap_or_else(|e| e.into_inner());
+        // Logging must never fail the call it describes.
+        let _ = writeln!(file, "{}", line);
+    }
+}
+
+fn sanitize(headers: &HeaderMap) -> Value {
+    let mut out = Map::new();
+    for (name, value) in headers {
+        let value = if SECRET_HEADERS.contains(&name.as_str()) {
+            "***".to_string()
+        } else {
+            String::from_utf8_lossy(value.as_bytes()).into_owned()
+        };
+        out.insert(name.as_str().to_string(), json!(value));
+    }
+    Value::Object(out)
+}
+
+fn truncate(body: &str) -> String {
+    if body.len() <= MAX_BODY {
+        return body.to_string();
+    }
+    let mut end = MAX_BODY;
+    while !body.is_char_boundary(end) {
+        end -= 1;
+    }
+    format!("{}... ({} bytes)", &body[..end], body.len())
+}
@@ -0,0 +1,117 @@
+use cdsapi::test_util::MockServer;
+use cdsapi::{CdsError, CdsEvent, JobStatus, Submission};
+use serde_json::json;
+use std::path::PathBuf;
+
+const DATASET: &str = "reanalysis-era5-single-levels";
+
+/// A fresh path in the temporary directory, unique to this process and `name`.
+fn temp_target(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cdsapi-async-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+#[tokio::test]
+async fn submits_polls_and_downloads() {
+    let server = MockServer::start()
+        .with_states(&["accepted", "running", "successful"])
+        .with_log(&["request cost reduced"])
+        .with_result(b"GRIB data".to_vec());
+    let (tx, rx) = std::sync::mpsc::channel();
+    let client = server.async_client().unwrap().with_event_sink(tx);
+
+    let target = temp_target("retrieve.grib");
+    client
+        .retrieve(DATASET, &json!({}), Some(&target))
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&target).unwrap(), b"GRIB data");
+
+    let events: Vec<CdsEvent> = rx.try_iter().collect();
+    assert!(matches!(events.first(), Some(CdsEvent::Submitted { .. })));
+    assert!(matches!(events.last(), Some(CdsEvent::Completed { .. })));
+    let states: Vec<_> = events
+        .iter()
+        .filter_map(|e| match e {
+            CdsEvent::StateChanged { status, .. } => Some(status.clone()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(
+        states,
+        [JobStatus::Queued, JobStatus::Running, JobStatus::Successful]
+    );
+    let lines: Vec<_> = events
+        .iter()
+        .filter_map(|e| match e {
+            CdsEvent::LogLine { line, .. } => Some(line.as_str()),
+            _ => None,
+        })
+        .collect();
+    assert_eq!(lines, ["2024-01-01T00:00:00 request cost reduced"]);
+}
+
+#[tokio::test]
+async fn retrieve_nowait_returns_pending_job() {
+    let server = MockServer::start().with_states(&["accepted", "running", "successful"]);
+    let client = server.async_client().unwrap();
+
+    let job = match client.retrieve_nowait(DATASET, &json!({}), None).await {
+        Ok(Submission::Pending { job, status }) => {
+            assert_eq!(status, JobStatus::Queued);
+            job
+        }
+        other => panic!("expected a pending job, got {:?}", other),
+    };
+    assert_eq!(job.id(), "job-1");
+
+    let target = temp_target("nowait.grib");
+    job.download(&target).await.unwrap();
+    assert_eq!(std::fs::read(&target).unwrap(), b"mock result");
+}
+
+#[tokio::test]
+async fn failed_job_reports_details_and_logs() {
+    let server = MockServer::start()
+        .with_states(&["accepted", "failed"])
+        .with_log(&["checking request", "no matching data"]);
+    let client = server.async_client().unwrap();
+
+    let err = client
+        .retrieve(DATASET, &json!({}), None)
+        .await
+        .unwrap_err();
+    match err {
+        CdsError::JobFailed { message, logs } => {
+            assert!(message.contains("ValueError: no data for the requested dates"));
+            assert_eq!(
+                logs,
+                [
+                    "2024-01-01T00:00:00 checking request",
+                    "2024-01-01T00:00:00 no matching data"
+                ]
+            );
+        }
+        other => panic!("expected JobFailed, got {:?}", other),
+    }
+}
+
+#[tokio::test]
+async fn restarts_download_when_range_is_ignored() {
+    let result = b"0123456789abcdef".to_vec();
+    let server = MockServer::start()
+        .with_result(result.clone())
+        .with_interrupted_downloads(1, 4)
+        .with_ignored_ranges();
+    let client = server.async_client().unwrap();
+
+    let target = temp_target("ignored-range.grib");
+    client
+        .retrieve(DATASET, &json!({}), Some(&target))
+        .await
+        .unwrap();
+    assert_eq!(std::fs::read(&target).unwrap(), result);
+}
@@ -0,0 +1,322 @@
+use cdsapi::test_util::{MockRequest, MockServer};
+use cdsapi::{CdsError, DownloadOutcome, OverwritePolicy, RetryPolicy};
+use serde_json::json;
+use std::io::Read;
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+const DATASET: &str = "reanalysis-era5-single-levels";
+
+/// A fresh path in the temporary directory, unique to this process and `name`.
+fn temp_target(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cdsapi-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn paths(requests: &[MockRequest], method: &str) -> Vec<String> {
+    requests
+        .iter()
+        .filter(|r| r.method == method)
+        .map(|r| r.path.split('?').next().unwrap().to_string())
+        .collect()
+}
+
+#[test]
+fn submits_polls_and_downloads() {
+    let server = MockServer::start()
+        .with_states(&["accepted", "running", "successful"])
+        .with_log(&["request cost reduced"])
+        .with_result(b"GRIB data".to_vec());
+    let client = server.client().unwrap();
+
+    let job = client.submit(DATASET, &json!({"year": ["2024"]})).unwrap();
+    let file = job.wait().unwrap();
+    assert_eq!(file.content_length, 9);
+    assert_eq!(job.logs(), ["2024-01-01T00:00:00 request cost reduced"]);
+    let polls = server
+        .requests()
+        .iter()
+        .filter(|r| r.path.starts_with("/api/retrieve/v1/jobs/job-1?log=true"))
+        .count();
+    assert_eq!(polls, 3);
+
+    let target = temp_target("submit.grib");
+    job.download(&target).unwrap();
+    assert_eq!(std::fs::read(&target).unwrap(), b"GRIB data");
+
+    let requests = server.requests();
+    let submit = &requests[0];
+    assert_eq!(
+        submit.path,
+        format!("/api/retrieve/v1/processes/{}/execution", DATASET)
+    );
+    assert_eq!(submit.header("private-token"), Some("mock-token"));
+    let body: serde_json::Value = serde_json::from_str(&submit.body).unwrap();
+    assert_eq!(body["inputs"]["year"], json!(["2024"]));
+    assert!(paths(&requests, "GET").contains(&"/downloads/job-1".to_string()));
+}
+
+#[test]
+fn retrieve_nowait_returns_pending_job() {
+    let server = MockServer::start().with_states(&["accepted", "running", "successful"]);
+    let client = server.client().unwrap();
+
+    let target = temp_target("nowait.grib");
+    let job = match client.retrieve_nowait(DATASET, &json!({}), Some(&target)) {
+        Ok(cdsapi::Submission::Pending { job, status }) => {
+            assert_eq!(status, cdsapi::JobStatus::Queued);
+            job
+        }
+        other => panic!("expected a pending job, got {:?}", other),
+    };
+    assert!(!target.exists());
+
+    job.download(&target).unwrap();
+    assert_eq!(std::fs::read(&target).unwrap(), b"mock result");
+}
+
+#[test]
+fn failed_job_reports_details_and_logs() {
+    let server = MockServer::start()
+        .with_states(&["accepted", "failed"])
+        .with_log(&["checking request", "no matching data"]);
+    let client = server.client().unwrap();
+
+    let err = client.retrieve(DATASET, &json!({}), None).unwrap_err();
+    match err {
+        CdsError::JobFailed { message, logs } => {
+            assert!(message.contains("processing failed with status failed"));
+            assert!(message.contains("ValueError: no data for the requested dates"));
+            assert_eq!(
+                logs,
+                [
+                    "2024-01-01T00:00:00 checking request",
+                    "2024-01-01T00:00:00 no matching data"
+                ]
+            );
+        }
+        other => panic!("expected JobFailed, got {:?}", other),
+    }
+}
+
+#[test]
+fn resumes_interrupted_download_with_range() {
+    let result = b"0123456789abcdef".to_vec();
+    let server = MockServer::start()
+        .with_result(result.clone())
+        .with_interrupted_downloads(1, 4);
+    let client = server.client().unwrap();
+
+    let target = temp_target("resume.grib");
+    client.retrieve(DATASET, &json!({}), Some(&target)).unwrap();
+    assert_eq!(std::fs::read(&target).unwrap(), result);
+
+    let ranges: Vec<_> = server
+        .requests()
+        .iter()
+        .filter(|r| r.path.starts_with("/downloads/"))
+        .filter_map(|r| r.header("range").map(str::to_string))
+        .collect();
+    assert_eq!(ranges, ["bytes=4-"]);
+}
+
+#[test]
+fn restarts_download_when_range_is_ignored() {
+    let result = b"0123456789abcdef".to_vec();
+    let server = MockServer::start()
+        .with_result(result.clone())
+        .with_interrupted_downloads(1, 4)
+        .with_ignored_ranges();
+    let client = server.client().unwrap();
+
+    let target = temp_target("ignored-range.grib");
+    client.retrieve(DATASET, &json!({}), Some(&target)).unwrap();
+    assert_eq!(std::fs::read(&target).unwrap(), result);
+}
+
+#[test]
+fn legacy_api_falls_back_to_v2() {
+    let server = MockServer::start()
+        .with_states(&["accepted", "successful"])
+        .with_result(b"legacy data".to_vec());
+    let client = server.legacy_client().unwrap();
+
+    let target = temp_target("legacy.grib");
+    let file = client.retrieve(DATASET, &json!({}), Some(&target)).unwrap();
+    assert_eq!(file.content_length, 11);
+    assert_eq!(std::fs::read(&target).unwrap(), b"legacy data");
+
+    let requests = server.requests();
+    assert_eq!(
+        paths(&requests, "POST"),
+        [
+            format!("/api/resources/{}", DATASET),
+            format!("/api/v2/resources/{}", DATASET),
+        ]
+    );
+    assert!(
+        requests[1]
+            .header("authorization")
+            .is_some_and(|auth| auth.starts_with("Basic "))
+    );
+    assert!(paths(&requests, "GET").contains(&"/api/v2/tasks/job-1".to_string()));
+
+    // The resolved base is remembered for later submissions.
+    client.retrieve(DATASET, &json!({}), None).unwrap();
+    let posts = paths(&server.requests(), "POST");
+    assert_eq!(
+        posts.last().unwrap(),
+        &format!("/api/v2/resources/{}", DATASET)
+    );
+    assert_eq!(posts.len(), 3);
+}
+
+#[test]
+fn legacy_failure_reports_reason() {
+    let server = MockServer::start().with_states(&["accepted", "failed"]);
+    let client = server.legacy_client().unwrap();
+
+    let err = client.retrieve(DATASET, &json!({}), None).unwrap_err();
+    match err {
+        CdsError::JobFailed { message, .. } => {
+            assert_eq!(message, "job failed. no data for the requested dates");
+        }
+        other => panic!("expected JobFailed, got {:?}", other),
+    }
+}
+
+#[test]
+fn gives_up_when_retry_budget_expires() {
+    let server = MockServer::start().with_submit_error(503, r#"{"title": "busy"}"#);
+    let client = server.client().unwrap().with_retry_policy(
+        RetryPolicy::new()
+            .with_max_attempts(1000)
+            .with_base_delay(Duration::from_millis(20))
+            .with_max_delay(Duration::from_millis(20))
+            .with_max_elapsed(Duration::from_millis(300)),
+    );
+
+    let started = Instant::now();
+    let err = client.retrieve(DATASET, &json!({}), None).unwrap_err();
+    assert_eq!(err.status().map(|s| s.as_u16()), Some(503));
+    assert!(started.elapsed() < Duration::from_secs(5));
+
+    let attempts = paths(&server.requests(), "POST").len();
+    assert!(
+        (2..100).contains(&attempts),
+        "{} attempts within the budget",
+        attempts
+    );
+}
+
+#[test]
+fn remote_reader_resumes_with_range() {
+    let result = b"0123456789abcdef".to_vec();
+    let server = MockServer::start()
+        .with_result(result.clone())
+        .with_interrupted_downloads(1, 4);
+    let client = server.client().unwrap();
+
+    let file = client.retrieve(DATASET, &json!({}), None).unwrap();
+    let mut data = Vec::new();
+    file.open(&client).read_to_end(&mut data).unwrap();
+    assert_eq!(data, result);
+
+    let ranges: Vec<_> = server
+        .requests()
+        .iter()
+        .filter(|r| r.method == "GET" && r.path.starts_with("/downloads/"))
+        .map(|r| r.header("range").map(str::to_string))
+        .collect();
+    assert_eq!(ranges, [None, Some("bytes=4-".to_string())]);
+}
+
+#[test]
+fn remote_reader_skips_bytes_when_range_is_ignored() {
+    let result = b"0123456789abcdef".to_vec();
+    let server = MockServer::start()
+        .with_result(result.clone())
+        .with_interrupted_downloads(1, 4)
+        .with_ignored_ranges();
+    let client = server.client().unwrap();
+
+    let file = client.retrieve(DATASET, &json!({}), None).unwrap();
+    let mut data = Vec::new();
+    file.open(&client).read_to_end(&mut data).unwrap();
+    assert_eq!(data, result);
+}
+
+#[test]
+fn overwrite_policy_decides_for_existing_targets() {
+    let result = b"0123456789abcdef".to_vec();
+    let server = MockServer::start().with_result(result.clone());
+    let client = server.client().unwrap();
+    let file = client.retrieve(DATASET, &json!({}), None).unwrap();
+    let downloads = || {
+        paths(&server.requests(), "GET")
+            .iter()
+            .filter(|p| p.starts_with("/downloads/"))
+            .count()
+    };
+
+    let target = temp_target("overwrite.grib");
+    std::fs::write(&target, b"old").unwrap();
+    client
+        .download_with(&file, &target, OverwritePolicy::Skip)
+        .unwrap();
+    assert!(
+        client
+            .download_with(&file, &target, OverwritePolicy::Error)
+            .is_err()
+    );
+    assert_eq!(std::fs::read(&target).unwrap(), b"old");
+    assert_eq!(downloads(), 0);
+
+    client
+        .download_with(&file, &target, OverwritePolicy::Overwrite)
+        .unwrap();
+    assert_eq!(std::fs::read(&target).unwrap(), result);
+
+    std::fs::write(&target, &result[..4]).unwrap();
+    client
+        .download_with(&file, &target, OverwritePolicy::Resume)
+        .unwrap();
+    assert_eq!(std::fs::read(&target).unwrap(), result);
+    let last = server
+        .requests()
+        .into_iter()
+        .rev()
+        .find(|r| r.method == "GET")
+        .unwrap();
+    assert_eq!(last.header("range"), Some("bytes=4-"));
+}
+
+#[test]
+fn download_if_changed_skips_matching_targets() {
+    let server = MockServer::start().with_result(b"GRIB data".to_vec());
+    let client = server.client().unwrap();
+    let file = client.retrieve(DATASET, &json!({}), None).unwrap();
+
+    let target = temp_target("if-changed.grib");
+    let outcome = client.download_if_changed(&file, &target).unwrap();
+    assert_eq!(outcome, DownloadOutcome::Downloaded(target.clone()));
+    let mut etag = target.clone().into_os_string();
+    etag.push(".etag");
+    assert_eq!(std::fs::read_to_string(&etag).unwrap(), "\"9\"");
+
+    let outcome = client.download_if_changed(&file, &target).unwrap();
+    assert_eq!(outcome, DownloadOutcome::Skipped(target.clone()));
+
+    std::fs::write(&etag, "\"stale\"").unwrap();
+    let outcome = client.download_if_changed(&file, &target).unwrap();
+    assert_eq!(outcome, DownloadOutcome::Downloaded(target.clone()));
+    assert_eq!(std::fs::read(&target).unwrap(), b"GRIB data");
+    let downloads = paths(&server.requests(), "GET")
+        .iter()
+        .filter(|p| p.starts_with("/downloads/"))
+        .count();
+    assert_eq!(downloads, 2);
+}
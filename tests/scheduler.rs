@@ -0,0 +1,136 @@
+use cdsapi::test_util::MockServer;
+use cdsapi::{BatchItem, CdsError, EnqueueOptions, EntryState, Scheduler};
+use serde_json::json;
+use std::path::PathBuf;
+use std::time::Duration;
+
+const DATASET: &str = "reanalysis-era5-single-levels";
+
+/// A fresh path in the temporary directory, unique to this process and `name`.
+fn temp_target(name: &str) -> PathBuf {
+    let dir = std::env::temp_dir().join(format!("cdsapi-scheduler-test-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    let path = dir.join(name);
+    let _ = std::fs::remove_file(&path);
+    path
+}
+
+fn item(name: &str) -> BatchItem {
+    BatchItem::new(DATASET, &json!({"name": [name]}), temp_target(name)).unwrap()
+}
+
+#[test]
+fn starts_entries_by_priority_then_enqueue_order() {
+    let server = MockServer::start();
+    let scheduler = Scheduler::new(server.client().unwrap(), 1);
+
+    // The first entry has the highest priority, so it starts first whether or not the
+    // worker picks it up before the others are enqueued.
+    let options = |priority| EnqueueOptions::new().priority(priority);
+    scheduler.enqueue_with(item("first"), &options(10)).unwrap();
+    scheduler.enqueue_with(item("low"), &options(-1)).unwrap();
+    scheduler.enqueue_with(item("high-a"), &options(5)).unwrap();
+    scheduler.enqueue(item("default"));
+    scheduler.enqueue_with(item("high-b"), &options(5)).unwrap();
+
+    let results = scheduler.join();
+    assert!(results.iter().all(|r| r.result.is_ok()));
+    let submitted: Vec<String> = server
+        .requests()
+        .iter()
+        .filter(|r| r.method == "POST")
+        .map(|r| {
+            let body: serde_json::Value = serde_json::from_str(&r.body).unwrap();
+            body["inputs"]["name"][0].as_str().unwrap().to_string()
+        })
+        .collect();
+    assert_eq!(submitted, ["first", "high-a", "high-b", "default", "low"]);
+}
+
+#[test]
+fn runs_entries_after_their_dependencies() {
+    let server = MockServer::start();
+    let scheduler = Scheduler::new(server.client().unwrap(), 4);
+
+    let first = scheduler.enqueue(item("dep-first"));
+    let options = EnqueueOptions::new().priority(100).after(first);
+    let second = scheduler
+        .enqueue_with(item("dep-second"), &options)
+        .unwrap();
+    assert!(
+        scheduler
+            .enqueue_with(item("dep-bad"), &EnqueueOptions::new().after(9))
+            .is_err()
+    );
+
+    let results = scheduler.join();
+    assert!(results.iter().all(|r| r.result.is_ok()));
+    let posts: Vec<usize> = server
+        .requests()
+        .iter()
+        .enumerate()
+        .filter(|(_, r)| r.method == "POST")
+        .map(|(i, _)| i)
+        .collect();
+    assert_eq!(posts.len(), 2);
+    // The second submission only happens once the first result was downloaded.
+    let first_download = server
+        .requests()
+        .iter()
+        .position(|r| r.path == "/downloads/job-1")
+        .unwrap();
+    assert!(first_download < posts[1]);
+    assert_eq!(results[second].index, second);
+}
+
+#[test]
+fn join_returns_when_a_dependency_fails() {
+    let server = MockServer::start().with_states(&["accepted", "failed"]);
+    let scheduler = Scheduler::new(server.client().unwrap(), 2)
+        .with_max_attempts(1)
+        .with_retry_delay(Duration::from_millis(10));
+
+    let a = scheduler.enqueue(item("fail-a"));
+    let b = scheduler
+        .enqueue_with(item("fail-b"), &EnqueueOptions::new().after(a))
+        .unwrap();
+    let c = scheduler
+        .enqueue_with(item("fail-c"), &EnqueueOptions::new().after(b))
+        .unwrap();
+
+    let results = scheduler.join();
+    assert!(matches!(results[a].result, Err(CdsError::JobFailed { .. })));
+    for index in [b, c] {
+        assert!(matches!(
+            results[index].result,
+            Err(CdsError::Cancelled { job_id: None })
+        ));
+    }
+    let posts = server
+        .requests()
+        .iter()
+        .filter(|r| r.method == "POST")
+        .count();
+    assert_eq!(posts, 1);
+}
+
+#[test]
+fn entries_added_after_a_failure_are_cancelled() {
+    let server = MockServer::start().with_states(&["failed"]);
+    let scheduler = Scheduler::new(server.client().unwrap(), 1).with_max_attempts(1);
+
+    let a = scheduler.enqueue(item("late-a"));
+    while !scheduler.snapshot().entries[a].state.is_finished() {
+        std::thread::sleep(Duration::from_millis(10));
+    }
+    let b = scheduler
+        .enqueue_with(item("late-b"), &EnqueueOptions::new().after(a))
+        .unwrap();
+    assert!(matches!(
+        scheduler.snapshot().entries[b].state,
+        EntryState::Failed { .. }
+    ));
+
+    let results = scheduler.join();
+    assert!(matches!(results[b].result, Err(CdsError::Cancelled { .. })));
+}